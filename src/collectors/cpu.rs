@@ -0,0 +1,311 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::{read_file, Collector, CollectorError, Roots};
+use crate::{CollectedData, MAX_POINTS};
+
+/// Cumulative jiffies of one `cpuN` line of `/proc/stat`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CpuTimes {
+    /// The N of `cpuN`, which skips offline cores.
+    pub id: usize,
+    pub user: u64,
+    pub nice: u64,
+    pub system: u64,
+    pub idle: u64,
+    pub iowait: u64,
+    pub irq: u64,
+    pub softirq: u64,
+    pub steal: u64,
+    pub guest: u64,
+    pub guest_nice: u64,
+}
+
+/// Share of time (in percent) a core spent in each state between two samples.
+///
+/// `total` is everything but idle, so the other fields add up to it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CpuSample {
+    /// The N of the `cpuN` it was taken from.
+    #[serde(default)]
+    pub id: usize,
+    pub total: f64,
+    pub user: f64,
+    pub nice: f64,
+    pub system: f64,
+    pub iowait: f64,
+    pub irq: f64,
+    pub softirq: f64,
+    pub steal: f64,
+    pub guest: f64,
+    /// Current clock in MHz, without cpufreq (most VMs) None.
+    #[serde(default)]
+    pub frequency: Option<f64>,
+}
+
+impl CpuSample {
+    /// The breakdown in stacking order, bottom first.
+    pub fn parts(&self) -> [(&'static str, f64); 8] {
+        [
+            ("user", self.user),
+            ("nice", self.nice),
+            ("system", self.system),
+            ("irq", self.irq),
+            ("softirq", self.softirq),
+            ("guest", self.guest),
+            ("iowait", self.iowait),
+            ("steal", self.steal),
+        ]
+    }
+}
+
+impl CpuTimes {
+    // The kernel already accounts guest time inside user and nice.
+    fn total(&self) -> u64 {
+        self.user + self.nice + self.system + self.idle + self.iowait
+            + self.irq + self.softirq + self.steal
+    }
+
+    pub fn sample_since(&self, previous: &CpuTimes) -> CpuSample {
+        let total = self.total().saturating_sub(previous.total());
+        if total == 0 {
+            return CpuSample { id: self.id, ..Default::default() };
+        }
+        let share = |now: u64, before: u64| now.saturating_sub(before) as f64 * 100.0 / total as f64;
+
+        let guest = share(self.guest, previous.guest);
+        let guest_nice = share(self.guest_nice, previous.guest_nice);
+        let sample = CpuSample {
+            id: self.id,
+            total: 0.0,
+            user: (share(self.user, previous.user) - guest).max(0.0),
+            nice: (share(self.nice, previous.nice) - guest_nice).max(0.0),
+            system: share(self.system, previous.system),
+            iowait: share(self.iowait, previous.iowait),
+            irq: share(self.irq, previous.irq),
+            softirq: share(self.softirq, previous.softirq),
+            steal: share(self.steal, previous.steal),
+            guest: guest + guest_nice,
+            frequency: None,
+        };
+        CpuSample {
+            total: sample.parts().iter().map(|(_, v)| v).sum::<f64>().min(100.0),
+            ..sample
+        }
+    }
+}
+
+/// Parses the per-core `cpuN` lines of `/proc/stat`, skipping the aggregate
+/// `cpu` line.
+pub fn parse_stat(content: &str) -> Vec<CpuTimes> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let id = fields.next()?.strip_prefix("cpu")?.parse().ok()?;
+            let mut values = fields.map(|v| v.parse::<u64>().unwrap_or(0));
+            let mut next = || values.next().unwrap_or(0);
+            Some(CpuTimes {
+                id,
+                user: next(),
+                nice: next(),
+                system: next(),
+                idle: next(),
+                iowait: next(),
+                irq: next(),
+                softirq: next(),
+                steal: next(),
+                guest: next(),
+                guest_nice: next(),
+            })
+        })
+        .collect()
+}
+
+/// The range cpufreq may pick a core's clock from, in MHz, and how.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CpuFrequencyPolicy {
+    pub min: f64,
+    pub max: f64,
+    /// e.g. `performance`, `powersave`, `schedutil`.
+    pub governor: String,
+}
+
+/// Reads `/sys/devices/system/cpu/cpuN/cpufreq`: the current clock and the
+/// policy, both None without cpufreq.
+pub fn read_frequency(roots: &Roots, id: usize) -> (Option<f64>, Option<CpuFrequencyPolicy>) {
+    let dir = roots.sys(format!("devices/system/cpu/cpu{}/cpufreq", id));
+    let read = |file: &str| fs::read_to_string(dir.join(file)).ok().map(|value| value.trim().to_string());
+    // In kHz. The scaling limits are the ones the governor keeps to, the
+    // cpuinfo ones what the hardware can do.
+    let mhz = |scaling: &str, cpuinfo: &str| {
+        read(scaling).or_else(|| read(cpuinfo)).and_then(|khz| khz.parse::<f64>().ok()).map(|khz| khz / 1000.0)
+    };
+    let current = mhz("scaling_cur_freq", "cpuinfo_cur_freq");
+    let policy = match (mhz("scaling_min_freq", "cpuinfo_min_freq"), mhz("scaling_max_freq", "cpuinfo_max_freq")) {
+        (Some(min), Some(max)) => Some(CpuFrequencyPolicy { min, max, governor: read("scaling_governor").unwrap_or_default() }),
+        _ => None,
+    };
+    (current, policy)
+}
+
+/// Diffs the jiffies counters of `/proc/stat` between calls.
+pub struct CpuCollector {
+    roots: Roots,
+    /// By core id, as hotplug can add or remove cores between calls.
+    previous: HashMap<usize, CpuTimes>,
+}
+
+impl CpuCollector {
+    pub fn new(roots: Roots) -> Self {
+        Self { roots, previous: HashMap::new() }
+    }
+
+    /// Samples the cores that were also in the previous reading.
+    fn sample(&mut self, times: Vec<CpuTimes>) -> Vec<CpuSample> {
+        let samples = times
+            .iter()
+            .filter_map(|now| Some(now.sample_since(self.previous.get(&now.id)?)))
+            .collect();
+        self.previous = times.into_iter().map(|core| (core.id, core)).collect();
+        samples
+    }
+}
+
+impl Collector for CpuCollector {
+    fn name(&self) -> &'static str {
+        "cpu"
+    }
+
+    fn default_interval(&self) -> Duration {
+        Duration::from_secs(2)
+    }
+
+    fn collect(&mut self, shared_data: &Arc<Mutex<CollectedData>>) -> Result<(), CollectorError> {
+        let path = self.roots.proc("stat");
+        let times = parse_stat(&read_file(&path)?);
+        if times.is_empty() {
+            return Err(CollectorError::parse(path, "no per-core cpu lines"));
+        }
+        let mut samples = self.sample(times);
+        // In the order of the samples, which push_samples keeps the
+        // histories in.
+        let policies: Vec<_> = samples
+            .iter_mut()
+            .map(|sample| {
+                let (current, policy) = read_frequency(&self.roots, sample.id);
+                sample.frequency = current;
+                policy
+            })
+            .collect();
+
+        let mut data = shared_data.lock().unwrap_or_else(PoisonError::into_inner);
+        if !samples.is_empty() {
+            data.cpu_policies = policies;
+        }
+        push_samples(&mut data.cpu_usage, samples);
+        data.updated_cpu = true;
+        Ok(())
+    }
+
+    fn resume(&mut self) {
+        self.previous.clear();
+    }
+}
+
+/// Adds each sample to the history of the core with its id, in the order of
+/// the samples, and drops the histories of cores that went away. A pass
+/// without samples, e.g. the first one after a pause, keeps them all.
+pub fn push_samples(history: &mut Vec<VecDeque<CpuSample>>, samples: Vec<CpuSample>) {
+    if samples.is_empty() {
+        return;
+    }
+    let mut known = std::mem::take(history);
+    for sample in samples {
+        let mut core = known
+            .iter()
+            .position(|core| core.front().is_some_and(|newest| newest.id == sample.id))
+            .map(|i| known.remove(i))
+            .unwrap_or_default();
+        core.push_front(sample);
+        core.truncate(MAX_POINTS);
+        history.push(core);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collectors::fixtures;
+
+    #[test]
+    fn parses_per_core_lines_only() {
+        let times = parse_stat(&fixtures::read("cpu/stat.1"));
+        assert_eq!(
+            times,
+            vec![CpuTimes {
+                user: 36091,
+                system: 12406,
+                idle: 117162,
+                iowait: 6597,
+                softirq: 7,
+                steal: 838,
+                ..Default::default()
+            }]
+        );
+    }
+
+    #[test]
+    fn samples_the_difference_between_readings() {
+        let before = parse_stat(&fixtures::read("cpu/stat.1"));
+        let after = parse_stat(&fixtures::read("cpu/stat.2"));
+        // 2 user and 98 idle jiffies elapsed.
+        let sample = after[0].sample_since(&before[0]);
+        assert_eq!(sample.id, after[0].id);
+        assert_eq!(sample.user, 2.0);
+        assert_eq!(sample.system, 0.0);
+        assert_eq!(sample.total, 2.0);
+    }
+
+    #[test]
+    fn collector_keeps_one_history_per_core() {
+        let shared_data = Arc::new(Mutex::new(CollectedData::default()));
+        let mut collector = CpuCollector::new(fixtures::host_roots());
+        collector.collect(&shared_data).unwrap();
+        collector.collect(&shared_data).unwrap();
+
+        let data = shared_data.lock().unwrap();
+        assert!(data.updated_cpu);
+        assert_eq!(data.cpu_usage.len(), 1);
+        assert_eq!(data.cpu_usage[0].len(), 1);
+        assert_eq!(data.cpu_usage[0][0].frequency, Some(2400.0));
+        assert_eq!(
+            data.cpu_policies,
+            [Some(CpuFrequencyPolicy { min: 800.0, max: 3600.0, governor: String::from("powersave") })]
+        );
+    }
+
+    #[test]
+    fn matches_cores_by_id_when_one_goes_offline() {
+        let core = |id, user, idle| CpuTimes { id, user, idle, ..Default::default() };
+        let mut collector = CpuCollector::new(Roots::default());
+        let mut history = vec![];
+        assert!(collector.sample(vec![core(0, 0, 0), core(1, 0, 0), core(2, 0, 0)]).is_empty());
+
+        let samples = collector.sample(vec![core(0, 0, 100), core(1, 0, 100), core(2, 100, 100)]);
+        push_samples(&mut history, samples);
+        // cpu1 went offline; cpu2 must not be diffed against it.
+        let samples = collector.sample(vec![core(0, 100, 200), core(2, 100, 200)]);
+        assert_eq!(samples.iter().map(|s| (s.id, s.total)).collect::<Vec<_>>(), [(0, 50.0), (2, 0.0)]);
+        push_samples(&mut history, samples);
+
+        assert_eq!(history.len(), 2);
+        let totals: Vec<Vec<f64>> = history.iter().map(|core| core.iter().map(|s| s.total).collect()).collect();
+        assert_eq!(totals, [vec![50.0, 0.0], vec![0.0, 50.0]]);
+        assert_eq!(history[1][0].id, 2);
+    }
+}
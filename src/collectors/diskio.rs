@@ -0,0 +1,260 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::process::read_uptime;
+use super::{merge_samples, read_file, Collector, CollectorError, Roots, Sampled};
+use crate::CollectedData;
+
+/// `/proc/diskstats` counts in 512-byte sectors whatever the device uses.
+const SECTOR_SIZE: f64 = 512.0;
+
+/// The counters of one line of `/proc/diskstats`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiskStatCounters {
+    pub reads: u64,
+    pub read_sectors: u64,
+    pub read_ms: u64,
+    pub writes: u64,
+    pub write_sectors: u64,
+    pub write_ms: u64,
+    pub in_flight: u64,
+    /// Time with at least one request in flight.
+    pub io_ms: u64,
+    /// `io_ms` weighted by the number of requests in flight.
+    pub weighted_ms: u64,
+}
+
+/// What iostat -x shows, between two samples.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DiskIoRates {
+    /// Bytes per second.
+    pub read_bytes: f64,
+    pub write_bytes: f64,
+    /// Requests completed per second.
+    pub reads: f64,
+    pub writes: f64,
+    /// Average milliseconds a request took, queueing included.
+    pub await_ms: f64,
+    /// Average number of requests in flight.
+    pub queue: f64,
+    /// Percentage of the time the device was busy.
+    pub utilization: f64,
+}
+
+impl DiskStatCounters {
+    fn rates_since(&self, before: &DiskStatCounters, seconds: f64) -> DiskIoRates {
+        let delta = |now: u64, before: u64| now.saturating_sub(before) as f64;
+        let requests = delta(self.reads, before.reads) + delta(self.writes, before.writes);
+        let request_ms = delta(self.read_ms, before.read_ms) + delta(self.write_ms, before.write_ms);
+        DiskIoRates {
+            read_bytes: delta(self.read_sectors, before.read_sectors) * SECTOR_SIZE / seconds,
+            write_bytes: delta(self.write_sectors, before.write_sectors) * SECTOR_SIZE / seconds,
+            reads: delta(self.reads, before.reads) / seconds,
+            writes: delta(self.writes, before.writes) / seconds,
+            await_ms: if requests > 0.0 { request_ms / requests } else { 0.0 },
+            queue: delta(self.weighted_ms, before.weighted_ms) / (seconds * 1000.0),
+            utilization: (delta(self.io_ms, before.io_ms) / (seconds * 10.0)).min(100.0),
+        }
+    }
+}
+
+/// A disk or one of its partitions, as the Disk I/O pane shows it.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockDevice {
+    pub name: String,
+    /// The disk a partition belongs to, None for whole disks.
+    pub disk: Option<String>,
+    pub counters: DiskStatCounters,
+    /// None on the first sample of a device.
+    pub rates: Option<DiskIoRates>,
+    pub sampled_at: f64,
+    #[serde(skip)]
+    pub history: VecDeque<DiskIoRates>,
+}
+
+impl Sampled for BlockDevice {
+    type Value = DiskIoRates;
+
+    fn key(&self) -> &str {
+        &self.name
+    }
+
+    fn sampled_at(&self) -> f64 {
+        self.sampled_at
+    }
+
+    fn value(&self) -> Option<DiskIoRates> {
+        self.rates
+    }
+
+    fn history(&mut self) -> &mut VecDeque<DiskIoRates> {
+        &mut self.history
+    }
+}
+
+/// Parses `/proc/diskstats`, keeping the order of the devices.
+pub fn parse_diskstats(content: &str) -> Vec<(String, DiskStatCounters)> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let name = fields.get(2)?;
+            let value = |n: usize| fields.get(n + 3)?.parse().ok();
+            let counters = DiskStatCounters {
+                reads: value(0)?,
+                read_sectors: value(2)?,
+                read_ms: value(3)?,
+                writes: value(4)?,
+                write_sectors: value(6)?,
+                write_ms: value(7)?,
+                in_flight: value(8)?,
+                io_ms: value(9)?,
+                weighted_ms: value(10)?,
+            };
+            Some((name.to_string(), counters))
+        })
+        .collect()
+}
+
+/// Maps every disk under `/sys/block` to itself and each of its partitions
+/// to it. Loop and RAM devices are left out, as in the device list.
+fn block_hierarchy(roots: &Roots) -> HashMap<String, Option<String>> {
+    let mut hierarchy = HashMap::new();
+    let disks = fs::read_dir(roots.sys("block")).into_iter().flatten().flatten();
+    for disk in disks {
+        let name = disk.file_name().to_string_lossy().into_owned();
+        if name.starts_with("loop") || name.starts_with("ram") {
+            continue;
+        }
+        // Partitions are the subdirectories with a `partition` file.
+        let partitions = fs::read_dir(disk.path()).into_iter().flatten().flatten();
+        for partition in partitions.filter(|entry| entry.path().join("partition").exists()) {
+            hierarchy.insert(partition.file_name().to_string_lossy().into_owned(), Some(name.clone()));
+        }
+        hierarchy.insert(name, None);
+    }
+    hierarchy
+}
+
+/// Throughput, IOPS, latency, queue depth and utilization of the block
+/// devices from `/proc/diskstats`, each disk followed by its partitions.
+pub struct DiskIoCollector {
+    roots: Roots,
+    previous: HashMap<String, (DiskStatCounters, f64)>,
+}
+
+impl DiskIoCollector {
+    pub fn new(roots: Roots) -> Self {
+        Self { roots, previous: HashMap::new() }
+    }
+
+    fn sample(&mut self, stats: Vec<(String, DiskStatCounters)>, uptime: f64) -> Vec<BlockDevice> {
+        let hierarchy = block_hierarchy(&self.roots);
+        let mut devices: Vec<BlockDevice> = stats
+            .into_iter()
+            .filter_map(|(name, counters)| {
+                let disk = hierarchy.get(&name)?.clone();
+                let rates = match self.previous.get(&name) {
+                    Some((before, at)) if uptime > *at => Some(counters.rates_since(before, uptime - at)),
+                    _ => None,
+                };
+                Some(BlockDevice { name, disk, counters, rates, sampled_at: uptime, history: VecDeque::new() })
+            })
+            .collect();
+        devices.sort_by(|a, b| {
+            let group = |device: &BlockDevice| (device.disk.clone().unwrap_or_else(|| device.name.clone()), device.disk.is_some());
+            group(a).cmp(&group(b))
+        });
+        self.previous = devices.iter().map(|d| (d.name.clone(), (d.counters, uptime))).collect();
+        devices
+    }
+}
+
+impl Collector for DiskIoCollector {
+    fn name(&self) -> &'static str {
+        "diskio"
+    }
+
+    fn default_interval(&self) -> Duration {
+        Duration::from_secs(2)
+    }
+
+    fn collect(&mut self, shared_data: &Arc<Mutex<CollectedData>>) -> Result<(), CollectorError> {
+        let path = self.roots.proc("diskstats");
+        let stats = parse_diskstats(&read_file(&path)?);
+        let uptime = read_uptime(&self.roots)?;
+        let samples = self.sample(stats, uptime);
+
+        let mut data = shared_data.lock().unwrap_or_else(PoisonError::into_inner);
+        merge_samples(&mut data.block_devices, samples);
+        data.updated_diskio = true;
+        Ok(())
+    }
+
+    fn resume(&mut self) {
+        self.previous.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collectors::fixtures;
+
+    #[test]
+    fn parses_recorded_diskstats() {
+        let stats = parse_diskstats(&fixtures::read("host/proc/diskstats"));
+        let names: Vec<&str> = stats.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["loop0", "vda", "vda1", "vda15"]);
+        assert_eq!(
+            stats[1].1,
+            DiskStatCounters {
+                reads: 11540,
+                read_sectors: 1009364,
+                read_ms: 5631,
+                writes: 25713,
+                write_sectors: 1523960,
+                write_ms: 39384,
+                in_flight: 0,
+                io_ms: 48196,
+                weighted_ms: 47284,
+            }
+        );
+    }
+
+    #[test]
+    fn rates_follow_iostat() {
+        let before = DiskStatCounters::default();
+        let now = DiskStatCounters {
+            reads: 30,
+            read_sectors: 2048,
+            read_ms: 90,
+            writes: 10,
+            write_ms: 110,
+            io_ms: 500,
+            weighted_ms: 3000,
+            ..Default::default()
+        };
+        let rates = now.rates_since(&before, 2.0);
+        assert_eq!((rates.read_bytes, rates.reads, rates.writes), (524288.0, 15.0, 5.0));
+        assert_eq!((rates.await_ms, rates.queue, rates.utilization), (5.0, 1.5, 25.0));
+    }
+
+    #[test]
+    fn collector_groups_partitions_under_their_disk() {
+        let shared_data = Arc::new(Mutex::new(CollectedData::default()));
+        DiskIoCollector::new(fixtures::host_roots()).collect(&shared_data).unwrap();
+        let data = shared_data.lock().unwrap();
+        let layout: Vec<(&str, Option<&str>)> =
+            data.block_devices.iter().map(|d| (d.name.as_str(), d.disk.as_deref())).collect();
+        // loop0 isn't listed under /sys/block.
+        assert_eq!(layout, [("vda", None), ("vda1", Some("vda")), ("vda15", Some("vda"))]);
+        // Rates need a second sample.
+        assert!(data.block_devices.iter().all(|device| device.rates.is_none() && device.history.is_empty()));
+        assert!(data.updated_diskio);
+    }
+}
@@ -0,0 +1,283 @@
+use std::collections::HashSet;
+use std::ffi::CString;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::{read_file, Collector, CollectorError, Roots};
+use crate::CollectedData;
+
+/// One line of `/proc/self/mountinfo`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MountInfo {
+    pub major: u32,
+    pub minor: u32,
+    pub mount_point: String,
+    pub fs_type: String,
+    pub source: String,
+}
+
+/// Undoes the octal escaping (`\040` for a space, ...) used in mountinfo.
+fn unescape(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let octal = bytes.get(i + 1..i + 4).filter(|digits| digits.iter().all(|d| (b'0'..=b'7').contains(d)));
+        match octal {
+            Some(digits) if bytes[i] == b'\\' => {
+                out.push(digits.iter().fold(0u8, |acc, d| acc.wrapping_mul(8) + (d - b'0')));
+                i += 4;
+            }
+            _ => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+pub fn parse_mountinfo(content: &str) -> Vec<MountInfo> {
+    content
+        .lines()
+        .filter_map(|line| {
+            // The optional fields end at a lone "-", after which come the
+            // filesystem type and the mount source.
+            let (left, right) = line.split_once(" - ")?;
+            let left: Vec<&str> = left.split(' ').collect();
+            let mut right = right.split(' ');
+            let (major, minor) = left.get(2)?.split_once(':')?;
+            Some(MountInfo {
+                major: major.parse().ok()?,
+                minor: minor.parse().ok()?,
+                mount_point: unescape(left.get(4)?),
+                fs_type: right.next()?.to_string(),
+                source: unescape(right.next()?),
+            })
+        })
+        .collect()
+}
+
+/// Decides which mounts show up in the Partitions pane.
+///
+/// A mount is hidden when its filesystem type is excluded or its mount point
+/// is (or lies below) an excluded path. The include lists take precedence over
+/// the exclude lists, so `tmpfs` can be brought back without clearing the
+/// defaults.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionFilter {
+    pub include_fs_types: Vec<String>,
+    pub exclude_fs_types: Vec<String>,
+    pub include_mount_points: Vec<String>,
+    pub exclude_mount_points: Vec<String>,
+}
+
+impl Default for PartitionFilter {
+    fn default() -> Self {
+        let exclude_fs_types = [
+            "autofs", "binfmt_misc", "bpf", "cgroup", "cgroup2", "configfs",
+            "debugfs", "devpts", "devtmpfs", "efivarfs", "fusectl", "hugetlbfs",
+            "mqueue", "nsfs", "overlay", "proc", "pstore", "ramfs", "rpc_pipefs",
+            "securityfs", "squashfs", "sysfs", "tmpfs", "tracefs",
+        ];
+        Self {
+            include_fs_types: vec![],
+            exclude_fs_types: exclude_fs_types.iter().map(|s| s.to_string()).collect(),
+            include_mount_points: vec![],
+            exclude_mount_points: vec![String::from("/proc"), String::from("/sys")],
+        }
+    }
+}
+
+fn is_under(path: &str, prefix: &str) -> bool {
+    let prefix = prefix.trim_end_matches('/');
+    prefix.is_empty()
+        || path == prefix
+        || path.strip_prefix(prefix).is_some_and(|rest| rest.starts_with('/'))
+}
+
+impl PartitionFilter {
+    pub fn accepts(&self, mount: &MountInfo) -> bool {
+        let fs_type = &mount.fs_type;
+        let mount_point = mount.mount_point.as_str();
+        if self.include_fs_types.contains(fs_type)
+            || self.include_mount_points.iter().any(|p| is_under(mount_point, p))
+        {
+            return true;
+        }
+        !self.exclude_fs_types.contains(fs_type)
+            && !self.exclude_mount_points.iter().any(|p| is_under(mount_point, p))
+    }
+}
+
+/// Space and inode usage of a mounted filesystem, in bytes.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartitionUsage {
+    pub device: String,
+    pub mount_point: String,
+    pub fs_type: String,
+    pub total: u64,
+    pub free: u64,
+    pub available: u64,
+    pub inodes_total: u64,
+    pub inodes_free: u64,
+}
+
+impl PartitionUsage {
+    pub fn used(&self) -> u64 {
+        self.total.saturating_sub(self.free)
+    }
+
+    /// Used space as df computes it, against what non-root users can reach.
+    pub fn used_percent(&self) -> f64 {
+        let usable = self.used() + self.available;
+        if usable == 0 {
+            0.0
+        } else {
+            self.used() as f64 * 100.0 / usable as f64
+        }
+    }
+
+    pub fn inodes_used(&self) -> u64 {
+        self.inodes_total.saturating_sub(self.inodes_free)
+    }
+}
+
+fn statvfs(path: &Path) -> io::Result<libc::statvfs> {
+    let path = CString::new(path.as_os_str().as_bytes()).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } == 0 {
+        Ok(stat)
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Lists the mounts from `/proc/self/mountinfo` and sizes them with statvfs.
+///
+/// With a foreign procfs the mounts of the host's init are listed instead,
+/// and sized through `/proc/1/root`.
+pub struct DiskCollector {
+    roots: Roots,
+    filter: PartitionFilter,
+}
+
+impl DiskCollector {
+    pub fn new(roots: Roots, filter: PartitionFilter) -> Self {
+        Self { roots, filter }
+    }
+
+    fn sample(&self, mounts: Vec<MountInfo>) -> Vec<PartitionUsage> {
+        // Bind mounts show the same device more than once; keep the first.
+        let mut seen = HashSet::new();
+        mounts
+            .into_iter()
+            .filter(|mount| self.filter.accepts(mount))
+            .filter(|mount| seen.insert((mount.major, mount.minor)))
+            .filter_map(|mount| {
+                let stat = statvfs(&self.roots.host_path(&mount.mount_point)).ok()?;
+                let block = stat.f_frsize as u64;
+                let usage = PartitionUsage {
+                    device: mount.source,
+                    mount_point: mount.mount_point,
+                    fs_type: mount.fs_type,
+                    total: stat.f_blocks as u64 * block,
+                    free: stat.f_bfree as u64 * block,
+                    available: stat.f_bavail as u64 * block,
+                    inodes_total: stat.f_files as u64,
+                    inodes_free: stat.f_ffree as u64,
+                };
+                (usage.total > 0).then_some(usage)
+            })
+            .collect()
+    }
+}
+
+impl Collector for DiskCollector {
+    fn name(&self) -> &'static str {
+        "disks"
+    }
+
+    fn default_interval(&self) -> Duration {
+        Duration::from_secs(2)
+    }
+
+    fn collect(&mut self, shared_data: &Arc<Mutex<CollectedData>>) -> Result<(), CollectorError> {
+        let path = if self.roots.is_foreign() {
+            self.roots.proc("1/mountinfo")
+        } else {
+            self.roots.proc("self/mountinfo")
+        };
+        let mounts = parse_mountinfo(&read_file(&path)?);
+        if mounts.is_empty() {
+            return Err(CollectorError::parse(path, "no mounts listed"));
+        }
+        let partitions = self.sample(mounts);
+
+        let mut data = shared_data.lock().unwrap_or_else(PoisonError::into_inner);
+        data.partitions = partitions;
+        data.updated_disks = true;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collectors::fixtures;
+
+    #[test]
+    fn unescapes_octal_sequences() {
+        assert_eq!(unescape(r"/mnt/my\040disk"), "/mnt/my disk");
+        assert_eq!(unescape(r"/a\\b\12"), r"/a\\b\12");
+    }
+
+    #[test]
+    fn parses_recorded_mountinfo() {
+        let mounts = parse_mountinfo(&fixtures::read("host/proc/1/mountinfo"));
+        assert_eq!(mounts.len(), 19);
+        let root = mounts.iter().find(|m| m.mount_point == "/").unwrap();
+        assert_eq!(
+            root,
+            &MountInfo {
+                major: 254,
+                minor: 0,
+                mount_point: String::from("/"),
+                fs_type: String::from("ext4"),
+                source: String::from("/dev/vda"),
+            }
+        );
+    }
+
+    #[test]
+    fn default_filter_hides_pseudo_filesystems() {
+        let filter = PartitionFilter::default();
+        let shown: Vec<_> = parse_mountinfo(&fixtures::read("host/proc/1/mountinfo"))
+            .into_iter()
+            .filter(|m| filter.accepts(m))
+            .map(|m| m.mount_point)
+            .collect();
+        assert_eq!(shown, vec!["/"]);
+
+        let filter = PartitionFilter { include_fs_types: vec![String::from("tmpfs")], ..filter };
+        let mounts = parse_mountinfo(&fixtures::read("host/proc/1/mountinfo"));
+        assert_eq!(mounts.iter().filter(|m| filter.accepts(m)).count(), 4);
+    }
+
+    #[test]
+    fn foreign_root_sizes_mounts_through_init() {
+        let shared_data = Arc::new(Mutex::new(CollectedData::default()));
+        DiskCollector::new(fixtures::host_roots(), PartitionFilter::default())
+            .collect(&shared_data)
+            .unwrap();
+        let data = shared_data.lock().unwrap();
+        assert_eq!(data.partitions.len(), 1);
+        assert_eq!(data.partitions[0].device, "/dev/vda");
+        assert!(data.partitions[0].total > 0);
+    }
+}
@@ -0,0 +1,248 @@
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+
+use super::Roots;
+
+/// A "Memory Device" (SMBIOS type 17) entry, i.e. one DIMM slot.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryDevice {
+    pub locator: String,
+    pub bank_locator: String,
+    /// Module size in bytes, `None` for an empty slot.
+    pub size: Option<u64>,
+    pub memory_type: String,
+    /// Maximum speed in MT/s.
+    pub speed: Option<u32>,
+    /// Speed the module is currently configured for, in MT/s.
+    pub configured_speed: Option<u32>,
+    pub manufacturer: String,
+    pub part_number: String,
+}
+
+const MEMORY_DEVICE: u8 = 17;
+const END_OF_TABLE: u8 = 127;
+
+fn memory_type_name(code: u8) -> &'static str {
+    match code {
+        0x01 => "Other",
+        0x03 => "DRAM",
+        0x04 => "EDRAM",
+        0x05 => "VRAM",
+        0x06 => "SRAM",
+        0x07 => "RAM",
+        0x08 => "ROM",
+        0x09 => "Flash",
+        0x0A => "EEPROM",
+        0x0B => "FEPROM",
+        0x0C => "EPROM",
+        0x0D => "CDRAM",
+        0x0E => "3DRAM",
+        0x0F => "SDRAM",
+        0x10 => "SGRAM",
+        0x11 => "RDRAM",
+        0x12 => "DDR",
+        0x13 => "DDR2",
+        0x14 => "DDR2 FB-DIMM",
+        0x18 => "DDR3",
+        0x19 => "FBD2",
+        0x1A => "DDR4",
+        0x1B => "LPDDR",
+        0x1C => "LPDDR2",
+        0x1D => "LPDDR3",
+        0x1E => "LPDDR4",
+        0x1F => "Logical non-volatile device",
+        0x20 => "HBM",
+        0x21 => "HBM2",
+        0x22 => "DDR5",
+        0x23 => "LPDDR5",
+        0x24 => "HBM3",
+        _ => "unknown",
+    }
+}
+
+/// Splits one SMBIOS structure off the front of `raw`, returning its
+/// formatted area, its string set and the remaining bytes.
+fn split_structure(raw: &[u8]) -> Option<(&[u8], Vec<String>, &[u8])> {
+    let length = *raw.get(1)? as usize;
+    if length < 4 {
+        return None;
+    }
+    let formatted = raw.get(..length)?;
+    let rest = &raw[length..];
+    // The string set is terminated by two NULs (just two NULs when empty).
+    let end = rest.windows(2).position(|w| w == [0, 0])?;
+    let strings = rest[..end]
+        .split(|&b| b == 0)
+        .filter(|s| !s.is_empty())
+        .map(|s| String::from_utf8_lossy(s).trim().to_string())
+        .collect();
+    Some((formatted, strings, &rest[end + 2..]))
+}
+
+fn parse_memory_device(formatted: &[u8], strings: &[String]) -> MemoryDevice {
+    let byte = |offset: usize| formatted.get(offset).copied();
+    let word = |offset: usize| formatted.get(offset..offset + 2).map(|b| u16::from_le_bytes([b[0], b[1]]));
+    let dword = |offset: usize| {
+        formatted
+            .get(offset..offset + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    };
+    // String references are 1-based, 0 meaning "no string".
+    let string = |offset: usize| {
+        byte(offset)
+            .filter(|&index| index > 0)
+            .and_then(|index| strings.get(index as usize - 1))
+            .cloned()
+            .unwrap_or_default()
+    };
+    let speed = |offset: usize, extended: usize| match word(offset) {
+        Some(0) | None => None,
+        Some(0xFFFF) => dword(extended).filter(|&s| s > 0),
+        Some(speed) => Some(speed as u32),
+    };
+
+    let size = match word(0x0C) {
+        Some(0) | Some(0xFFFF) | None => None,
+        Some(0x7FFF) => dword(0x1C).map(|mb| (mb & 0x7FFF_FFFF) as u64 * 1024 * 1024),
+        Some(size) if size & 0x8000 != 0 => Some((size & 0x7FFF) as u64 * 1024),
+        Some(size) => Some(size as u64 * 1024 * 1024),
+    };
+
+    MemoryDevice {
+        locator: string(0x10),
+        bank_locator: string(0x11),
+        size,
+        memory_type: byte(0x12).map(memory_type_name).unwrap_or("unknown").to_string(),
+        speed: speed(0x15, 0x54),
+        configured_speed: speed(0x20, 0x58),
+        manufacturer: string(0x17),
+        part_number: string(0x1A),
+    }
+}
+
+/// Parses the type 17 structures of a raw SMBIOS table, such as
+/// `/sys/firmware/dmi/tables/DMI` or a single `entries/17-*/raw` file.
+pub fn parse_table(mut raw: &[u8]) -> Vec<MemoryDevice> {
+    let mut devices = vec![];
+    while let Some((formatted, strings, rest)) = split_structure(raw) {
+        match formatted[0] {
+            MEMORY_DEVICE => devices.push(parse_memory_device(formatted, &strings)),
+            END_OF_TABLE => break,
+            _ => {}
+        }
+        raw = rest;
+    }
+    devices
+}
+
+/// Reads the DIMM layout from the per-entry files when readable, falling back
+/// to the whole table. Both are usually root only, in which case this is empty.
+pub fn read_memory_devices(roots: &Roots) -> Vec<MemoryDevice> {
+    let from_entries = fs::read_dir(roots.sys("firmware/dmi/entries")).ok().map(|entries| {
+        let mut paths: Vec<_> = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with("17-"))
+            .map(|entry| entry.path())
+            .collect();
+        // entries are named 17-0, 17-1, ..., 17-10; keep the slot order.
+        paths.sort_by_key(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str()?.strip_prefix("17-")?.parse::<u32>().ok())
+                .unwrap_or(u32::MAX)
+        });
+        paths
+            .into_iter()
+            .filter_map(|path| fs::read(path.join("raw")).ok())
+            .flat_map(|raw| parse_table(&raw))
+            .collect::<Vec<_>>()
+    });
+
+    match from_entries {
+        Some(devices) if !devices.is_empty() => devices,
+        _ => fs::read(roots.sys("firmware/dmi/tables/DMI"))
+            .map(|raw| parse_table(&raw))
+            .unwrap_or_default(),
+    }
+}
+
+/// The memory technology of the installed modules, e.g. "DDR4".
+pub fn memory_technology(devices: &[MemoryDevice]) -> String {
+    devices
+        .iter()
+        .filter(|device| device.size.is_some() && device.memory_type != "unknown")
+        .map(|device| device.memory_type.clone())
+        .next()
+        .unwrap_or_else(|| String::from("unknown"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collectors::fixtures;
+
+    #[test]
+    fn reads_the_recorded_table_of_a_vm() {
+        // No per-entry files in the fixture tree, so this goes through the
+        // whole table, past the BIOS, system, chassis and processor entries.
+        assert_eq!(
+            read_memory_devices(&fixtures::host_roots()),
+            vec![MemoryDevice {
+                locator: String::from("DIMM 0"),
+                size: Some(6 * 1024 * 1024 * 1024),
+                memory_type: String::from("RAM"),
+                manufacturer: String::from("QEMU"),
+                ..Default::default()
+            }]
+        );
+        let raw = fs::read(fixtures::path("host/sys/firmware/dmi/tables/DMI")).unwrap();
+        assert_eq!(memory_technology(&parse_table(&raw)), "RAM");
+    }
+
+    /// A type 17 structure laid out as SMBIOS 3.2 firmware reports an 8 GiB
+    /// DDR4-3200 module running at 2400 MT/s, followed by end-of-table.
+    fn memory_device_table() -> Vec<u8> {
+        let mut formatted = vec![0u8; 0x28];
+        formatted[0] = MEMORY_DEVICE;
+        formatted[1] = 0x28;
+        formatted[0x0C..0x0E].copy_from_slice(&8192u16.to_le_bytes());
+        formatted[0x10] = 1;
+        formatted[0x11] = 2;
+        formatted[0x12] = 0x1A;
+        formatted[0x15..0x17].copy_from_slice(&3200u16.to_le_bytes());
+        formatted[0x17] = 3;
+        formatted[0x1A] = 4;
+        formatted[0x20..0x22].copy_from_slice(&2400u16.to_le_bytes());
+        let mut table = formatted;
+        table.extend_from_slice(b"DIMM_A1\0BANK 0\0Samsung\0M378A1K43DB2-CTD   \0\0");
+        table.extend_from_slice(&[END_OF_TABLE, 4, 0, 0, 0, 0]);
+        table
+    }
+
+    #[test]
+    fn parses_memory_devices() {
+        assert_eq!(
+            parse_table(&memory_device_table()),
+            vec![MemoryDevice {
+                locator: String::from("DIMM_A1"),
+                bank_locator: String::from("BANK 0"),
+                size: Some(8 * 1024 * 1024 * 1024),
+                memory_type: String::from("DDR4"),
+                speed: Some(3200),
+                configured_speed: Some(2400),
+                manufacturer: String::from("Samsung"),
+                part_number: String::from("M378A1K43DB2-CTD"),
+            }]
+        );
+    }
+
+    #[test]
+    fn truncated_tables_are_ignored() {
+        let table = memory_device_table();
+        assert_eq!(parse_table(&table[..0x20]), vec![]);
+        // Cut inside the memory device of the recorded table.
+        let raw = fs::read(fixtures::path("host/sys/firmware/dmi/tables/DMI")).unwrap();
+        assert_eq!(parse_table(&raw[..0x130]), vec![]);
+        assert_eq!(memory_technology(&[]), "unknown");
+    }
+}
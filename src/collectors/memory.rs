@@ -0,0 +1,168 @@
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::{read_file, Collector, CollectorError, Roots};
+use crate::CollectedData;
+
+/// Memory counters from `/proc/meminfo`, in bytes unless noted otherwise.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryStats {
+    pub total: u64,
+    pub free: u64,
+    pub available: u64,
+    pub buffers: u64,
+    pub cached: u64,
+    pub swap_cached: u64,
+    pub shmem: u64,
+    pub slab: u64,
+    pub s_reclaimable: u64,
+    pub dirty: u64,
+    pub writeback: u64,
+    pub anon_pages: u64,
+    /// Number of huge pages, not bytes.
+    pub huge_pages_total: u64,
+    /// Number of huge pages, not bytes.
+    pub huge_pages_free: u64,
+    pub huge_page_size: u64,
+    pub swap_total: u64,
+    pub swap_free: u64,
+}
+
+impl MemoryStats {
+    /// Page cache and reclaimable kernel memory, as `free` reports buff/cache.
+    pub fn buff_cache(&self) -> u64 {
+        self.buffers + self.cached + self.s_reclaimable
+    }
+
+    pub fn used(&self) -> u64 {
+        self.total.saturating_sub(self.free + self.buff_cache())
+    }
+
+    pub fn swap_used(&self) -> u64 {
+        self.swap_total.saturating_sub(self.swap_free)
+    }
+
+    pub fn huge_pages_bytes(&self) -> u64 {
+        self.huge_pages_total * self.huge_page_size
+    }
+}
+
+/// Parses `/proc/meminfo`. Unknown keys are ignored and missing ones stay 0.
+pub fn parse_meminfo(content: &str) -> MemoryStats {
+    let mut stats = MemoryStats::default();
+    for line in content.lines() {
+        let (key, value) = match line.split_once(':') {
+            Some(pair) => pair,
+            None => continue,
+        };
+        let mut value = value.split_whitespace();
+        let amount: u64 = match value.next().and_then(|v| v.parse().ok()) {
+            Some(amount) => amount,
+            None => continue,
+        };
+        let bytes = if value.next() == Some("kB") { amount * 1024 } else { amount };
+        let field = match key {
+            "MemTotal" => &mut stats.total,
+            "MemFree" => &mut stats.free,
+            "MemAvailable" => &mut stats.available,
+            "Buffers" => &mut stats.buffers,
+            "Cached" => &mut stats.cached,
+            "SwapCached" => &mut stats.swap_cached,
+            "Shmem" => &mut stats.shmem,
+            "Slab" => &mut stats.slab,
+            "SReclaimable" => &mut stats.s_reclaimable,
+            "Dirty" => &mut stats.dirty,
+            "Writeback" => &mut stats.writeback,
+            "AnonPages" => &mut stats.anon_pages,
+            "HugePages_Total" => &mut stats.huge_pages_total,
+            "HugePages_Free" => &mut stats.huge_pages_free,
+            "Hugepagesize" => &mut stats.huge_page_size,
+            "SwapTotal" => &mut stats.swap_total,
+            "SwapFree" => &mut stats.swap_free,
+            _ => continue,
+        };
+        *field = bytes;
+    }
+    stats
+}
+
+pub fn read_meminfo(roots: &Roots) -> Result<MemoryStats, CollectorError> {
+    let path = roots.proc("meminfo");
+    let stats = parse_meminfo(&read_file(&path)?);
+    if stats.total == 0 {
+        return Err(CollectorError::parse(path, "no MemTotal"));
+    }
+    Ok(stats)
+}
+
+pub struct MemoryCollector {
+    roots: Roots,
+}
+
+impl MemoryCollector {
+    pub fn new(roots: Roots) -> Self {
+        Self { roots }
+    }
+}
+
+impl Collector for MemoryCollector {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    fn default_interval(&self) -> Duration {
+        Duration::from_secs(2)
+    }
+
+    fn collect(&mut self, shared_data: &Arc<Mutex<CollectedData>>) -> Result<(), CollectorError> {
+        let memory = read_meminfo(&self.roots)?;
+
+        let mut data = shared_data.lock().unwrap_or_else(PoisonError::into_inner);
+        data.memory = memory;
+        data.updated_memory = true;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collectors::fixtures;
+
+    #[test]
+    fn parses_recorded_meminfo() {
+        let stats = parse_meminfo(&fixtures::read("host/proc/meminfo"));
+        assert_eq!(stats.total, 6158152 * 1024);
+        assert_eq!(stats.free, 3666116 * 1024);
+        assert_eq!(stats.available, 5608808 * 1024);
+        assert_eq!(stats.buff_cache(), (10252 + 2121816 + 65744) * 1024);
+        assert_eq!(stats.used(), stats.total - stats.free - stats.buff_cache());
+        assert_eq!(stats.swap_used(), 0);
+        assert_eq!(stats.huge_page_size, 2048 * 1024);
+    }
+
+    #[test]
+    fn ignores_lines_it_does_not_know() {
+        let stats = parse_meminfo("Buffers: 1 kB\nbogus line\nHugePages_Total:       4\n");
+        assert_eq!(stats.buffers, 1024);
+        assert_eq!(stats.huge_pages_total, 4);
+        assert_eq!(stats.total, 0);
+    }
+
+    #[test]
+    fn missing_meminfo_is_an_error() {
+        let roots = Roots { proc: fixtures::path("host/etc"), ..fixtures::host_roots() };
+        assert!(matches!(read_meminfo(&roots), Err(CollectorError::Io { .. })));
+    }
+
+    #[test]
+    fn collector_reads_the_configured_root() {
+        let shared_data = Arc::new(Mutex::new(CollectedData::default()));
+        MemoryCollector::new(fixtures::host_roots()).collect(&shared_data).unwrap();
+        let data = shared_data.lock().unwrap();
+        assert!(data.updated_memory);
+        assert_eq!(data.memory.total, 6158152 * 1024);
+    }
+}
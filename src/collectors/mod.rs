@@ -0,0 +1,355 @@
+// Native data sources that read procfs/sysfs directly instead of parsing the
+// output of external tools.
+
+pub mod cpu;
+pub mod diskio;
+pub mod disks;
+pub mod dmi;
+pub mod memory;
+pub mod network;
+pub mod process;
+pub mod process_details;
+pub mod sensors;
+pub mod sockets;
+pub mod sysinfo;
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{self, Display};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::{CollectedData, MAX_POINTS};
+
+/// Where the host's procfs, sysfs and /etc are mounted.
+///
+/// Running in a container with the host's trees bind mounted (e.g.
+/// `--proc-root /host/proc`) monitors the host instead of the container, and
+/// tests point these at recorded fixture trees.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Roots {
+    pub proc: PathBuf,
+    pub sys: PathBuf,
+    pub etc: PathBuf,
+}
+
+impl Default for Roots {
+    fn default() -> Self {
+        Self {
+            proc: PathBuf::from("/proc"),
+            sys: PathBuf::from("/sys"),
+            etc: PathBuf::from("/etc"),
+        }
+    }
+}
+
+impl Roots {
+    pub fn proc(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.proc.join(path)
+    }
+
+    pub fn sys(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.sys.join(path)
+    }
+
+    pub fn etc(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.etc.join(path)
+    }
+
+    /// True when reading the host through a procfs other than our own.
+    pub fn is_foreign(&self) -> bool {
+        self.proc != Path::new("/proc")
+    }
+
+    /// Maps an absolute path of the monitored system's root filesystem to one
+    /// we can open, going through init's root when the procfs is foreign.
+    pub fn host_path(&self, path: impl AsRef<Path>) -> PathBuf {
+        let path = path.as_ref();
+        if self.is_foreign() {
+            self.proc("1/root").join(path.strip_prefix("/").unwrap_or(path))
+        } else {
+            path.to_path_buf()
+        }
+    }
+}
+
+/// A data source run periodically by the scheduler.
+pub trait Collector: Send {
+    /// Name used to refer to the collector on the command line and in the UI.
+    fn name(&self) -> &'static str;
+
+    fn default_interval(&self) -> Duration;
+
+    fn collect(&mut self, shared_data: &Arc<Mutex<CollectedData>>) -> Result<(), CollectorError>;
+
+    /// Called before the first pass after the scheduler paused the collector,
+    /// e.g. to forget counters that rates would otherwise span the pause with.
+    fn resume(&mut self) {}
+}
+
+/// Why a collection pass failed. The scheduler records it and tries again at
+/// the next interval.
+#[derive(Debug)]
+pub enum CollectorError {
+    /// A file could not be read.
+    Io { path: PathBuf, source: io::Error },
+    /// A file was read but didn't have the expected layout.
+    Parse { path: PathBuf, message: String },
+}
+
+impl CollectorError {
+    pub fn io(path: impl AsRef<Path>, source: io::Error) -> Self {
+        CollectorError::Io { path: path.as_ref().to_path_buf(), source }
+    }
+
+    pub fn parse(path: impl AsRef<Path>, message: impl Into<String>) -> Self {
+        CollectorError::Parse { path: path.as_ref().to_path_buf(), message: message.into() }
+    }
+}
+
+impl Display for CollectorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CollectorError::Io { path, source } => write!(f, "cannot read {}: {}", path.display(), source),
+            CollectorError::Parse { path, message } => write!(f, "unexpected contents in {}: {}", path.display(), message),
+        }
+    }
+}
+
+impl std::error::Error for CollectorError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CollectorError::Io { source, .. } => Some(source),
+            CollectorError::Parse { .. } => None,
+        }
+    }
+}
+
+/// Reads a whole procfs/sysfs file, keeping the path in the error.
+pub fn read_file(path: impl AsRef<Path>) -> Result<String, CollectorError> {
+    fs::read_to_string(&path).map_err(|e| CollectorError::io(path, e))
+}
+
+/// One of a list of devices sampled on every pass, like a network interface
+/// or a sensor, whose recent values are charted.
+pub trait Sampled {
+    type Value;
+
+    /// Tells the device apart from the others in the list.
+    fn key(&self) -> &str;
+
+    /// Uptime when sampled, to tell new samples from repeated ones.
+    fn sampled_at(&self) -> f64;
+
+    /// What the history keeps of this sample; None until there is something
+    /// to keep, e.g. for rates, which need two samples.
+    fn value(&self) -> Option<Self::Value>;
+
+    /// Newest first, at most `MAX_POINTS` long.
+    fn history(&mut self) -> &mut VecDeque<Self::Value>;
+}
+
+/// Takes the new samples, carrying over the history of the devices already
+/// known. Remote viewers and replays get the samples without their history
+/// and rebuild it this way too.
+pub fn merge_samples<T: Sampled>(devices: &mut Vec<T>, samples: Vec<T>) {
+    let mut known: HashMap<String, T> = devices.drain(..).map(|device| (device.key().to_string(), device)).collect();
+    for mut sample in samples {
+        let previous = known.remove(sample.key());
+        let repeated = previous.as_ref().is_some_and(|previous| previous.sampled_at() == sample.sampled_at());
+        if let Some(mut previous) = previous {
+            std::mem::swap(sample.history(), previous.history());
+        }
+        if let (false, Some(value)) = (repeated, sample.value()) {
+            let history = sample.history();
+            history.push_front(value);
+            history.truncate(MAX_POINTS);
+        }
+        devices.push(sample);
+    }
+}
+
+/// How a collector has been doing, as shown in the pane banners.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CollectorHealth {
+    pub interval: Duration,
+    pub last_error: Option<String>,
+    pub last_success: Option<SystemTime>,
+    pub consecutive_failures: u32,
+    /// Set while the scheduler pauses the collector because no pane shows its
+    /// data, which leaves it stale on purpose.
+    #[serde(default)]
+    pub paused: bool,
+}
+
+impl CollectorHealth {
+    pub fn record(&mut self, result: &Result<(), CollectorError>, now: SystemTime) {
+        match result {
+            Ok(()) => {
+                self.last_error = None;
+                self.last_success = Some(now);
+                self.consecutive_failures = 0;
+            }
+            Err(error) => {
+                self.last_error = Some(error.to_string());
+                self.consecutive_failures += 1;
+            }
+        }
+    }
+
+    pub fn is_failing(&self) -> bool {
+        self.consecutive_failures > 0
+    }
+
+    /// True when the data is older than a few intervals, e.g. because the
+    /// collector keeps failing or takes longer than its interval.
+    pub fn is_stale(&self, now: SystemTime) -> bool {
+        match self.last_success {
+            Some(at) => now.duration_since(at).unwrap_or_default() > self.interval * 3 + Duration::from_secs(1),
+            None => self.is_failing(),
+        }
+    }
+}
+
+/// Every periodic collector, configured from the command line.
+pub fn all(config: &Config) -> Vec<Box<dyn Collector>> {
+    let roots = &config.roots;
+    vec![
+        Box::new(process::ProcessCollector::new(roots.clone())),
+        Box::new(memory::MemoryCollector::new(roots.clone())),
+        Box::new(cpu::CpuCollector::new(roots.clone())),
+        Box::new(disks::DiskCollector::new(roots.clone(), config.partition_filter.clone())),
+        Box::new(diskio::DiskIoCollector::new(roots.clone())),
+        Box::new(network::NetworkCollector::new(roots.clone())),
+        Box::new(sockets::SocketCollector::new(roots.clone())),
+        Box::new(sensors::SensorCollector::new(roots.clone())),
+    ]
+}
+
+/// Number of clock ticks per second used by the jiffies counters in procfs.
+pub fn clock_ticks() -> f64 {
+    let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    if ticks > 0 { ticks as f64 } else { 100.0 }
+}
+
+/// Size of a memory page in bytes.
+pub fn page_size() -> u64 {
+    let size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    if size > 0 { size as u64 } else { 4096 }
+}
+
+/// Recorded procfs/sysfs files under `tests/fixtures`.
+#[cfg(test)]
+pub mod fixtures {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use super::Roots;
+
+    pub fn path(relative: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(relative)
+    }
+
+    pub fn read(relative: &str) -> String {
+        fs::read_to_string(path(relative)).unwrap()
+    }
+
+    /// Roots pointing at the recorded tree of a small single-core VM.
+    pub fn host_roots() -> Roots {
+        Roots {
+            proc: path("host/proc"),
+            sys: path("host/sys"),
+            etc: path("host/etc"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn host_path_goes_through_init_root_when_foreign() {
+        assert_eq!(Roots::default().host_path("/var/lib"), Path::new("/var/lib"));
+        let roots = Roots { proc: PathBuf::from("/host/proc"), ..Roots::default() };
+        assert_eq!(roots.host_path("/var/lib"), Path::new("/host/proc/1/root/var/lib"));
+        assert_eq!(roots.host_path("/"), Path::new("/host/proc/1/root"));
+    }
+
+    #[test]
+    fn read_file_error_names_the_path() {
+        let error = read_file(fixtures::path("missing")).unwrap_err();
+        assert!(matches!(error, CollectorError::Io { .. }));
+        assert!(error.to_string().contains("tests/fixtures/missing"));
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Reading {
+        name: &'static str,
+        at: f64,
+        value: Option<u32>,
+        history: VecDeque<u32>,
+    }
+
+    impl Sampled for Reading {
+        type Value = u32;
+
+        fn key(&self) -> &str {
+            self.name
+        }
+
+        fn sampled_at(&self) -> f64 {
+            self.at
+        }
+
+        fn value(&self) -> Option<u32> {
+            self.value
+        }
+
+        fn history(&mut self) -> &mut VecDeque<u32> {
+            &mut self.history
+        }
+    }
+
+    #[test]
+    fn merging_keeps_the_history_of_known_devices() {
+        let reading = |name, at, value| Reading { name, at, value, history: VecDeque::new() };
+        let mut readings = vec![];
+        // Nothing to keep yet, as with a first rate.
+        merge_samples(&mut readings, vec![reading("eth0", 1.0, None)]);
+        merge_samples(&mut readings, vec![reading("eth0", 2.0, Some(5)), reading("wg0", 2.0, Some(1))]);
+        // The same sample twice doesn't grow the history.
+        merge_samples(&mut readings, vec![reading("eth0", 2.0, Some(5)), reading("wg0", 2.0, Some(1))]);
+        merge_samples(&mut readings, vec![reading("eth0", 3.0, Some(8))]);
+        assert_eq!(readings.len(), 1);
+        assert_eq!(readings[0].history, [8, 5]);
+    }
+
+    #[test]
+    fn health_tracks_failures_and_staleness() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let mut health = CollectorHealth { interval: Duration::from_secs(2), ..Default::default() };
+        assert!(!health.is_stale(start));
+
+        health.record(&Ok(()), start);
+        assert!(!health.is_failing());
+        assert!(!health.is_stale(start + Duration::from_secs(4)));
+
+        let error = CollectorError::parse("/proc/stat", "no per-core cpu lines");
+        health.record(&Err(error), start + Duration::from_secs(2));
+        health.record(&Err(CollectorError::parse("/proc/stat", "again")), start + Duration::from_secs(4));
+        assert!(health.is_failing());
+        assert_eq!(health.consecutive_failures, 2);
+        assert_eq!(health.last_error.as_deref(), Some("unexpected contents in /proc/stat: again"));
+        assert!(health.is_stale(start + Duration::from_secs(10)));
+
+        health.record(&Ok(()), start + Duration::from_secs(10));
+        assert_eq!(health.consecutive_failures, 0);
+        assert_eq!(health.last_error, None);
+    }
+}
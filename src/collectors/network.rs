@@ -0,0 +1,297 @@
+use std::collections::{HashMap, VecDeque};
+use std::ffi::CStr;
+use std::fs;
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::process::read_uptime;
+use super::{merge_samples, read_file, Collector, CollectorError, Roots, Sampled};
+use crate::CollectedData;
+
+/// The totals of one line of `/proc/net/dev`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetDevCounters {
+    pub rx_bytes: u64,
+    pub rx_packets: u64,
+    pub rx_errors: u64,
+    pub rx_drops: u64,
+    pub tx_bytes: u64,
+    pub tx_packets: u64,
+    pub tx_errors: u64,
+    pub tx_drops: u64,
+}
+
+/// The same counters per second, between two samples.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct NetworkRates {
+    pub rx_bytes: f64,
+    pub rx_packets: f64,
+    pub rx_errors: f64,
+    pub rx_drops: f64,
+    pub tx_bytes: f64,
+    pub tx_packets: f64,
+    pub tx_errors: f64,
+    pub tx_drops: f64,
+}
+
+impl NetDevCounters {
+    fn rates_since(&self, before: &NetDevCounters, seconds: f64) -> NetworkRates {
+        // Counters restart when a driver is reloaded.
+        let rate = |now: u64, before: u64| now.saturating_sub(before) as f64 / seconds;
+        NetworkRates {
+            rx_bytes: rate(self.rx_bytes, before.rx_bytes),
+            rx_packets: rate(self.rx_packets, before.rx_packets),
+            rx_errors: rate(self.rx_errors, before.rx_errors),
+            rx_drops: rate(self.rx_drops, before.rx_drops),
+            tx_bytes: rate(self.tx_bytes, before.tx_bytes),
+            tx_packets: rate(self.tx_packets, before.tx_packets),
+            tx_errors: rate(self.tx_errors, before.tx_errors),
+            tx_drops: rate(self.tx_drops, before.tx_drops),
+        }
+    }
+}
+
+/// An interface as the Network pane shows it.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NetworkInterface {
+    pub name: String,
+    pub mtu: Option<u32>,
+    /// `up`, `down`, `unknown` (loopback, tunnels) and so on.
+    pub operstate: String,
+    /// Link speed in Mb/s, unknown for virtual interfaces.
+    pub speed: Option<u32>,
+    pub mac: String,
+    /// With the prefix length, e.g. `10.0.0.2/24`.
+    pub addresses: Vec<String>,
+    pub counters: NetDevCounters,
+    /// None on the first sample of an interface.
+    pub rates: Option<NetworkRates>,
+    pub sampled_at: f64,
+    #[serde(skip)]
+    pub history: VecDeque<NetworkRates>,
+}
+
+impl Sampled for NetworkInterface {
+    type Value = NetworkRates;
+
+    fn key(&self) -> &str {
+        &self.name
+    }
+
+    fn sampled_at(&self) -> f64 {
+        self.sampled_at
+    }
+
+    fn value(&self) -> Option<NetworkRates> {
+        self.rates
+    }
+
+    fn history(&mut self) -> &mut VecDeque<NetworkRates> {
+        &mut self.history
+    }
+}
+
+/// Parses `/proc/net/dev`, keeping the order of the interfaces.
+pub fn parse_net_dev(content: &str) -> Vec<(String, NetDevCounters)> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let (name, values) = line.split_once(':')?;
+            let values: Vec<u64> = values.split_whitespace().map(|v| v.parse().unwrap_or(0)).collect();
+            let value = |n: usize| values.get(n).copied();
+            let counters = NetDevCounters {
+                rx_bytes: value(0)?,
+                rx_packets: value(1)?,
+                rx_errors: value(2)?,
+                rx_drops: value(3)?,
+                tx_bytes: value(8)?,
+                tx_packets: value(9)?,
+                tx_errors: value(10)?,
+                tx_drops: value(11)?,
+            };
+            Some((name.trim().to_string(), counters))
+        })
+        .collect()
+}
+
+fn prefix_len(mask: &[u8]) -> u32 {
+    mask.iter().map(|byte| byte.count_ones()).sum()
+}
+
+/// The addresses of our own network namespace's interfaces, by name.
+fn interface_addresses() -> io::Result<HashMap<String, Vec<String>>> {
+    let mut addrs: *mut libc::ifaddrs = std::ptr::null_mut();
+    if unsafe { libc::getifaddrs(&mut addrs) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let mut addresses: HashMap<String, Vec<String>> = HashMap::new();
+    let mut next = addrs;
+    while let Some(ifaddr) = unsafe { next.as_ref() } {
+        next = ifaddr.ifa_next;
+        if ifaddr.ifa_addr.is_null() || ifaddr.ifa_netmask.is_null() {
+            continue;
+        }
+        let name = unsafe { CStr::from_ptr(ifaddr.ifa_name) }.to_string_lossy().into_owned();
+        let address = match i32::from(unsafe { (*ifaddr.ifa_addr).sa_family }) {
+            libc::AF_INET => {
+                let (addr, mask) = unsafe {
+                    (&*(ifaddr.ifa_addr as *const libc::sockaddr_in), &*(ifaddr.ifa_netmask as *const libc::sockaddr_in))
+                };
+                let ip = Ipv4Addr::from(u32::from_be(addr.sin_addr.s_addr));
+                format!("{}/{}", ip, prefix_len(&mask.sin_addr.s_addr.to_ne_bytes()))
+            }
+            libc::AF_INET6 => {
+                let (addr, mask) = unsafe {
+                    (&*(ifaddr.ifa_addr as *const libc::sockaddr_in6), &*(ifaddr.ifa_netmask as *const libc::sockaddr_in6))
+                };
+                format!("{}/{}", Ipv6Addr::from(addr.sin6_addr.s6_addr), prefix_len(&mask.sin6_addr.s6_addr))
+            }
+            _ => continue,
+        };
+        addresses.entry(name).or_default().push(address);
+    }
+    unsafe { libc::freeifaddrs(addrs) };
+    Ok(addresses)
+}
+
+/// Rates from `/proc/net/dev` and link details from `/sys/class/net`.
+///
+/// With a foreign procfs the counters of the host's init namespace are read
+/// instead. Addresses come from getifaddrs, which only sees our own
+/// namespace, so they are left out then.
+pub struct NetworkCollector {
+    roots: Roots,
+    previous: HashMap<String, (NetDevCounters, f64)>,
+}
+
+impl NetworkCollector {
+    pub fn new(roots: Roots) -> Self {
+        Self { roots, previous: HashMap::new() }
+    }
+
+    fn read_link(&self, name: &str) -> (Option<u32>, String, Option<u32>, String) {
+        let dir = self.roots.sys("class/net").join(name);
+        let read = |file: &str| fs::read_to_string(dir.join(file)).map(|value| value.trim().to_string()).ok();
+        // Reading the speed fails with EINVAL without a link, and some
+        // drivers report -1.
+        (
+            read("mtu").and_then(|mtu| mtu.parse().ok()),
+            read("operstate").unwrap_or_else(|| String::from("unknown")),
+            read("speed").and_then(|speed| speed.parse().ok()),
+            read("address").unwrap_or_default(),
+        )
+    }
+
+    fn sample(&mut self, devices: Vec<(String, NetDevCounters)>, uptime: f64) -> Vec<NetworkInterface> {
+        let mut addresses = if self.roots.is_foreign() { HashMap::new() } else { interface_addresses().unwrap_or_default() };
+        let interfaces = devices
+            .into_iter()
+            .map(|(name, counters)| {
+                let rates = match self.previous.get(&name) {
+                    Some((before, at)) if uptime > *at => Some(counters.rates_since(before, uptime - at)),
+                    _ => None,
+                };
+                let (mtu, operstate, speed, mac) = self.read_link(&name);
+                NetworkInterface {
+                    addresses: addresses.remove(&name).unwrap_or_default(),
+                    name,
+                    mtu,
+                    operstate,
+                    speed,
+                    mac,
+                    counters,
+                    rates,
+                    sampled_at: uptime,
+                    history: VecDeque::new(),
+                }
+            })
+            .collect::<Vec<_>>();
+        self.previous = interfaces.iter().map(|i| (i.name.clone(), (i.counters, uptime))).collect();
+        interfaces
+    }
+}
+
+impl Collector for NetworkCollector {
+    fn name(&self) -> &'static str {
+        "network"
+    }
+
+    fn default_interval(&self) -> Duration {
+        Duration::from_secs(2)
+    }
+
+    fn collect(&mut self, shared_data: &Arc<Mutex<CollectedData>>) -> Result<(), CollectorError> {
+        let path = if self.roots.is_foreign() {
+            self.roots.proc("1/net/dev")
+        } else {
+            self.roots.proc("net/dev")
+        };
+        let devices = parse_net_dev(&read_file(&path)?);
+        if devices.is_empty() {
+            return Err(CollectorError::parse(path, "no interfaces listed"));
+        }
+        let uptime = read_uptime(&self.roots)?;
+        let samples = self.sample(devices, uptime);
+
+        let mut data = shared_data.lock().unwrap_or_else(PoisonError::into_inner);
+        merge_samples(&mut data.network, samples);
+        data.updated_network = true;
+        Ok(())
+    }
+
+    fn resume(&mut self) {
+        self.previous.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collectors::fixtures;
+
+    #[test]
+    fn parses_recorded_net_dev() {
+        let devices = parse_net_dev(&fixtures::read("host/proc/1/net/dev"));
+        let names: Vec<&str> = devices.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["lo", "eth0"]);
+        assert_eq!(
+            devices[1].1,
+            NetDevCounters {
+                rx_bytes: 61481704,
+                rx_packets: 4945,
+                rx_errors: 2,
+                rx_drops: 7,
+                tx_bytes: 306255,
+                tx_packets: 3229,
+                tx_errors: 0,
+                tx_drops: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn collector_reads_links_and_computes_rates() {
+        let shared_data = Arc::new(Mutex::new(CollectedData::default()));
+        let mut collector = NetworkCollector::new(fixtures::host_roots());
+        collector.collect(&shared_data).unwrap();
+        {
+            let data = shared_data.lock().unwrap();
+            let eth0 = &data.network[1];
+            assert_eq!((eth0.mtu, eth0.operstate.as_str(), eth0.speed), (Some(1500), "up", Some(1000)));
+            assert_eq!(eth0.mac, "02:fc:00:00:00:01");
+            // Loopback has no speed file.
+            assert_eq!(data.network[0].speed, None);
+            assert_eq!((eth0.rates, eth0.history.len()), (None, 0));
+            assert!(data.updated_network);
+        }
+
+        let eth0 = shared_data.lock().unwrap().network[1].clone();
+        let counters = NetDevCounters { rx_bytes: eth0.counters.rx_bytes + 3000, ..eth0.counters };
+        let interfaces = collector.sample(vec![(eth0.name, counters)], eth0.sampled_at + 2.0);
+        assert_eq!(interfaces[0].rates.map(|rates| rates.rx_bytes), Some(1500.0));
+    }
+}
@@ -0,0 +1,458 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use super::{read_file, Collector, CollectorError, Roots};
+use crate::{CollectedData, ProcessInfo};
+
+/// The fields of `/proc/[pid]/stat` the task list cares about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcStat {
+    pub pid: usize,
+    pub comm: String,
+    pub state: char,
+    pub ppid: usize,
+    pub tty_nr: u32,
+    pub ticks: u64,
+    pub priority: i64,
+    pub nice: i64,
+    pub threads: usize,
+    pub start_time: u64,
+    pub vsize: u64,
+    pub rss_pages: u64,
+    /// CPU the process last ran on.
+    pub processor: u32,
+    pub policy: u32,
+}
+
+/// Parses the contents of `/proc/[pid]/stat`.
+///
+/// The command name is wrapped in parentheses and may itself contain spaces
+/// or parentheses, so the remaining fields are split after the last `)`.
+pub fn parse_stat(content: &str) -> Option<ProcStat> {
+    let open = content.find('(')?;
+    let close = content.rfind(')')?;
+    let pid = content[..open].trim().parse().ok()?;
+    let comm = content[open + 1..close].to_string();
+    // Field 3 (state) is the first one after the command name.
+    let fields: Vec<&str> = content[close + 1..].split_whitespace().collect();
+    let field = |n: usize| fields.get(n - 3).copied();
+
+    let state = field(3)?.chars().next()?;
+    let ppid = field(4)?.parse().ok()?;
+    let tty_nr = field(7)?.parse::<i64>().ok()? as u32;
+    let utime: u64 = field(14)?.parse().ok()?;
+    let stime: u64 = field(15)?.parse().ok()?;
+    let priority = field(18)?.parse().ok()?;
+    let nice = field(19)?.parse().ok()?;
+    let threads = field(20)?.parse().ok()?;
+    let start_time = field(22)?.parse().ok()?;
+    let vsize = field(23)?.parse().ok()?;
+    let rss_pages = field(24)?.parse::<i64>().ok()?.max(0) as u64;
+    let processor = field(39).and_then(|p| p.parse().ok()).unwrap_or(0);
+    let policy = field(41).and_then(|p| p.parse().ok()).unwrap_or(0);
+
+    Some(ProcStat {
+        pid,
+        comm,
+        state,
+        ppid,
+        tty_nr,
+        ticks: utime + stime,
+        priority,
+        nice,
+        threads,
+        start_time,
+        vsize,
+        rss_pages,
+        processor,
+        policy,
+    })
+}
+
+/// Names the controlling terminal from the `tty_nr` device number like ps
+/// does, `?` when there is none.
+pub fn tty_name(tty_nr: u32) -> String {
+    let major = (tty_nr >> 8) & 0xfff;
+    let minor = (tty_nr & 0xff) | ((tty_nr >> 12) & 0xfff00);
+    match major {
+        0 => String::from("?"),
+        4 if minor < 64 => format!("tty{}", minor),
+        4 => format!("ttyS{}", minor - 64),
+        136..=143 => format!("pts/{}", (major - 136) * 256 + minor),
+        _ => format!("{}:{}", major, minor),
+    }
+}
+
+/// Extracts the real uid from the contents of `/proc/[pid]/status`.
+pub fn parse_status_uid(content: &str) -> Option<u32> {
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix("Uid:"))
+        .and_then(|uids| uids.split_whitespace().next())
+        .and_then(|uid| uid.parse().ok())
+}
+
+/// Reads a `kB` line of `/proc/[pid]/status`, like `VmSwap:`, in bytes.
+pub fn parse_status_kb(content: &str, key: &str) -> Option<u64> {
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix(key))
+        .and_then(|value| value.split_whitespace().next())
+        .and_then(|kb| kb.parse::<u64>().ok())
+        .map(|kb| kb * 1024)
+}
+
+/// The bytes read from and written to storage in `/proc/[pid]/io`.
+pub fn parse_io(content: &str) -> Option<(u64, u64)> {
+    let value = |key: &str| {
+        content
+            .lines()
+            .find_map(|line| line.strip_prefix(key))
+            .and_then(|value| value.trim().parse().ok())
+    };
+    Some((value("read_bytes:")?, value("write_bytes:")?))
+}
+
+/// The cgroup v2 path of `/proc/[pid]/cgroup`, or the first v1 hierarchy's.
+pub fn parse_cgroup(content: &str) -> String {
+    let path = |line: &str| line.splitn(3, ':').nth(2).map(str::to_string);
+    content
+        .lines()
+        .find(|line| line.starts_with("0::"))
+        .or_else(|| content.lines().next())
+        .and_then(path)
+        .unwrap_or_default()
+}
+
+/// Turns the NUL separated `/proc/[pid]/cmdline` into a printable command,
+/// falling back to `[comm]` for kernel threads like ps does.
+pub fn format_cmdline(raw: &[u8], comm: &str) -> String {
+    let args: Vec<_> = raw
+        .split(|&b| b == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(String::from_utf8_lossy)
+        .collect();
+    if args.is_empty() {
+        format!("[{}]", comm)
+    } else {
+        args.join(" ")
+    }
+}
+
+pub fn parse_passwd(content: &str) -> HashMap<u32, String> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let uid = fields.nth(1)?.parse().ok()?;
+            Some((uid, name.to_string()))
+        })
+        .collect()
+}
+
+fn invalid(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("malformed {}", what))
+}
+
+pub fn read_uptime(roots: &Roots) -> Result<f64, CollectorError> {
+    let path = roots.proc("uptime");
+    read_file(&path)?
+        .split_whitespace()
+        .next()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| CollectorError::parse(path, "no uptime value"))
+}
+
+/// The per-process files read only when something shows what they hold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessFiles {
+    /// `io`, for the read and write rates.
+    pub io: bool,
+    pub oom_score: bool,
+    pub cgroup: bool,
+}
+
+impl ProcessFiles {
+    pub const ALL: ProcessFiles = ProcessFiles { io: true, oom_score: true, cgroup: true };
+}
+
+/// Everything: exports and agents have no columns to go by.
+impl Default for ProcessFiles {
+    fn default() -> Self {
+        ProcessFiles::ALL
+    }
+}
+
+struct RawProcess {
+    stat: ProcStat,
+    uid: u32,
+    cmd: String,
+    swap: u64,
+    /// Unreadable for other users' processes without privileges.
+    io: Option<(u64, u64)>,
+    oom_score: Option<i64>,
+    cgroup: String,
+}
+
+fn read_process(roots: &Roots, pid: usize, files: ProcessFiles) -> io::Result<RawProcess> {
+    let dir = roots.proc(pid.to_string());
+    let stat = fs::read_to_string(dir.join("stat"))?;
+    let stat = parse_stat(&stat).ok_or_else(|| invalid("stat"))?;
+    let status = fs::read_to_string(dir.join("status"))?;
+    let uid = parse_status_uid(&status).ok_or_else(|| invalid("status"))?;
+    let cmdline = fs::read(dir.join("cmdline"))?;
+    let cmd = format_cmdline(&cmdline, &stat.comm);
+    let swap = parse_status_kb(&status, "VmSwap:").unwrap_or(0);
+    let optional = |name: &str, wanted: bool| if wanted { fs::read_to_string(dir.join(name)).ok() } else { None };
+    let io = optional("io", files.io).and_then(|io| parse_io(&io));
+    let oom_score = optional("oom_score", files.oom_score).and_then(|score| score.trim().parse().ok());
+    let cgroup = optional("cgroup", files.cgroup).map(|cgroup| parse_cgroup(&cgroup)).unwrap_or_default();
+    Ok(RawProcess { stat, uid, cmd, swap, io, oom_score, cgroup })
+}
+
+/// What the previous sample saw of a process, for the rates.
+struct Previous {
+    start_time: u64,
+    ticks: u64,
+    io: Option<(u64, u64)>,
+}
+
+/// Builds the task list from `/proc/[pid]/{stat,status,cmdline}`, and the
+/// other files `CollectedData::process_files` asks for.
+///
+/// CPU usage is the share of one core used since the previous sample, so
+/// like the I/O rates it needs two samples: a process seen for the first
+/// time reads 0%.
+pub struct ProcessCollector {
+    roots: Roots,
+    previous: HashMap<usize, Previous>,
+    previous_uptime: f64,
+    boot_time: Option<u64>,
+    users: HashMap<u32, String>,
+    clock_ticks: f64,
+    page_size: u64,
+}
+
+impl ProcessCollector {
+    pub fn new(roots: Roots) -> Self {
+        Self {
+            roots,
+            previous: HashMap::new(),
+            previous_uptime: 0.0,
+            boot_time: None,
+            users: HashMap::new(),
+            clock_ticks: super::clock_ticks(),
+            page_size: super::page_size(),
+        }
+    }
+
+    fn sample(&mut self, files: ProcessFiles) -> Result<Vec<ProcessInfo>, CollectorError> {
+        let uptime = read_uptime(&self.roots)?;
+        let mem_total = super::memory::read_meminfo(&self.roots)?.total;
+        let elapsed = uptime - self.previous_uptime;
+        if self.boot_time.is_none() {
+            let proc_stat = read_file(self.roots.proc("stat"))?;
+            self.boot_time = super::sysinfo::parse_boot_time(&proc_stat);
+        }
+        let boot_time = self.boot_time.unwrap_or(0);
+
+        let mut raw = vec![];
+        let entries = fs::read_dir(&self.roots.proc).map_err(|e| CollectorError::io(&self.roots.proc, e))?;
+        for entry in entries {
+            let pid = match entry.ok().and_then(|e| e.file_name().to_str()?.parse().ok()) {
+                Some(pid) => pid,
+                None => continue,
+            };
+            // The process may exit between listing /proc and reading its files.
+            if let Ok(process) = read_process(&self.roots, pid, files) {
+                raw.push(process);
+            }
+        }
+
+        if raw.iter().any(|p| !self.users.contains_key(&p.uid)) {
+            if let Ok(passwd) = fs::read_to_string(self.roots.etc("passwd")) {
+                self.users = parse_passwd(&passwd);
+            }
+        }
+
+        let mut current = HashMap::with_capacity(raw.len());
+        let process_list = raw
+            .into_iter()
+            .map(|RawProcess { stat, uid, cmd, swap, io, oom_score, cgroup }| {
+                current.insert(stat.pid, Previous { start_time: stat.start_time, ticks: stat.ticks, io });
+                let previous = self
+                    .previous
+                    .get(&stat.pid)
+                    .filter(|previous| previous.start_time == stat.start_time && elapsed > 0.0);
+                let io_rate = |bytes: fn((u64, u64)) -> u64| {
+                    let (now, before) = (io?, previous?.io?);
+                    Some(bytes(now).saturating_sub(bytes(before)) as f64 / elapsed)
+                };
+                // Until a second sample there is nothing to compare with.
+                let cpu = match previous {
+                    Some(previous) => (stat.ticks.saturating_sub(previous.ticks) as f64 / self.clock_ticks) / elapsed,
+                    None => 0.0,
+                } * 100.0;
+                let rss = stat.rss_pages * self.page_size;
+                let memory = if mem_total > 0 {
+                    rss as f64 / mem_total as f64 * 100.0
+                } else {
+                    0.0
+                };
+                // ps shows "-" as the nice value of real-time processes.
+                let nice = if stat.policy == 1 || stat.policy == 2 {
+                    String::from("-")
+                } else {
+                    stat.nice.to_string()
+                };
+                ProcessInfo {
+                    pid: stat.pid,
+                    ppid: stat.ppid,
+                    nice,
+                    status: stat.state.to_string(),
+                    user: self.users.get(&uid).cloned().unwrap_or_else(|| uid.to_string()),
+                    cpu,
+                    memory,
+                    cmd,
+                    rss,
+                    vsz: stat.vsize,
+                    swap,
+                    threads: stat.threads,
+                    start: boot_time + (stat.start_time as f64 / self.clock_ticks) as u64,
+                    elapsed: (uptime - stat.start_time as f64 / self.clock_ticks).max(0.0) as u64,
+                    tty: tty_name(stat.tty_nr),
+                    priority: stat.priority,
+                    processor: stat.processor,
+                    io_read: io_rate(|(read, _)| read),
+                    io_write: io_rate(|(_, write)| write),
+                    oom_score,
+                    cgroup,
+                }
+            })
+            .collect();
+
+        self.previous = current;
+        self.previous_uptime = uptime;
+        Ok(process_list)
+    }
+}
+
+impl Collector for ProcessCollector {
+    fn name(&self) -> &'static str {
+        "tasks"
+    }
+
+    fn default_interval(&self) -> Duration {
+        Duration::from_secs(2)
+    }
+
+    fn collect(&mut self, shared_data: &Arc<Mutex<CollectedData>>) -> Result<(), CollectorError> {
+        let files = shared_data.lock().unwrap_or_else(PoisonError::into_inner).process_files;
+        let process_list = self.sample(files)?;
+
+        let mut data = shared_data.lock().unwrap_or_else(PoisonError::into_inner);
+        data.process_list = process_list;
+        data.updated_tasks = true;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collectors::fixtures;
+
+    #[test]
+    fn parses_recorded_stat() {
+        let stat = parse_stat(&fixtures::read("host/proc/11947/stat")).unwrap();
+        assert_eq!(
+            stat,
+            ProcStat {
+                pid: 11947,
+                comm: String::from("sleep"),
+                state: 'S',
+                ppid: 11942,
+                tty_nr: 0,
+                ticks: 0,
+                priority: 20,
+                nice: 0,
+                threads: 1,
+                start_time: 173496,
+                vsize: 2560000,
+                rss_pages: 303,
+                processor: 0,
+                policy: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn command_names_may_contain_spaces_and_parentheses() {
+        let stat = parse_stat("42 (a) b (c)) R 1 42 42 0 -1 0 0 0 0 0 5 7 0 0 20 -5 1 0 100 0 9").unwrap();
+        assert_eq!(stat.comm, "a) b (c)");
+        assert_eq!(stat.ticks, 12);
+        assert_eq!(stat.nice, -5);
+        assert_eq!(stat.rss_pages, 9);
+    }
+
+    #[test]
+    fn kernel_threads_show_their_name_in_brackets() {
+        let stat = parse_stat(&fixtures::read("host/proc/2/stat")).unwrap();
+        let cmdline = fs::read(fixtures::path("host/proc/2/cmdline")).unwrap();
+        assert_eq!(format_cmdline(&cmdline, &stat.comm), "[kthreadd]");
+        let cmdline = fs::read(fixtures::path("host/proc/11947/cmdline")).unwrap();
+        assert_eq!(format_cmdline(&cmdline, "sleep"), "sleep 1000");
+    }
+
+    #[test]
+    fn parses_status_and_passwd() {
+        assert_eq!(parse_status_uid(&fixtures::read("host/proc/11947/status")), Some(0));
+        let users = parse_passwd(&fixtures::read("host/etc/passwd"));
+        assert_eq!(users.get(&0).map(String::as_str), Some("root"));
+        assert_eq!(users.get(&65534).map(String::as_str), Some("nobody"));
+    }
+
+    #[test]
+    fn parses_terminals_io_and_cgroups() {
+        assert_eq!(tty_name(0), "?");
+        assert_eq!(tty_name(34817), "pts/1");
+        assert_eq!(tty_name(1025), "tty1");
+        assert_eq!(tty_name(1088), "ttyS0");
+        assert_eq!(parse_io(&fixtures::read("host/proc/11947/io")), Some((4096, 0)));
+        assert_eq!(parse_status_kb(&fixtures::read("host/proc/11947/status"), "VmRSS:"), Some(1376 * 1024));
+        assert_eq!(parse_cgroup(&fixtures::read("host/proc/11947/cgroup")), "/system.slice/sleep.service");
+        assert_eq!(parse_cgroup("12:pids:/user.slice\n1:name=systemd:/init.scope\n"), "/user.slice");
+    }
+
+    #[test]
+    fn collector_lists_the_recorded_processes() {
+        let shared_data = Arc::new(Mutex::new(CollectedData::default()));
+        ProcessCollector::new(fixtures::host_roots()).collect(&shared_data).unwrap();
+        let data = shared_data.lock().unwrap();
+        let mut processes: Vec<_> = data.process_list.iter().map(|p| (p.pid, p.user.as_str(), p.cmd.as_str())).collect();
+        processes.sort();
+        assert_eq!(processes, vec![(2, "root", "[kthreadd]"), (11947, "root", "sleep 1000")]);
+
+        let sleep = data.process_list.iter().find(|p| p.pid == 11947).unwrap();
+        assert_eq!((sleep.threads, sleep.vsz, sleep.tty.as_str(), sleep.priority), (1, 2560000, "?", 20));
+        assert_eq!((sleep.oom_score, sleep.cgroup.as_str()), (Some(666), "/system.slice/sleep.service"));
+        // Started 1734.96s after boot, at 100 ticks per second.
+        assert_eq!(sleep.start, 1792306333 + 1734);
+        // Rates need a second sample.
+        assert_eq!((sleep.cpu, sleep.io_read), (0.0, None));
+    }
+
+    #[test]
+    fn collector_skips_the_files_no_column_needs() {
+        let files = ProcessFiles { io: false, oom_score: false, cgroup: true };
+        let shared_data = Arc::new(Mutex::new(CollectedData { process_files: files, ..Default::default() }));
+        ProcessCollector::new(fixtures::host_roots()).collect(&shared_data).unwrap();
+        let data = shared_data.lock().unwrap();
+        let sleep = data.process_list.iter().find(|p| p.pid == 11947).unwrap();
+        assert_eq!((sleep.oom_score, sleep.cgroup.as_str()), (None, "/system.slice/sleep.service"));
+    }
+}
@@ -0,0 +1,375 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use super::process::{format_cmdline, parse_stat, read_uptime, ProcStat};
+use super::{Collector, CollectorError, Roots};
+use crate::{CollectedData, MAX_POINTS};
+
+/// One row of `/proc/[pid]/limits`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Limit {
+    pub name: String,
+    pub soft: String,
+    pub hard: String,
+    pub units: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThreadInfo {
+    pub tid: usize,
+    pub comm: String,
+    pub state: char,
+}
+
+/// Everything the detail pane shows about one process.
+///
+/// Sections the kernel refuses to show (other users' fds and environment
+/// without privileges) hold the error instead.
+#[derive(Debug, Clone)]
+pub struct ProcessDetails {
+    pub pid: usize,
+    pub comm: String,
+    pub cmd: String,
+    pub state: char,
+    pub start_time: u64,
+    /// Set once the process is gone; the other fields keep the last values.
+    pub exited: bool,
+    /// Parent, grandparent and so on up to init.
+    pub parents: Vec<(usize, String)>,
+    pub threads: Vec<ThreadInfo>,
+    pub fds: Result<Vec<(usize, String)>, String>,
+    pub cwd: Result<String, String>,
+    pub exe: Result<String, String>,
+    pub environ: Result<Vec<String>, String>,
+    pub limits: Vec<Limit>,
+    pub cgroup: Vec<String>,
+    pub namespaces: Result<Vec<(String, String)>, String>,
+    /// CPU percent and resident bytes, newest first.
+    pub history: VecDeque<(f64, u64)>,
+}
+
+impl ProcessDetails {
+    /// What there is to show of a process that exited before it was read.
+    fn gone(pid: usize, cmd: String) -> Self {
+        fn gone<T>() -> Result<T, String> {
+            Err(String::from("the process exited"))
+        }
+        Self {
+            pid,
+            comm: cmd.split_whitespace().next().unwrap_or_default().rsplit('/').next().unwrap_or_default().to_string(),
+            cmd,
+            state: 'X',
+            start_time: 0,
+            exited: true,
+            parents: vec![],
+            threads: vec![],
+            fds: gone(),
+            cwd: gone(),
+            exe: gone(),
+            environ: gone(),
+            limits: vec![],
+            cgroup: vec![],
+            namespaces: gone(),
+            history: VecDeque::new(),
+        }
+    }
+}
+
+/// Parses `/proc/[pid]/limits`, whose columns are aligned under the header.
+pub fn parse_limits(content: &str) -> Vec<Limit> {
+    let mut lines = content.lines();
+    let header = match lines.next() {
+        Some(header) => header,
+        None => return vec![],
+    };
+    let (soft, hard, units) = match (header.find("Soft Limit"), header.find("Hard Limit"), header.find("Units")) {
+        (Some(soft), Some(hard), Some(units)) => (soft, hard, units),
+        _ => return vec![],
+    };
+    let column = |line: &str, from: usize, to: usize| line.get(from..to.min(line.len())).unwrap_or_default().trim().to_string();
+    lines
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| Limit {
+            name: column(line, 0, soft),
+            soft: column(line, soft, hard),
+            hard: column(line, hard, units),
+            units: column(line, units, line.len()),
+        })
+        .collect()
+}
+
+/// Splits the NUL separated `/proc/[pid]/environ`.
+pub fn parse_environ(raw: &[u8]) -> Vec<String> {
+    raw.split(|&b| b == 0)
+        .filter(|var| !var.is_empty())
+        .map(|var| String::from_utf8_lossy(var).into_owned())
+        .collect()
+}
+
+/// Keeps the error of a section the kernel wouldn't show for display.
+fn section<T>(result: io::Result<T>) -> Result<T, String> {
+    result.map_err(|error| error.to_string())
+}
+
+fn read_link(path: &Path) -> io::Result<String> {
+    fs::read_link(path).map(|target| target.to_string_lossy().into_owned())
+}
+
+/// Reads a directory of symlinks named by number, like `fd/`.
+fn read_numbered_links(dir: &Path) -> io::Result<Vec<(usize, String)>> {
+    let mut links = vec![];
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if let Some(number) = entry.file_name().to_str().and_then(|name| name.parse().ok()) {
+            // An fd may be closed between listing and reading it.
+            if let Ok(target) = read_link(&entry.path()) {
+                links.push((number, target));
+            }
+        }
+    }
+    links.sort();
+    Ok(links)
+}
+
+fn read_namespaces(dir: &Path) -> io::Result<Vec<(String, String)>> {
+    let mut namespaces = vec![];
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        namespaces.push((entry.file_name().to_string_lossy().into_owned(), read_link(&entry.path())?));
+    }
+    namespaces.sort();
+    Ok(namespaces)
+}
+
+fn read_stat(roots: &Roots, pid: usize) -> io::Result<ProcStat> {
+    let stat = fs::read_to_string(roots.proc(format!("{}/stat", pid)))?;
+    parse_stat(&stat).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed stat"))
+}
+
+fn read_threads(roots: &Roots, pid: usize) -> Vec<ThreadInfo> {
+    let mut threads: Vec<ThreadInfo> = fs::read_dir(roots.proc(format!("{}/task", pid)))
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.ok()?.path().join("stat");
+            let stat = parse_stat(&fs::read_to_string(path).ok()?)?;
+            Some(ThreadInfo { tid: stat.pid, comm: stat.comm, state: stat.state })
+        })
+        .collect();
+    threads.sort_by_key(|thread| thread.tid);
+    threads
+}
+
+/// Whether reading a process failed because it has exited.
+fn is_gone(error: &io::Error) -> bool {
+    error.kind() == io::ErrorKind::NotFound || error.raw_os_error() == Some(libc::ESRCH)
+}
+
+/// Reads the details of `pid`. Fails with `NotFound` (or `ESRCH` while it is
+/// being reaped) once it has exited.
+pub fn read_details(roots: &Roots, pid: usize) -> io::Result<(ProcessDetails, ProcStat)> {
+    let stat = read_stat(roots, pid)?;
+    let dir = roots.proc(pid.to_string());
+
+    let mut parents = vec![];
+    let mut ppid = stat.ppid;
+    // Stop on a loop, which only a racing pid reuse could produce.
+    while ppid != 0 && parents.len() < 64 {
+        match read_stat(roots, ppid) {
+            Ok(parent) => {
+                parents.push((parent.pid, parent.comm));
+                ppid = parent.ppid;
+            }
+            Err(_) => break,
+        }
+    }
+
+    let details = ProcessDetails {
+        pid,
+        comm: stat.comm.clone(),
+        cmd: format_cmdline(&fs::read(dir.join("cmdline")).unwrap_or_default(), &stat.comm),
+        state: stat.state,
+        start_time: stat.start_time,
+        exited: false,
+        parents,
+        threads: read_threads(roots, pid),
+        fds: section(read_numbered_links(&dir.join("fd"))),
+        cwd: section(read_link(&dir.join("cwd"))),
+        exe: section(read_link(&dir.join("exe"))),
+        environ: section(fs::read(dir.join("environ")).map(|raw| parse_environ(&raw))),
+        limits: fs::read_to_string(dir.join("limits")).map(|limits| parse_limits(&limits)).unwrap_or_default(),
+        cgroup: fs::read_to_string(dir.join("cgroup"))
+            .map(|cgroup| cgroup.lines().map(str::to_string).collect())
+            .unwrap_or_default(),
+        namespaces: section(read_namespaces(&dir.join("ns"))),
+        history: VecDeque::new(),
+    };
+    Ok((details, stat))
+}
+
+/// Follows the process picked in the task list (`CollectedData::detail_pid`).
+pub struct ProcessDetailsCollector {
+    roots: Roots,
+    /// Ticks and uptime of the previous reading, for the CPU share.
+    previous: Option<(u64, f64)>,
+    clock_ticks: f64,
+    page_size: u64,
+}
+
+impl ProcessDetailsCollector {
+    pub fn new(roots: Roots) -> Self {
+        Self {
+            roots,
+            previous: None,
+            clock_ticks: super::clock_ticks(),
+            page_size: super::page_size(),
+        }
+    }
+}
+
+impl Collector for ProcessDetailsCollector {
+    fn name(&self) -> &'static str {
+        "process"
+    }
+
+    fn default_interval(&self) -> Duration {
+        Duration::from_secs(1)
+    }
+
+    fn collect(&mut self, shared_data: &Arc<Mutex<CollectedData>>) -> Result<(), CollectorError> {
+        let (pid, last) = {
+            let data = shared_data.lock().unwrap_or_else(PoisonError::into_inner);
+            let last = data.process_details.as_ref().filter(|details| Some(details.pid) == data.detail_pid);
+            (data.detail_pid, last.map(|details| (details.start_time, details.exited, details.history.clone())))
+        };
+        let pid = match pid {
+            Some(pid) => pid,
+            None => return Ok(()),
+        };
+        if let Some((_, true, _)) = last {
+            return Ok(());
+        }
+
+        let exited = |shared_data: &Arc<Mutex<CollectedData>>| {
+            let mut data = shared_data.lock().unwrap_or_else(PoisonError::into_inner);
+            if let Some(details) = data.process_details.as_mut().filter(|details| details.pid == pid) {
+                details.exited = true;
+            } else if data.detail_pid == Some(pid) {
+                // Gone before the first read: say so rather than nothing.
+                let cmd = data.process_list.iter().find(|p| p.pid == pid).map(|p| p.cmd.clone()).unwrap_or_default();
+                data.process_details = Some(ProcessDetails::gone(pid, cmd));
+            }
+        };
+        let uptime = read_uptime(&self.roots)?;
+        let (mut details, stat) = match read_details(&self.roots, pid) {
+            Ok(read) => read,
+            Err(error) if is_gone(&error) => {
+                exited(shared_data);
+                return Ok(());
+            }
+            Err(error) => return Err(CollectorError::io(self.roots.proc(pid.to_string()), error)),
+        };
+
+        let mut history = match last {
+            // Same pid but a later start time: ours is gone and the pid reused.
+            Some((start_time, _, _)) if start_time != details.start_time => {
+                exited(shared_data);
+                return Ok(());
+            }
+            Some((_, _, history)) => history,
+            None => {
+                self.previous = None;
+                VecDeque::new()
+            }
+        };
+        let cpu = match self.previous {
+            Some((ticks, previous_uptime)) if uptime > previous_uptime => {
+                stat.ticks.saturating_sub(ticks) as f64 / self.clock_ticks / (uptime - previous_uptime) * 100.0
+            }
+            _ => 0.0,
+        };
+        self.previous = Some((stat.ticks, uptime));
+        history.push_front((cpu, stat.rss_pages * self.page_size));
+        history.truncate(MAX_POINTS);
+        details.history = history;
+
+        let mut data = shared_data.lock().unwrap_or_else(PoisonError::into_inner);
+        // The selection may have changed while reading.
+        if data.detail_pid == Some(pid) {
+            data.process_details = Some(details);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collectors::fixtures;
+
+    #[test]
+    fn parses_recorded_limits() {
+        let limits = parse_limits(&fixtures::read("host/proc/11947/limits"));
+        assert_eq!(limits.len(), 16);
+        assert_eq!(
+            limits[3],
+            Limit {
+                name: String::from("Max stack size"),
+                soft: String::from("8388608"),
+                hard: String::from("unlimited"),
+                units: String::from("bytes"),
+            }
+        );
+        assert_eq!(limits[13].units, "");
+    }
+
+    #[test]
+    fn reads_the_recorded_process() {
+        let (details, _) = read_details(&fixtures::host_roots(), 11947).unwrap();
+        assert_eq!(details.cmd, "sleep 1000");
+        // The parent isn't part of the recording.
+        assert!(details.parents.is_empty());
+        assert_eq!(details.threads, vec![ThreadInfo { tid: 11947, comm: String::from("sleep"), state: 'S' }]);
+        assert_eq!(details.fds.unwrap()[1], (1, String::from("pipe:[52411]")));
+        assert_eq!(details.cwd.unwrap(), "/root");
+        assert_eq!(details.exe.unwrap(), "/usr/bin/sleep");
+        assert_eq!(details.environ.unwrap(), ["PATH=/usr/local/bin:/usr/bin:/bin", "HOME=/root", "LANG=C.UTF-8"]);
+        assert_eq!(details.cgroup, ["0::/system.slice/sleep.service"]);
+        assert_eq!(details.namespaces.unwrap()[0], (String::from("net"), String::from("net:[4026531833]")));
+    }
+
+    #[test]
+    fn collector_marks_exited_processes() {
+        let shared_data = Arc::new(Mutex::new(CollectedData { detail_pid: Some(11947), ..Default::default() }));
+        let mut collector = ProcessDetailsCollector::new(fixtures::host_roots());
+        collector.collect(&shared_data).unwrap();
+        collector.collect(&shared_data).unwrap();
+        let history_len = |data: &CollectedData| data.process_details.as_ref().unwrap().history.len();
+        assert_eq!(history_len(&shared_data.lock().unwrap()), 2);
+
+        // Pretend we were following a pid missing from the recording.
+        {
+            let mut data = shared_data.lock().unwrap();
+            data.detail_pid = Some(4242);
+            data.process_details.as_mut().unwrap().pid = 4242;
+        }
+        collector.collect(&shared_data).unwrap();
+        let data = shared_data.lock().unwrap();
+        assert!(data.process_details.as_ref().unwrap().exited);
+        assert_eq!(history_len(&data), 2);
+    }
+
+    #[test]
+    fn collector_reports_processes_gone_before_the_first_read() {
+        let shared_data = Arc::new(Mutex::new(CollectedData { detail_pid: Some(4242), ..Default::default() }));
+        ProcessDetailsCollector::new(fixtures::host_roots()).collect(&shared_data).unwrap();
+        let data = shared_data.lock().unwrap();
+        let details = data.process_details.as_ref().unwrap();
+        assert_eq!((details.pid, details.exited), (4242, true));
+        assert!(details.history.is_empty());
+    }
+}
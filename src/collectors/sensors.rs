@@ -0,0 +1,314 @@
+use std::collections::{HashSet, VecDeque};
+use std::fmt::{self, Display};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::process::read_uptime;
+use super::{merge_samples, Collector, CollectorError, Roots, Sampled};
+use crate::CollectedData;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SensorKind {
+    #[default]
+    Temperature,
+    Fan,
+    Voltage,
+}
+
+impl SensorKind {
+    /// The hwmon attribute prefix and what its values are divided by.
+    fn hwmon(self) -> (&'static str, f64) {
+        match self {
+            SensorKind::Temperature => ("temp", 1000.0),
+            SensorKind::Fan => ("fan", 1.0),
+            SensorKind::Voltage => ("in", 1000.0),
+        }
+    }
+
+    pub fn unit(self) -> &'static str {
+        match self {
+            SensorKind::Temperature => "°C",
+            SensorKind::Fan => "RPM",
+            SensorKind::Voltage => "V",
+        }
+    }
+
+    pub fn format(self, value: f64) -> String {
+        match self {
+            SensorKind::Temperature => format!("{:.1} {}", value, self.unit()),
+            SensorKind::Fan => format!("{:.0} {}", value, self.unit()),
+            SensorKind::Voltage => format!("{:.3} {}", value, self.unit()),
+        }
+    }
+}
+
+impl Display for SensorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SensorKind::Temperature => write!(f, "Temperature"),
+            SensorKind::Fan => write!(f, "Fan"),
+            SensorKind::Voltage => write!(f, "Voltage"),
+        }
+    }
+}
+
+/// How close a reading is to its thresholds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SensorLevel {
+    Normal,
+    /// Past the high threshold, or within a tenth of the critical one.
+    High,
+    Critical,
+}
+
+/// A reading, in °C, RPM or volts.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Sensor {
+    /// Unique on the host, e.g. `hwmon0/temp1` or `thermal_zone1`.
+    pub id: String,
+    /// The hwmon chip name, or `thermal` for thermal zones.
+    pub chip: String,
+    pub label: String,
+    pub kind: SensorKind,
+    pub value: f64,
+    pub max: Option<f64>,
+    pub crit: Option<f64>,
+    pub sampled_at: f64,
+    #[serde(skip)]
+    pub history: VecDeque<f64>,
+}
+
+impl Sampled for Sensor {
+    type Value = f64;
+
+    fn key(&self) -> &str {
+        &self.id
+    }
+
+    fn sampled_at(&self) -> f64 {
+        self.sampled_at
+    }
+
+    fn value(&self) -> Option<f64> {
+        Some(self.value)
+    }
+
+    fn history(&mut self) -> &mut VecDeque<f64> {
+        &mut self.history
+    }
+}
+
+impl Sensor {
+    pub fn level(&self) -> SensorLevel {
+        match (self.max, self.crit) {
+            (_, Some(crit)) if self.value >= crit => SensorLevel::Critical,
+            (Some(max), _) if self.value >= max => SensorLevel::High,
+            (_, Some(crit)) if self.value >= crit * 0.9 => SensorLevel::High,
+            _ => SensorLevel::Normal,
+        }
+    }
+}
+
+fn read_number(path: &Path) -> Option<f64> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// The sensors of one `/sys/class/hwmon/hwmonN`, by kind and index.
+fn hwmon_sensors(hwmon: &Path, uptime: f64) -> Vec<Sensor> {
+    let hwmon_name = hwmon.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    // Older drivers keep their attributes on the device instead.
+    let dir = if hwmon.join("name").exists() { hwmon.to_path_buf() } else { hwmon.join("device") };
+    let chip = fs::read_to_string(dir.join("name")).map(|name| name.trim().to_string()).unwrap_or_default();
+
+    let mut inputs: Vec<(SensorKind, u32)> = fs::read_dir(&dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let channel = name.strip_suffix("_input")?;
+            [SensorKind::Temperature, SensorKind::Fan, SensorKind::Voltage].into_iter().find_map(|kind| {
+                let index = channel.strip_prefix(kind.hwmon().0)?.parse().ok()?;
+                Some((kind, index))
+            })
+        })
+        .collect();
+    inputs.sort_unstable();
+
+    inputs
+        .into_iter()
+        .filter_map(|(kind, index)| {
+            let (prefix, scale) = kind.hwmon();
+            let channel = format!("{}{}", prefix, index);
+            let attribute = |suffix: &str| read_number(&dir.join(format!("{}_{}", channel, suffix))).map(|value| value / scale);
+            Some(Sensor {
+                id: format!("{}/{}", hwmon_name, channel),
+                chip: chip.clone(),
+                label: fs::read_to_string(dir.join(format!("{}_label", channel)))
+                    .map(|label| label.trim().to_string())
+                    .unwrap_or_else(|_| channel.clone()),
+                kind,
+                // Reading a sensor that is switched off fails with ENODATA.
+                value: attribute("input")?,
+                max: attribute("max"),
+                crit: attribute("crit"),
+                sampled_at: uptime,
+                history: VecDeque::new(),
+            })
+        })
+        .collect()
+}
+
+/// A `/sys/class/thermal/thermal_zoneN`, with its hot and critical trip
+/// points as thresholds.
+fn thermal_zone(dir: &Path, uptime: f64) -> Option<Sensor> {
+    let zone = dir.file_name()?.to_string_lossy().into_owned();
+    let label = fs::read_to_string(dir.join("type")).ok()?.trim().to_string();
+    let mut sensor = Sensor {
+        id: zone,
+        chip: String::from("thermal"),
+        label,
+        kind: SensorKind::Temperature,
+        value: read_number(&dir.join("temp"))? / 1000.0,
+        max: None,
+        crit: None,
+        sampled_at: uptime,
+        history: VecDeque::new(),
+    };
+    for trip in 0.. {
+        let kind = match fs::read_to_string(dir.join(format!("trip_point_{}_type", trip))) {
+            Ok(kind) => kind,
+            Err(_) => break,
+        };
+        let temp = read_number(&dir.join(format!("trip_point_{}_temp", trip))).map(|temp| temp / 1000.0);
+        match kind.trim() {
+            "critical" => sensor.crit = temp,
+            "hot" => sensor.max = temp,
+            _ => {}
+        }
+    }
+    Some(sensor)
+}
+
+fn sorted_entries(dir: PathBuf, prefix: &str) -> Vec<PathBuf> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with(prefix))
+        .map(|entry| entry.path())
+        .collect();
+    // hwmon10 after hwmon9.
+    entries.sort_by_key(|path| {
+        let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        (name.len(), name)
+    });
+    entries
+}
+
+/// Temperatures, fans and voltages from `/sys/class/hwmon`, and the
+/// thermal zones no hwmon chip already covers.
+pub struct SensorCollector {
+    roots: Roots,
+}
+
+impl SensorCollector {
+    pub fn new(roots: Roots) -> Self {
+        Self { roots }
+    }
+
+    fn sample(&self, uptime: f64) -> Vec<Sensor> {
+        let mut sensors: Vec<Sensor> = sorted_entries(self.roots.sys("class/hwmon"), "hwmon")
+            .iter()
+            .flat_map(|dir| hwmon_sensors(dir, uptime))
+            .collect();
+        // Thermal zones register a hwmon chip named after their type.
+        let chips: HashSet<String> = sensors.iter().map(|sensor| sensor.chip.clone()).collect();
+        sensors.extend(
+            sorted_entries(self.roots.sys("class/thermal"), "thermal_zone")
+                .iter()
+                .filter_map(|dir| thermal_zone(dir, uptime))
+                .filter(|zone| !chips.contains(&zone.label)),
+        );
+        sensors
+    }
+}
+
+impl Collector for SensorCollector {
+    fn name(&self) -> &'static str {
+        "sensors"
+    }
+
+    fn default_interval(&self) -> Duration {
+        Duration::from_secs(2)
+    }
+
+    /// Machines without sensors, such as most VMs, get an empty list.
+    fn collect(&mut self, shared_data: &Arc<Mutex<CollectedData>>) -> Result<(), CollectorError> {
+        let uptime = read_uptime(&self.roots)?;
+        let samples = self.sample(uptime);
+
+        let mut data = shared_data.lock().unwrap_or_else(PoisonError::into_inner);
+        merge_samples(&mut data.sensors, samples);
+        data.updated_sensors = true;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collectors::fixtures;
+
+    #[test]
+    fn collector_reads_hwmon_and_thermal_zones() {
+        let shared_data = Arc::new(Mutex::new(CollectedData::default()));
+        SensorCollector::new(fixtures::host_roots()).collect(&shared_data).unwrap();
+        let data = shared_data.lock().unwrap();
+        let layout: Vec<(&str, &str, &str)> =
+            data.sensors.iter().map(|s| (s.id.as_str(), s.chip.as_str(), s.label.as_str())).collect();
+        // The acpitz zone is already hwmon2.
+        assert_eq!(
+            layout,
+            [
+                ("hwmon0/temp1", "coretemp", "Package id 0"),
+                ("hwmon0/temp2", "coretemp", "Core 0"),
+                ("hwmon1/fan1", "nct6775", "fan1"),
+                ("hwmon1/in0", "nct6775", "Vcore"),
+                ("hwmon2/temp1", "acpitz", "temp1"),
+                ("thermal_zone1", "thermal", "iwlwifi_1"),
+            ]
+        );
+
+        let package = &data.sensors[0];
+        assert_eq!((package.value, package.max, package.crit), (45.0, Some(80.0), Some(100.0)));
+        assert_eq!((data.sensors[2].kind, data.sensors[2].value), (SensorKind::Fan, 1200.0));
+        assert_eq!((data.sensors[3].value, data.sensors[3].max), (1.04, Some(1.5)));
+        let wifi = &data.sensors[5];
+        assert_eq!((wifi.value, wifi.max, wifi.crit), (38.0, Some(90.0), Some(110.0)));
+        assert!(data.updated_sensors);
+    }
+
+    #[test]
+    fn levels_rise_towards_the_critical_threshold() {
+        let sensor = |value, max, crit| Sensor { value, max, crit, ..Default::default() };
+        assert_eq!(sensor(45.0, Some(80.0), Some(100.0)).level(), SensorLevel::Normal);
+        assert_eq!(sensor(85.0, Some(80.0), Some(100.0)).level(), SensorLevel::High);
+        assert_eq!(sensor(93.0, None, Some(100.0)).level(), SensorLevel::High);
+        assert_eq!(sensor(100.0, Some(80.0), Some(100.0)).level(), SensorLevel::Critical);
+        assert_eq!(sensor(1200.0, None, None).level(), SensorLevel::Normal);
+    }
+
+    #[test]
+    fn no_sensors_is_not_an_error() {
+        let roots = Roots { sys: fixtures::path("host/missing"), ..fixtures::host_roots() };
+        let shared_data = Arc::new(Mutex::new(CollectedData::default()));
+        SensorCollector::new(roots).collect(&shared_data).unwrap();
+        assert!(shared_data.lock().unwrap().sensors.is_empty());
+    }
+}
@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::process::parse_stat;
+use super::{read_file, Collector, CollectorError, Roots};
+use crate::CollectedData;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    Tcp,
+    Tcp6,
+    Udp,
+    Udp6,
+}
+
+impl Protocol {
+    pub const ALL: [Protocol; 4] = [Protocol::Tcp, Protocol::Tcp6, Protocol::Udp, Protocol::Udp6];
+
+    /// Also the name of its table under `/proc/net`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Protocol::Tcp => "tcp",
+            Protocol::Tcp6 => "tcp6",
+            Protocol::Udp => "udp",
+            Protocol::Udp6 => "udp6",
+        }
+    }
+
+    fn is_tcp(self) -> bool {
+        matches!(self, Protocol::Tcp | Protocol::Tcp6)
+    }
+}
+
+impl Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// A socket with the process holding it, when we may look at its fds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SocketInfo {
+    pub protocol: Protocol,
+    /// As ss names them, e.g. `LISTEN`, `ESTAB`, `UNCONN`.
+    pub state: String,
+    pub local: SocketAddr,
+    pub remote: SocketAddr,
+    pub inode: u64,
+    pub pid: Option<usize>,
+    pub process: String,
+}
+
+impl SocketInfo {
+    /// Case-insensitive substring of any column; `text` is lowercase.
+    pub fn matches(&self, text: &str) -> bool {
+        let pid = self.pid.map(|pid| pid.to_string()).unwrap_or_default();
+        [self.protocol.name(), &self.state, &self.local.to_string(), &self.remote.to_string(), &pid, &self.process]
+            .iter()
+            .any(|column| column.to_lowercase().contains(text))
+    }
+
+    /// Whether the socket holds `port` on this host. Connecting to that
+    /// port elsewhere doesn't count.
+    pub fn uses_port(&self, port: u16) -> bool {
+        self.local.port() == port
+    }
+}
+
+fn state_name(protocol: Protocol, state: u8) -> &'static str {
+    match (state, protocol.is_tcp()) {
+        (0x01, _) => "ESTAB",
+        (0x02, _) => "SYN-SENT",
+        (0x03, _) => "SYN-RECV",
+        (0x04, _) => "FIN-WAIT-1",
+        (0x05, _) => "FIN-WAIT-2",
+        (0x06, _) => "TIME-WAIT",
+        // A UDP socket that isn't connected sits in CLOSE.
+        (0x07, false) => "UNCONN",
+        (0x07, true) => "CLOSE",
+        (0x08, _) => "CLOSE-WAIT",
+        (0x09, _) => "LAST-ACK",
+        (0x0A, _) => "LISTEN",
+        (0x0B, _) => "CLOSING",
+        (0x0C, _) => "NEW-SYN-RECV",
+        _ => "UNKNOWN",
+    }
+}
+
+/// Reads `0100007F:0035`: the address as the kernel's 32-bit words in
+/// host byte order, then the port.
+fn parse_address(field: &str) -> Option<SocketAddr> {
+    let (address, port) = field.split_once(':')?;
+    let port = u16::from_str_radix(port, 16).ok()?;
+    let mut bytes = vec![];
+    for word in address.as_bytes().chunks(8) {
+        let word = u32::from_str_radix(std::str::from_utf8(word).ok()?, 16).ok()?;
+        bytes.extend_from_slice(&word.to_ne_bytes());
+    }
+    let ip = match bytes.len() {
+        4 => IpAddr::V4(Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3])),
+        16 => IpAddr::V6(Ipv6Addr::from(<[u8; 16]>::try_from(bytes).ok()?)),
+        _ => return None,
+    };
+    Some(SocketAddr::new(ip, port))
+}
+
+/// Parses one of `/proc/net/{tcp,tcp6,udp,udp6}`, without the owners.
+pub fn parse_proc_net(content: &str, protocol: Protocol) -> Vec<SocketInfo> {
+    content
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            Some(SocketInfo {
+                protocol,
+                state: state_name(protocol, u8::from_str_radix(fields.get(3)?, 16).ok()?).to_string(),
+                local: parse_address(fields.get(1)?)?,
+                remote: parse_address(fields.get(2)?)?,
+                inode: fields.get(9)?.parse().ok()?,
+                pid: None,
+                process: String::new(),
+            })
+        })
+        .collect()
+}
+
+/// Reads `socket:[12345]`, the target of a socket's fd link.
+pub fn parse_socket_link(target: &str) -> Option<u64> {
+    target.strip_prefix("socket:[")?.strip_suffix(']')?.parse().ok()
+}
+
+/// Maps socket inodes to the lowest PID holding them, going through every
+/// process's fds we may read.
+fn socket_owners(roots: &Roots) -> HashMap<u64, usize> {
+    let mut owners = HashMap::new();
+    let pids = fs::read_dir(&roots.proc)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<usize>().ok());
+    for pid in pids {
+        // Other users' fds need privileges; the process may also be gone.
+        let fds = match fs::read_dir(roots.proc(format!("{}/fd", pid))) {
+            Ok(fds) => fds,
+            Err(_) => continue,
+        };
+        for fd in fds.flatten() {
+            let inode = fs::read_link(fd.path()).ok().and_then(|target| parse_socket_link(&target.to_string_lossy()));
+            if let Some(inode) = inode {
+                let owner = owners.entry(inode).or_insert(pid);
+                *owner = (*owner).min(pid);
+            }
+        }
+    }
+    owners
+}
+
+/// Lists the TCP and UDP sockets and who holds them.
+///
+/// With a foreign procfs the tables of the host's init namespace are read.
+pub struct SocketCollector {
+    roots: Roots,
+}
+
+impl SocketCollector {
+    pub fn new(roots: Roots) -> Self {
+        Self { roots }
+    }
+
+    fn table(&self, protocol: Protocol) -> Result<Vec<SocketInfo>, CollectorError> {
+        let path = if self.roots.is_foreign() {
+            self.roots.proc(format!("1/net/{}", protocol.name()))
+        } else {
+            self.roots.proc(format!("net/{}", protocol.name()))
+        };
+        // Without IPv6 support the tcp6 and udp6 tables don't exist.
+        match fs::metadata(&path) {
+            Ok(_) => Ok(parse_proc_net(&read_file(&path)?, protocol)),
+            Err(_) if matches!(protocol, Protocol::Tcp6 | Protocol::Udp6) => Ok(vec![]),
+            Err(error) => Err(CollectorError::io(path, error)),
+        }
+    }
+}
+
+impl Collector for SocketCollector {
+    fn name(&self) -> &'static str {
+        "sockets"
+    }
+
+    fn default_interval(&self) -> Duration {
+        Duration::from_secs(3)
+    }
+
+    fn collect(&mut self, shared_data: &Arc<Mutex<CollectedData>>) -> Result<(), CollectorError> {
+        let mut sockets = vec![];
+        for protocol in Protocol::ALL {
+            sockets.extend(self.table(protocol)?);
+        }
+        let owners = socket_owners(&self.roots);
+        let mut names: HashMap<usize, String> = HashMap::new();
+        for socket in &mut sockets {
+            socket.pid = owners.get(&socket.inode).copied();
+            if let Some(pid) = socket.pid {
+                socket.process = names
+                    .entry(pid)
+                    .or_insert_with(|| {
+                        let stat = fs::read_to_string(self.roots.proc(format!("{}/stat", pid))).unwrap_or_default();
+                        parse_stat(&stat).map(|stat| stat.comm).unwrap_or_default()
+                    })
+                    .clone();
+            }
+        }
+
+        let mut data = shared_data.lock().unwrap_or_else(PoisonError::into_inner);
+        data.sockets = sockets;
+        data.updated_sockets = true;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collectors::fixtures;
+
+    #[test]
+    fn parses_recorded_tables() {
+        let tcp = parse_proc_net(&fixtures::read("host/proc/1/net/tcp"), Protocol::Tcp);
+        assert_eq!(tcp.len(), 2);
+        assert_eq!((tcp[0].state.as_str(), tcp[0].local.to_string(), tcp[0].inode), ("LISTEN", String::from("0.0.0.0:8080"), 52500));
+        assert_eq!((tcp[1].state.as_str(), tcp[1].local.to_string()), ("ESTAB", String::from("10.0.2.15:22")));
+        assert_eq!(tcp[1].remote.to_string(), "10.0.2.2:54321");
+
+        let tcp6 = parse_proc_net(&fixtures::read("host/proc/1/net/tcp6"), Protocol::Tcp6);
+        assert_eq!(tcp6[0].local.to_string(), "[::1]:631");
+        let udp = parse_proc_net(&fixtures::read("host/proc/1/net/udp"), Protocol::Udp);
+        assert_eq!((udp[0].state.as_str(), udp[0].local.to_string()), ("UNCONN", String::from("127.0.0.53:53")));
+        assert!(parse_proc_net(&fixtures::read("host/proc/1/net/udp6"), Protocol::Udp6).is_empty());
+    }
+
+    #[test]
+    fn collector_finds_the_owning_process() {
+        let shared_data = Arc::new(Mutex::new(CollectedData::default()));
+        SocketCollector::new(fixtures::host_roots()).collect(&shared_data).unwrap();
+        let data = shared_data.lock().unwrap();
+        assert_eq!(data.sockets.len(), 4);
+        assert_eq!((data.sockets[0].pid, data.sockets[0].process.as_str()), (Some(11947), "sleep"));
+        // Nothing recorded holds the ssh connection.
+        assert_eq!(data.sockets[1].pid, None);
+
+        assert!(data.sockets[0].matches("sleep"));
+        assert!(data.sockets[1].matches("estab"));
+        assert!(data.sockets[1].uses_port(22));
+        // 54321 is the client's end of the ssh connection.
+        assert!(!data.sockets[1].uses_port(54321));
+        assert!(!data.sockets[0].uses_port(22));
+        assert_eq!(parse_socket_link("pipe:[52411]"), None);
+    }
+}
@@ -0,0 +1,393 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use super::Roots;
+
+/// Facts about the machine that don't change while the dashboard runs.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SystemInfo {
+    pub hostname: String,
+    pub kernel: String,
+    pub kernel_version: String,
+    pub architecture: String,
+    pub distribution: String,
+    /// Seconds since the Unix epoch.
+    pub boot_time: u64,
+    pub cpu_model: String,
+    pub topology: CpuTopology,
+    pub total_memory: u64,
+    pub virtualization: Option<String>,
+    pub container: Option<String>,
+    pub devices: Vec<Device>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CpuTopology {
+    pub sockets: usize,
+    pub cores: usize,
+    pub threads: usize,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Device {
+    pub class: String,
+    pub description: String,
+    /// Capacity in bytes, for disks.
+    #[serde(default)]
+    pub size: Option<u64>,
+}
+
+impl SystemInfo {
+    /// Time since boot as of `now`, which is in the past when replaying.
+    pub fn uptime(&self, now: SystemTime) -> Duration {
+        let boot = UNIX_EPOCH + Duration::from_secs(self.boot_time);
+        now.duration_since(boot).unwrap_or_default()
+    }
+}
+
+fn read_trimmed(path: impl AsRef<Path>) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string()).filter(|s| !s.is_empty())
+}
+
+/// Returns the value of `key` in an os-release file, without quotes.
+pub fn parse_os_release(content: &str, key: &str) -> Option<String> {
+    content.lines().find_map(|line| {
+        let (k, v) = line.split_once('=')?;
+        (k.trim() == key).then(|| v.trim().trim_matches('"').trim_matches('\'').to_string())
+    })
+}
+
+pub fn parse_boot_time(proc_stat: &str) -> Option<u64> {
+    proc_stat
+        .lines()
+        .find_map(|line| line.strip_prefix("btime "))
+        .and_then(|v| v.trim().parse().ok())
+}
+
+/// Extracts the CPU model and the socket/core/thread counts from `/proc/cpuinfo`.
+pub fn parse_cpuinfo(content: &str) -> (String, CpuTopology) {
+    let mut model = None;
+    let mut threads = 0;
+    let mut sockets = HashSet::new();
+    let mut cores = HashSet::new();
+    let mut physical_id = None;
+
+    for line in content.lines() {
+        let (key, value) = match line.split_once(':') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => continue,
+        };
+        match key {
+            "processor" => threads += 1,
+            "model name" | "Hardware" | "cpu model" | "Model" if model.is_none() => {
+                model = Some(value.to_string());
+            }
+            "physical id" => {
+                sockets.insert(value.to_string());
+                physical_id = Some(value.to_string());
+            }
+            "core id" => {
+                cores.insert((physical_id.clone(), value.to_string()));
+            }
+            _ => {}
+        }
+    }
+
+    // Architectures without "physical id"/"core id" (most ARM boards) only
+    // list processors.
+    let topology = CpuTopology {
+        sockets: sockets.len().max(1),
+        cores: if cores.is_empty() { threads } else { cores.len() },
+        threads,
+    };
+    (model.unwrap_or_else(|| String::from("unknown")), topology)
+}
+
+fn detect_virtualization(roots: &Roots, cpuinfo: &str) -> Option<String> {
+    if let Some(hypervisor) = read_trimmed(roots.sys("hypervisor/type")) {
+        return Some(hypervisor);
+    }
+    let has_flag = cpuinfo
+        .lines()
+        .filter(|line| line.starts_with("flags"))
+        .any(|line| line.split_whitespace().any(|flag| flag == "hypervisor"));
+    if !has_flag {
+        return None;
+    }
+
+    let dmi = [
+        read_trimmed(roots.sys("class/dmi/id/sys_vendor")),
+        read_trimmed(roots.sys("class/dmi/id/product_name")),
+        read_trimmed(roots.sys("class/dmi/id/bios_vendor")),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>()
+    .join(" ");
+    let known = [
+        ("QEMU", "QEMU"),
+        ("KVM", "KVM"),
+        ("VMware", "VMware"),
+        ("VirtualBox", "VirtualBox"),
+        ("innotek", "VirtualBox"),
+        ("Microsoft", "Hyper-V"),
+        ("Xen", "Xen"),
+        ("Amazon", "Amazon EC2"),
+        ("Google", "Google Compute Engine"),
+        ("Parallels", "Parallels"),
+        ("BHYVE", "bhyve"),
+    ];
+    Some(
+        known
+            .iter()
+            .find(|(needle, _)| dmi.contains(needle))
+            .map(|(_, name)| name.to_string())
+            .unwrap_or_else(|| String::from("unknown hypervisor")),
+    )
+}
+
+fn detect_container(roots: &Roots) -> Option<String> {
+    if roots.host_path("/.dockerenv").exists() {
+        return Some(String::from("docker"));
+    }
+    if roots.host_path("/run/.containerenv").exists() {
+        return Some(String::from("podman"));
+    }
+    if let Ok(environ) = fs::read(roots.proc("1/environ")) {
+        let container = environ
+            .split(|&b| b == 0)
+            .find_map(|var| var.strip_prefix(b"container="))
+            .map(|name| String::from_utf8_lossy(name).into_owned());
+        if container.is_some() {
+            return container;
+        }
+    }
+    let cgroup = fs::read_to_string(roots.proc("1/cgroup")).unwrap_or_default();
+    ["kubepods", "docker", "lxc", "containerd"]
+        .iter()
+        .find(|name| cgroup.contains(*name))
+        .map(|name| name.to_string())
+}
+
+fn pci_class_name(class: u32) -> &'static str {
+    match class >> 16 {
+        0x01 => "Storage controller",
+        0x02 => "Network controller",
+        0x03 => "Display controller",
+        0x04 => "Multimedia controller",
+        0x05 => "Memory controller",
+        0x06 => "Bridge",
+        0x07 => "Communication controller",
+        0x08 => "System peripheral",
+        0x09 => "Input device",
+        0x0C => "Serial bus controller",
+        0x0D => "Wireless controller",
+        0x10 => "Encryption controller",
+        0x12 => "Processing accelerator",
+        _ => "Other device",
+    }
+}
+
+/// Parses the vendor and device names out of a `pci.ids` database.
+pub fn parse_pci_ids(content: &str) -> HashMap<(u16, Option<u16>), String> {
+    let mut names = HashMap::new();
+    let mut vendor = None;
+    for line in content.lines() {
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+        // Device classes come after all the vendors.
+        if line.starts_with("C ") {
+            break;
+        }
+        let depth = line.chars().take_while(|&c| c == '\t').count();
+        let (id, name) = match line.trim().split_once(' ') {
+            Some((id, name)) => (u16::from_str_radix(id, 16).ok(), name.trim().to_string()),
+            None => continue,
+        };
+        match (depth, id) {
+            (0, Some(id)) => {
+                vendor = Some(id);
+                names.insert((id, None), name);
+            }
+            (1, Some(id)) => {
+                if let Some(vendor) = vendor {
+                    names.insert((vendor, Some(id)), name);
+                }
+            }
+            _ => {}
+        }
+    }
+    names
+}
+
+fn read_hex(path: impl AsRef<Path>) -> Option<u32> {
+    let value = read_trimmed(path)?;
+    u32::from_str_radix(value.trim_start_matches("0x"), 16).ok()
+}
+
+/// The machine hardware name, e.g. `x86_64`, of the running kernel rather
+/// than of our build. Kernels before 6.1 lack `kernel/arch`, so fall back to
+/// uname, which containers share with the host.
+fn read_architecture(roots: &Roots) -> Option<String> {
+    read_trimmed(roots.proc("sys/kernel/arch")).or_else(|| {
+        let mut name: libc::utsname = unsafe { std::mem::zeroed() };
+        if unsafe { libc::uname(&mut name) } != 0 {
+            return None;
+        }
+        let machine = unsafe { std::ffi::CStr::from_ptr(name.machine.as_ptr()) };
+        Some(machine.to_string_lossy().into_owned()).filter(|machine| !machine.is_empty())
+    })
+}
+
+fn driver_name(device: &Path) -> Option<String> {
+    fs::read_link(device.join("driver"))
+        .ok()
+        .and_then(|link| link.file_name().map(|name| name.to_string_lossy().into_owned()))
+}
+
+fn collect_devices(roots: &Roots) -> Vec<Device> {
+    let pci_ids = ["/usr/share/hwdata/pci.ids", "/usr/share/misc/pci.ids", "/usr/share/pci.ids"]
+        .iter()
+        .find_map(|path| fs::read_to_string(roots.host_path(path)).ok())
+        .map(|content| parse_pci_ids(&content))
+        .unwrap_or_default();
+
+    let mut devices = vec![];
+
+    let mut pci: Vec<_> = fs::read_dir(roots.sys("bus/pci/devices"))
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+        .unwrap_or_default();
+    pci.sort();
+    for path in pci {
+        let class = read_hex(path.join("class")).unwrap_or(0);
+        let vendor = read_hex(path.join("vendor")).unwrap_or(0) as u16;
+        let device = read_hex(path.join("device")).unwrap_or(0) as u16;
+        let name = match (pci_ids.get(&(vendor, None)), pci_ids.get(&(vendor, Some(device)))) {
+            (Some(vendor_name), Some(device_name)) => format!("{} {}", vendor_name, device_name),
+            (Some(vendor_name), None) => format!("{} [{:04x}]", vendor_name, device),
+            _ => format!("[{:04x}:{:04x}]", vendor, device),
+        };
+        let address = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        let driver = driver_name(&path).map(|d| format!(" ({})", d)).unwrap_or_default();
+        devices.push(Device {
+            class: pci_class_name(class).to_string(),
+            description: format!("{} {}{}", address, name, driver),
+            size: None,
+        });
+    }
+
+    let mut blocks: Vec<_> = fs::read_dir(roots.sys("block"))
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+        .unwrap_or_default();
+    blocks.sort();
+    for path in blocks {
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        if name.starts_with("loop") || name.starts_with("ram") {
+            continue;
+        }
+        let sectors: u64 = read_trimmed(path.join("size")).and_then(|s| s.parse().ok()).unwrap_or(0);
+        let model = read_trimmed(path.join("device/model")).unwrap_or_default();
+        devices.push(Device {
+            class: String::from("Disk"),
+            description: format!("{} {}", name, model).trim_end().to_string(),
+            size: Some(sectors * 512),
+        });
+    }
+
+    let mut interfaces: Vec<_> = fs::read_dir(roots.sys("class/net"))
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+        .unwrap_or_default();
+    interfaces.sort();
+    for path in interfaces {
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        if name == "lo" {
+            continue;
+        }
+        let mac = read_trimmed(path.join("address")).unwrap_or_default();
+        let driver = driver_name(&path.join("device")).map(|d| format!(" ({})", d)).unwrap_or_default();
+        devices.push(Device {
+            class: String::from("Network interface"),
+            description: format!("{} {}{}", name, mac, driver),
+            size: None,
+        });
+    }
+
+    devices
+}
+
+pub fn read_system_info(roots: &Roots) -> SystemInfo {
+    let cpuinfo = fs::read_to_string(roots.proc("cpuinfo")).unwrap_or_default();
+    let (cpu_model, topology) = parse_cpuinfo(&cpuinfo);
+    let os_release = fs::read_to_string(roots.etc("os-release"))
+        .or_else(|_| fs::read_to_string(roots.host_path("/usr/lib/os-release")))
+        .unwrap_or_default();
+    let unknown = || String::from("unknown");
+
+    SystemInfo {
+        hostname: read_trimmed(roots.proc("sys/kernel/hostname")).unwrap_or_else(unknown),
+        kernel: format!(
+            "{} {}",
+            read_trimmed(roots.proc("sys/kernel/ostype")).unwrap_or_else(|| String::from("Linux")),
+            read_trimmed(roots.proc("sys/kernel/osrelease")).unwrap_or_else(unknown),
+        ),
+        kernel_version: read_trimmed(roots.proc("sys/kernel/version")).unwrap_or_else(unknown),
+        architecture: read_architecture(roots).unwrap_or_else(unknown),
+        distribution: parse_os_release(&os_release, "PRETTY_NAME")
+            .or_else(|| parse_os_release(&os_release, "NAME"))
+            .unwrap_or_else(unknown),
+        boot_time: fs::read_to_string(roots.proc("stat"))
+            .ok()
+            .and_then(|stat| parse_boot_time(&stat))
+            .unwrap_or(0),
+        cpu_model,
+        topology,
+        total_memory: super::memory::read_meminfo(roots).map(|m| m.total).unwrap_or(0),
+        virtualization: detect_virtualization(roots, &cpuinfo),
+        container: detect_container(roots),
+        devices: collect_devices(roots),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collectors::fixtures;
+
+    #[test]
+    fn parses_recorded_cpuinfo() {
+        let (model, topology) = parse_cpuinfo(&fixtures::read("host/proc/cpuinfo"));
+        assert_eq!(model, "Intel(R) Xeon(R) Processor @ 2.10GHz");
+        assert_eq!(topology, CpuTopology { sockets: 1, cores: 1, threads: 1 });
+    }
+
+    #[test]
+    fn parses_os_release_and_boot_time() {
+        let os_release = fixtures::read("host/etc/os-release");
+        assert_eq!(parse_os_release(&os_release, "PRETTY_NAME").as_deref(), Some("Debian GNU/Linux 12 (bookworm)"));
+        assert_eq!(parse_os_release(&os_release, "VERSION_ID").as_deref(), Some("12"));
+        assert_eq!(parse_boot_time(&fixtures::read("host/proc/stat")), Some(1792306333));
+    }
+
+    #[test]
+    fn reads_everything_from_the_configured_roots() {
+        let info = read_system_info(&fixtures::host_roots());
+        assert_eq!(info.hostname, "vm");
+        assert_eq!(info.kernel, "Linux 6.18.44-fc-v139");
+        assert_eq!(info.architecture, "x86_64");
+        assert_eq!(info.distribution, "Debian GNU/Linux 12 (bookworm)");
+        assert_eq!(info.total_memory, 6158152 * 1024);
+        assert_eq!(info.virtualization.as_deref(), Some("unknown hypervisor"));
+        assert_eq!(
+            info.devices,
+            vec![
+                Device { class: String::from("Disk"), description: String::from("vda"), size: Some(256 * 1024 * 1024 * 1024) },
+                Device { class: String::from("Network interface"), description: String::from("eth0 02:fc:00:00:00:01"), size: None },
+            ]
+        );
+    }
+}
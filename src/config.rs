@@ -0,0 +1,236 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::collectors::disks::PartitionFilter;
+use crate::collectors::Roots;
+use crate::export::Format;
+
+pub const USAGE: &str = "\
+Usage: trabalho_so [OPTIONS]
+
+Options:
+  --proc-root DIR                 Read procfs from DIR instead of /proc, e.g. the
+                                  host's /proc mounted into a container
+  --sys-root DIR                  Read sysfs from DIR instead of /sys
+  --etc-root DIR                  Read passwd and os-release from DIR instead of /etc
+  --fs-include TYPE[,TYPE...]     Show these filesystem types even if excluded
+  --fs-exclude TYPE[,TYPE...]     Hide these filesystem types (added to tmpfs,
+                                  overlay, squashfs and other pseudo filesystems)
+  --mount-include PATH[,PATH...]  Show mounts at or below these paths even if excluded
+  --mount-exclude PATH[,PATH...]  Hide mounts at or below these paths
+  --record FILE                   Save every collection pass to FILE for replay
+  --replay FILE                   Show a recording instead of this machine
+  --interval [NAME=]SECONDS       Run a collector (tasks, cpu, memory, disks,
+                                  diskio, network, sockets, sensors), or all of
+                                  them, at this interval instead of the default
+  --openmetrics ADDR              Serve the metrics for Prometheus at
+                                  http://ADDR/metrics, e.g. 127.0.0.1:9184
+  --headless                      Stream the metrics instead of opening a window
+  --format json|csv               Output format of --headless (default json)
+  --output FILE                   Write --headless output to FILE instead of stdout
+  --metrics NAME[,NAME...]        Collectors to run with --headless or --agent
+                                  (default all)
+  --samples N                     Exit after N collection passes with --headless
+  --agent ADDR                    Run without a window and serve snapshots to
+                                  viewers on ADDR, e.g. 0.0.0.0:9185
+  --connect ADDR                  Show the agent at ADDR instead of this machine;
+                                  repeat to watch several hosts
+  -h, --help                      Print this help";
+
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    pub roots: Roots,
+    pub partition_filter: PartitionFilter,
+    /// Applied in order; no name means every collector.
+    pub intervals: Vec<(Option<String>, Duration)>,
+    pub record: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+    pub headless: bool,
+    pub format: Format,
+    pub output: Option<PathBuf>,
+    /// Empty means every collector.
+    pub metrics: Vec<String>,
+    pub samples: Option<u64>,
+    pub openmetrics: Option<String>,
+    pub agent: Option<String>,
+    pub connect: Vec<String>,
+}
+
+impl Config {
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Config, String> {
+        let mut config = Config::default();
+        let mut args = args.into_iter();
+        // The last option given that only --headless uses.
+        let mut headless_only = None;
+
+        while let Some(arg) = args.next() {
+            if arg == "--headless" {
+                config.headless = true;
+                continue;
+            }
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+                _ => (arg, None),
+            };
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("missing value for {}", flag))
+            };
+            let filter = &mut config.partition_filter;
+            match flag.as_str() {
+                "--proc-root" => config.roots.proc = PathBuf::from(value()?),
+                "--sys-root" => config.roots.sys = PathBuf::from(value()?),
+                "--etc-root" => config.roots.etc = PathBuf::from(value()?),
+                "--fs-include" => filter.include_fs_types.extend(split_list(&value()?)),
+                "--fs-exclude" => filter.exclude_fs_types.extend(split_list(&value()?)),
+                "--mount-include" => filter.include_mount_points.extend(split_list(&value()?)),
+                "--mount-exclude" => filter.exclude_mount_points.extend(split_list(&value()?)),
+                "--interval" => {
+                    let value = value()?;
+                    let interval = match value.split_once('=') {
+                        Some((name, secs)) => parse_seconds(secs).map(|secs| (Some(name.to_string()), secs)),
+                        None => parse_seconds(&value).map(|secs| (None, secs)),
+                    }
+                    .ok_or_else(|| format!("invalid interval {}, expected [NAME=]SECONDS", value))?;
+                    config.intervals.push(interval);
+                }
+                "--record" => config.record = Some(PathBuf::from(value()?)),
+                "--replay" => config.replay = Some(PathBuf::from(value()?)),
+                "--openmetrics" => config.openmetrics = Some(value()?),
+                "--agent" => config.agent = Some(value()?),
+                "--connect" => config.connect.push(value()?),
+                "--format" => {
+                    config.format = value()?.parse()?;
+                    headless_only = Some("--format");
+                }
+                "--output" => {
+                    config.output = Some(PathBuf::from(value()?));
+                    headless_only = Some("--output");
+                }
+                "--metrics" => config.metrics.extend(split_list(&value()?)),
+                "--samples" => {
+                    let value = value()?;
+                    let samples = value.parse().ok().filter(|&n| n > 0);
+                    config.samples = Some(samples.ok_or_else(|| format!("invalid sample count {}", value))?);
+                    headless_only = Some("--samples");
+                }
+                _ => return Err(format!("unknown option {}", flag)),
+            }
+        }
+        if let (false, Some(flag)) = (config.headless, headless_only) {
+            return Err(format!("{} needs --headless", flag));
+        }
+        // An agent serves only the metrics picked, too.
+        if !config.headless && config.agent.is_none() && !config.metrics.is_empty() {
+            return Err(String::from("--metrics needs --headless or --agent"));
+        }
+        if config.record.is_some() && config.replay.is_some() {
+            return Err(String::from("--record and --replay can't be used together"));
+        }
+        if config.openmetrics.is_some() && config.replay.is_some() {
+            return Err(String::from("--openmetrics and --replay can't be used together"));
+        }
+        if config.headless && config.replay.is_some() {
+            return Err(String::from("--headless and --replay can't be used together"));
+        }
+        if config.agent.is_some() && config.replay.is_some() {
+            return Err(String::from("--agent and --replay can't be used together"));
+        }
+        if !config.connect.is_empty() {
+            // Nothing is collected here when watching agents.
+            let conflicting = [
+                ("--replay", config.replay.is_some()),
+                ("--record", config.record.is_some()),
+                ("--openmetrics", config.openmetrics.is_some()),
+                ("--headless", config.headless),
+                ("--agent", config.agent.is_some()),
+            ];
+            if let Some((flag, _)) = conflicting.iter().find(|(_, set)| *set) {
+                return Err(format!("--connect and {} can't be used together", flag));
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+fn parse_seconds(value: &str) -> Option<Duration> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|secs| secs.is_finite() && *secs > 0.0)
+        .map(Duration::from_secs_f64)
+}
+
+fn split_list(value: &str) -> impl Iterator<Item = String> + '_ {
+    value.split(',').map(str::trim).filter(|s| !s.is_empty()).map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Config, String> {
+        Config::from_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses_roots_in_both_forms() {
+        let config = parse(&["--proc-root", "/host/proc", "--sys-root=/host/sys"]).unwrap();
+        assert_eq!(config.roots.proc, PathBuf::from("/host/proc"));
+        assert_eq!(config.roots.sys, PathBuf::from("/host/sys"));
+        assert_eq!(config.roots.etc, PathBuf::from("/etc"));
+    }
+
+    #[test]
+    fn parses_lists_and_intervals() {
+        let config = parse(&["--fs-include", "tmpfs, nfs", "--interval", "5", "--interval", "cpu=0.5"]).unwrap();
+        assert_eq!(config.partition_filter.include_fs_types, ["tmpfs", "nfs"]);
+        assert_eq!(
+            config.intervals,
+            vec![(None, Duration::from_secs(5)), (Some(String::from("cpu")), Duration::from_millis(500))]
+        );
+    }
+
+    #[test]
+    fn parses_headless_options() {
+        let config = parse(&["--headless", "--format=csv", "--metrics", "cpu,memory", "--samples", "3"]).unwrap();
+        assert!(config.headless);
+        assert_eq!(config.format, Format::Csv);
+        assert_eq!(config.metrics, ["cpu", "memory"]);
+        assert_eq!(config.samples, Some(3));
+        assert_eq!(config.output, None);
+    }
+
+    #[test]
+    fn parses_remote_options() {
+        let config = parse(&["--connect", "db1:9185", "--connect=db2:9185"]).unwrap();
+        assert_eq!(config.connect, ["db1:9185", "db2:9185"]);
+        assert_eq!(
+            parse(&["--connect", "db1:9185", "--record", "out.jsonl"]).unwrap_err(),
+            "--connect and --record can't be used together"
+        );
+        assert_eq!(parse(&["--agent", "0.0.0.0:9185"]).unwrap().agent.as_deref(), Some("0.0.0.0:9185"));
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        assert_eq!(parse(&["--proc-root"]).unwrap_err(), "missing value for --proc-root");
+        assert!(parse(&["--interval", "cpu=0"]).is_err());
+        assert_eq!(parse(&["--bogus"]).unwrap_err(), "unknown option --bogus");
+        assert!(parse(&["--format", "xml"]).is_err());
+        assert!(parse(&["--samples", "0"]).is_err());
+    }
+
+    #[test]
+    fn rejects_headless_options_without_headless() {
+        assert_eq!(parse(&["--format", "csv"]).unwrap_err(), "--format needs --headless");
+        assert_eq!(parse(&["--output=out.json"]).unwrap_err(), "--output needs --headless");
+        assert_eq!(parse(&["--samples", "3"]).unwrap_err(), "--samples needs --headless");
+        assert_eq!(parse(&["--metrics", "cpu"]).unwrap_err(), "--metrics needs --headless or --agent");
+        assert!(parse(&["--agent", "0.0.0.0:9185", "--metrics", "cpu"]).is_ok());
+        assert!(parse(&["--samples", "3", "--headless"]).is_ok());
+    }
+}
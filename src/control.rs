@@ -0,0 +1,212 @@
+// Signals and nice values for the processes in the task list.
+
+use std::fmt::{self, Display};
+use std::fs;
+use std::io;
+
+use crate::collectors::process::parse_stat;
+
+/// A signal offered in the Tasks pane.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Signal(pub i32);
+
+impl Signal {
+    pub const TERM: Signal = Signal(libc::SIGTERM);
+    pub const KILL: Signal = Signal(libc::SIGKILL);
+    pub const STOP: Signal = Signal(libc::SIGSTOP);
+    pub const CONT: Signal = Signal(libc::SIGCONT);
+    pub const HUP: Signal = Signal(libc::SIGHUP);
+
+    /// The ones with a button of their own.
+    pub const COMMON: [Signal; 5] = [Signal::TERM, Signal::KILL, Signal::STOP, Signal::CONT, Signal::HUP];
+
+    /// Every standard signal, for the custom signal picker.
+    pub const ALL: [Signal; 30] = [
+        Signal(libc::SIGHUP),
+        Signal(libc::SIGINT),
+        Signal(libc::SIGQUIT),
+        Signal(libc::SIGILL),
+        Signal(libc::SIGTRAP),
+        Signal(libc::SIGABRT),
+        Signal(libc::SIGBUS),
+        Signal(libc::SIGFPE),
+        Signal(libc::SIGKILL),
+        Signal(libc::SIGUSR1),
+        Signal(libc::SIGSEGV),
+        Signal(libc::SIGUSR2),
+        Signal(libc::SIGPIPE),
+        Signal(libc::SIGALRM),
+        Signal(libc::SIGTERM),
+        Signal(libc::SIGCHLD),
+        Signal(libc::SIGCONT),
+        Signal(libc::SIGSTOP),
+        Signal(libc::SIGTSTP),
+        Signal(libc::SIGTTIN),
+        Signal(libc::SIGTTOU),
+        Signal(libc::SIGURG),
+        Signal(libc::SIGXCPU),
+        Signal(libc::SIGXFSZ),
+        Signal(libc::SIGVTALRM),
+        Signal(libc::SIGPROF),
+        Signal(libc::SIGWINCH),
+        Signal(libc::SIGIO),
+        Signal(libc::SIGPWR),
+        Signal(libc::SIGSYS),
+    ];
+
+    pub fn name(self) -> Option<&'static str> {
+        let name = match self.0 {
+            libc::SIGHUP => "HUP",
+            libc::SIGINT => "INT",
+            libc::SIGQUIT => "QUIT",
+            libc::SIGILL => "ILL",
+            libc::SIGTRAP => "TRAP",
+            libc::SIGABRT => "ABRT",
+            libc::SIGBUS => "BUS",
+            libc::SIGFPE => "FPE",
+            libc::SIGKILL => "KILL",
+            libc::SIGUSR1 => "USR1",
+            libc::SIGSEGV => "SEGV",
+            libc::SIGUSR2 => "USR2",
+            libc::SIGPIPE => "PIPE",
+            libc::SIGALRM => "ALRM",
+            libc::SIGTERM => "TERM",
+            libc::SIGCHLD => "CHLD",
+            libc::SIGCONT => "CONT",
+            libc::SIGSTOP => "STOP",
+            libc::SIGTSTP => "TSTP",
+            libc::SIGTTIN => "TTIN",
+            libc::SIGTTOU => "TTOU",
+            libc::SIGURG => "URG",
+            libc::SIGXCPU => "XCPU",
+            libc::SIGXFSZ => "XFSZ",
+            libc::SIGVTALRM => "VTALRM",
+            libc::SIGPROF => "PROF",
+            libc::SIGWINCH => "WINCH",
+            libc::SIGIO => "IO",
+            libc::SIGPWR => "PWR",
+            libc::SIGSYS => "SYS",
+            _ => return None,
+        };
+        Some(name)
+    }
+}
+
+impl Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "SIG{} ({})", name, self.0),
+            None => write!(f, "signal {}", self.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessAction {
+    Signal(Signal),
+    Renice(i32),
+}
+
+impl ProcessAction {
+    /// What the confirmation asks, e.g. "Send SIGTERM (15) to".
+    pub fn describe(self) -> String {
+        match self {
+            ProcessAction::Signal(signal) => format!("Send {} to", signal),
+            ProcessAction::Renice(nice) => format!("Set nice {} on", nice),
+        }
+    }
+}
+
+/// Refuses init and the dashboard itself, whatever the user's rights.
+pub fn check_target(pid: usize) -> Result<(), String> {
+    if pid <= 1 {
+        Err(format!("refusing to touch PID {}: it is init", pid))
+    } else if pid == std::process::id() as usize {
+        Err(format!("refusing to touch PID {}: it is this dashboard", pid))
+    } else {
+        Ok(())
+    }
+}
+
+/// When `pid` started, in clock ticks after boot. With the PID it names one
+/// process, even once the PID is reused.
+pub fn start_time(pid: usize) -> Result<u64, String> {
+    fs::read_to_string(format!("/proc/{}/stat", pid))
+        .ok()
+        .and_then(|stat| parse_stat(&stat))
+        .map(|stat| stat.start_time)
+        .ok_or_else(|| format!("PID {} no longer exists", pid))
+}
+
+/// Sends the signal or changes the nice value, explaining the usual
+/// failures. `start_time` is the one seen when the user picked the process,
+/// so a process that took over its PID since is left alone.
+pub fn apply(pid: usize, start_time: u64, action: ProcessAction) -> Result<(), String> {
+    check_target(pid)?;
+    let failed = |reason: &str| format!("{} PID {} failed: {}", action.describe(), pid, reason);
+    match self::start_time(pid) {
+        Ok(now) if now == start_time => {}
+        Ok(_) => return Err(failed("the process exited and its PID was reused")),
+        Err(_) => return Err(failed("the process no longer exists")),
+    }
+    let pid_t = libc::pid_t::try_from(pid).map_err(|_| format!("invalid PID {}", pid))?;
+    let result = match action {
+        ProcessAction::Signal(Signal(signal)) => unsafe { libc::kill(pid_t, signal) },
+        ProcessAction::Renice(nice) => unsafe { libc::setpriority(libc::PRIO_PROCESS, pid_t as libc::id_t, nice) },
+    };
+    if result == 0 {
+        return Ok(());
+    }
+
+    let error = io::Error::last_os_error();
+    let reason = match (error.raw_os_error(), action) {
+        (Some(libc::EPERM), _) => String::from("permission denied (EPERM), the process belongs to another user"),
+        (Some(libc::EACCES), ProcessAction::Renice(_)) => {
+            String::from("permission denied (EACCES), lowering the nice value needs root")
+        }
+        (Some(libc::ESRCH), _) => String::from("the process no longer exists"),
+        _ => error.to_string(),
+    };
+    Err(failed(&reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::Command;
+
+    #[test]
+    fn protects_init_and_itself() {
+        assert!(check_target(1).is_err());
+        assert!(apply(std::process::id() as usize, 0, ProcessAction::Signal(Signal::KILL)).is_err());
+        assert!(check_target(2).is_ok());
+    }
+
+    #[test]
+    fn renices_and_signals_a_child() {
+        let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+        let pid = child.id() as usize;
+        let start = start_time(pid).unwrap();
+        // Raising the nice value is always allowed.
+        apply(pid, start, ProcessAction::Renice(5)).unwrap();
+        let nice = unsafe { libc::getpriority(libc::PRIO_PROCESS, pid as libc::id_t) };
+        assert_eq!(nice, 5);
+
+        apply(pid, start, ProcessAction::Signal(Signal::STOP)).unwrap();
+        apply(pid, start, ProcessAction::Signal(Signal::CONT)).unwrap();
+        // Another start time is another process, even with the same PID.
+        let error = apply(pid, start + 1, ProcessAction::Signal(Signal::KILL)).unwrap_err();
+        assert!(error.ends_with("the process exited and its PID was reused"), "{}", error);
+        apply(pid, start, ProcessAction::Signal(Signal::TERM)).unwrap();
+        assert_eq!(child.wait().unwrap().signal(), Some(libc::SIGTERM));
+        assert!(apply(pid, start, ProcessAction::Signal(Signal::TERM)).is_err());
+    }
+
+    #[test]
+    fn names_signals() {
+        assert_eq!(Signal::TERM.to_string(), "SIGTERM (15)");
+        assert_eq!(Signal(64).to_string(), "signal 64");
+        assert!(Signal::ALL.iter().all(|signal| signal.name().is_some()));
+    }
+}
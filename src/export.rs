@@ -0,0 +1,335 @@
+// Streams the collected metrics to stdout or a file for `--headless` runs.
+
+use std::io::{self, Write};
+use std::ops::ControlFlow;
+use std::time::SystemTime;
+
+use serde::Serialize;
+use serde_json::json;
+
+use crate::collectors::sensors::SensorKind;
+use crate::recording::millis_since_epoch;
+use crate::scheduler::Sink;
+use crate::CollectedData;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// One JSON object per metric and collection pass.
+    #[default]
+    Json,
+    /// `timestamp,metric,instance,field,value` rows.
+    Csv,
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            _ => Err(format!("unknown format {}, expected json or csv", value)),
+        }
+    }
+}
+
+/// One line of the JSON output.
+#[derive(Serialize)]
+struct Line<'a> {
+    timestamp: u64,
+    metric: &'a str,
+    data: serde_json::Value,
+}
+
+/// One row of the CSV output.
+struct Row<'a> {
+    metric: &'a str,
+    instance: String,
+    field: &'a str,
+    value: f64,
+}
+
+/// Quotes a CSV field when it contains a separator, quote or newline.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn rows<'a>(metric: &'a str, data: &'a CollectedData) -> Vec<Row<'a>> {
+    let row = |instance: String, field: &'a str, value: f64| Row { metric, instance, field, value };
+    match metric {
+        "cpu" => data
+            .cpu_usage
+            .iter()
+            .filter_map(|history| history.front())
+            .flat_map(|sample| {
+                std::iter::once(("total", sample.total))
+                    .chain(sample.parts())
+                    .chain(sample.frequency.map(|mhz| ("frequency_mhz", mhz)))
+                    .map(move |(field, value)| row(format!("cpu{}", sample.id), field, value))
+            })
+            .collect(),
+        "memory" => {
+            let memory = &data.memory;
+            [
+                ("total", memory.total),
+                ("used", memory.used()),
+                ("free", memory.free),
+                ("available", memory.available),
+                ("buff_cache", memory.buff_cache()),
+                ("swap_total", memory.swap_total),
+                ("swap_used", memory.swap_used()),
+            ]
+            .into_iter()
+            .map(|(field, value)| row(String::new(), field, value as f64))
+            .collect()
+        }
+        "disks" => data
+            .partitions
+            .iter()
+            .flat_map(|partition| {
+                [
+                    ("total", partition.total as f64),
+                    ("used", partition.used() as f64),
+                    ("available", partition.available as f64),
+                    ("used_percent", partition.used_percent()),
+                    ("inodes_total", partition.inodes_total as f64),
+                    ("inodes_used", partition.inodes_used() as f64),
+                ]
+                .into_iter()
+                .map(|(field, value)| row(partition.mount_point.clone(), field, value))
+            })
+            .collect(),
+        "diskio" => data
+            .block_devices
+            .iter()
+            // Rates need a second sample.
+            .filter_map(|device| Some((device, device.rates?)))
+            .flat_map(|(device, rates)| {
+                [
+                    ("read_bytes_per_second", rates.read_bytes),
+                    ("write_bytes_per_second", rates.write_bytes),
+                    ("reads_per_second", rates.reads),
+                    ("writes_per_second", rates.writes),
+                    ("await_ms", rates.await_ms),
+                    ("queue", rates.queue),
+                    ("utilization_percent", rates.utilization),
+                ]
+                .into_iter()
+                .map(|(field, value)| row(device.name.clone(), field, value))
+            })
+            .collect(),
+        "network" => data
+            .network
+            .iter()
+            .filter_map(|interface| Some((interface, interface.rates?)))
+            .flat_map(|(interface, rates)| {
+                [
+                    ("rx_bytes_per_second", rates.rx_bytes),
+                    ("tx_bytes_per_second", rates.tx_bytes),
+                    ("rx_packets_per_second", rates.rx_packets),
+                    ("tx_packets_per_second", rates.tx_packets),
+                    ("rx_errors_per_second", rates.rx_errors),
+                    ("tx_errors_per_second", rates.tx_errors),
+                    ("rx_drops_per_second", rates.rx_drops),
+                    ("tx_drops_per_second", rates.tx_drops),
+                ]
+                .into_iter()
+                .map(|(field, value)| row(interface.name.clone(), field, value))
+            })
+            .collect(),
+        "sensors" => data
+            .sensors
+            .iter()
+            .map(|sensor| {
+                let field = match sensor.kind {
+                    SensorKind::Temperature => "celsius",
+                    SensorKind::Fan => "rpm",
+                    SensorKind::Voltage => "volts",
+                };
+                row(sensor.id.clone(), field, sensor.value)
+            })
+            .collect(),
+        "tasks" => data
+            .process_list
+            .iter()
+            .flat_map(|process| {
+                [("cpu", process.cpu), ("memory", process.memory)]
+                    .into_iter()
+                    .map(|(field, value)| row(process.pid.to_string(), field, value))
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+fn json_value(metric: &str, data: &CollectedData) -> serde_json::Value {
+    match metric {
+        "cpu" => json!(data.cpu_usage.iter().filter_map(|history| history.front()).collect::<Vec<_>>()),
+        "memory" => json!(data.memory),
+        "disks" => json!(data.partitions),
+        "diskio" => json!(data.block_devices),
+        "network" => json!(data.network),
+        "sockets" => json!(data.sockets),
+        "sensors" => json!(data.sensors),
+        "tasks" => json!(data.process_list),
+        _ => serde_json::Value::Null,
+    }
+}
+
+/// Writes the metrics of the collectors that just ran, stopping after a
+/// number of passes when asked to.
+pub struct Exporter {
+    writer: Box<dyn Write + Send>,
+    format: Format,
+    wrote_header: bool,
+    remaining: Option<u64>,
+}
+
+impl Exporter {
+    pub fn new(writer: Box<dyn Write + Send>, format: Format, samples: Option<u64>) -> Self {
+        Self {
+            writer,
+            format,
+            wrote_header: false,
+            remaining: samples,
+        }
+    }
+
+    fn write_metrics(&mut self, timestamp: u64, metrics: &[&str], data: &CollectedData) -> io::Result<()> {
+        match self.format {
+            Format::Json => {
+                for &metric in metrics {
+                    let line = Line { timestamp, metric, data: json_value(metric, data) };
+                    serde_json::to_writer(&mut self.writer, &line)?;
+                    self.writer.write_all(b"\n")?;
+                }
+            }
+            Format::Csv => {
+                if !self.wrote_header {
+                    writeln!(self.writer, "timestamp,metric,instance,field,value")?;
+                    self.wrote_header = true;
+                }
+                for &metric in metrics {
+                    for row in rows(metric, data) {
+                        writeln!(
+                            self.writer,
+                            "{},{},{},{},{}",
+                            timestamp,
+                            row.metric,
+                            csv_field(&row.instance),
+                            row.field,
+                            row.value
+                        )?;
+                    }
+                }
+            }
+        }
+        self.writer.flush()
+    }
+}
+
+impl Sink for Exporter {
+    fn name(&self) -> &'static str {
+        "export"
+    }
+
+    fn write(&mut self, collected: &[&'static str], data: &CollectedData) -> io::Result<ControlFlow<()>> {
+        // The CPU collector needs two readings before it has a sample.
+        let metrics: Vec<&str> = collected
+            .iter()
+            .copied()
+            .filter(|&metric| metric != "cpu" || data.cpu_usage.iter().any(|history| !history.is_empty()))
+            .collect();
+        if metrics.is_empty() {
+            return Ok(ControlFlow::Continue(()));
+        }
+        self.write_metrics(millis_since_epoch(SystemTime::now()), &metrics, data)?;
+
+        match &mut self.remaining {
+            Some(remaining) => {
+                *remaining = remaining.saturating_sub(1);
+                Ok(if *remaining == 0 { ControlFlow::Break(()) } else { ControlFlow::Continue(()) })
+            }
+            None => Ok(ControlFlow::Continue(())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collectors::cpu::CpuSample;
+    use crate::collectors::disks::PartitionUsage;
+    use std::sync::{Arc, Mutex};
+
+    /// A writer the test can read back after handing it to the exporter.
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Buffer {
+        fn contents(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    fn data() -> CollectedData {
+        CollectedData {
+            cpu_usage: vec![[CpuSample { total: 12.5, user: 12.5, ..Default::default() }].into()],
+            partitions: vec![PartitionUsage {
+                mount_point: String::from("/mnt/a,b"),
+                total: 100,
+                free: 40,
+                available: 40,
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn writes_long_format_csv() {
+        let buffer = Buffer::default();
+        let mut exporter = Exporter::new(Box::new(buffer.clone()), Format::Csv, None);
+        exporter.write_metrics(1000, &["cpu", "disks"], &data()).unwrap();
+        let contents = buffer.contents();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines[0], "timestamp,metric,instance,field,value");
+        assert_eq!(lines[1], "1000,cpu,cpu0,total,12.5");
+        assert_eq!(lines[2], "1000,cpu,cpu0,user,12.5");
+        assert!(lines.contains(&"1000,disks,\"/mnt/a,b\",used_percent,60"));
+        assert_eq!(lines.len(), 1 + 9 + 6);
+    }
+
+    #[test]
+    fn writes_one_json_object_per_metric() {
+        let buffer = Buffer::default();
+        let mut exporter = Exporter::new(Box::new(buffer.clone()), Format::Json, None);
+        exporter.write_metrics(1000, &["disks"], &data()).unwrap();
+        let line: serde_json::Value = serde_json::from_str(buffer.contents().trim()).unwrap();
+        assert_eq!(line["metric"], "disks");
+        assert_eq!(line["timestamp"], 1000);
+        assert_eq!(line["data"][0]["total"], 100);
+    }
+
+    #[test]
+    fn stops_after_the_requested_samples() {
+        let mut exporter = Exporter::new(Box::new(Buffer::default()), Format::Json, Some(2));
+        let data = data();
+        assert_eq!(exporter.write(&["memory"], &data).unwrap(), ControlFlow::Continue(()));
+        assert_eq!(exporter.write(&["memory"], &data).unwrap(), ControlFlow::Break(()));
+    }
+}
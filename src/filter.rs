@@ -0,0 +1,340 @@
+// Filters for the task list: plain text matched against pid, user and
+// command, or expressions like `cpu > 5 && user == postgres` and
+// `cmd ~ /java.*-Xmx/`.
+
+use regex::Regex;
+
+use crate::ProcessInfo;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Pid,
+    Ppid,
+    User,
+    Cmd,
+    Status,
+    Nice,
+    Cpu,
+    Memory,
+}
+
+impl Field {
+    fn parse(name: &str) -> Option<Field> {
+        let field = match name {
+            "pid" => Field::Pid,
+            "ppid" => Field::Ppid,
+            "user" => Field::User,
+            "cmd" | "command" => Field::Cmd,
+            "status" | "state" => Field::Status,
+            "nice" => Field::Nice,
+            "cpu" => Field::Cpu,
+            "mem" | "memory" => Field::Memory,
+            _ => return None,
+        };
+        Some(field)
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Field::Pid => "pid",
+            Field::Ppid => "ppid",
+            Field::User => "user",
+            Field::Cmd => "cmd",
+            Field::Status => "status",
+            Field::Nice => "nice",
+            Field::Cpu => "cpu",
+            Field::Memory => "mem",
+        }
+    }
+
+    fn is_numeric(self) -> bool {
+        !matches!(self, Field::User | Field::Cmd | Field::Status)
+    }
+
+    fn text(self, process: &ProcessInfo) -> String {
+        match self {
+            Field::Pid => process.pid.to_string(),
+            Field::Ppid => process.ppid.to_string(),
+            Field::User => process.user.clone(),
+            Field::Cmd => process.cmd.clone(),
+            Field::Status => process.status.clone(),
+            Field::Nice => process.nice.clone(),
+            Field::Cpu => process.cpu.to_string(),
+            Field::Memory => process.memory.to_string(),
+        }
+    }
+
+    /// None for the nice value of real-time processes, shown as "-".
+    fn number(self, process: &ProcessInfo) -> Option<f64> {
+        match self {
+            Field::Pid => Some(process.pid as f64),
+            Field::Ppid => Some(process.ppid as f64),
+            Field::Nice => process.nice.parse().ok(),
+            Field::Cpu => Some(process.cpu),
+            Field::Memory => Some(process.memory),
+            Field::User | Field::Cmd | Field::Status => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Condition {
+    Number(Field, &'static str, f64),
+    Text(Field, bool, String),
+    Matches(Field, bool, Regex),
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Condition(Condition),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn matches(&self, process: &ProcessInfo) -> bool {
+        match self {
+            Expr::Condition(Condition::Number(field, op, value)) => match field.number(process) {
+                Some(number) => match *op {
+                    "==" => number == *value,
+                    "!=" => number != *value,
+                    ">" => number > *value,
+                    ">=" => number >= *value,
+                    "<" => number < *value,
+                    _ => number <= *value,
+                },
+                None => false,
+            },
+            Expr::Condition(Condition::Text(field, equal, value)) => (field.text(process) == *value) == *equal,
+            Expr::Condition(Condition::Matches(field, matches, regex)) => regex.is_match(&field.text(process)) == *matches,
+            Expr::Not(expr) => !expr.matches(process),
+            Expr::And(a, b) => a.matches(process) && b.matches(process),
+            Expr::Or(a, b) => a.matches(process) || b.matches(process),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Regex(String),
+    Op(&'static str),
+    Open,
+    Close,
+}
+
+const OPERATORS: [&str; 12] = ["&&", "||", "==", "!=", ">=", "<=", "!~", ">", "<", "~", "!", "="];
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut rest = input.trim_start();
+    while let Some(c) = rest.chars().next() {
+        if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+            // A lone `=` reads as `==`.
+            tokens.push(Token::Op(if *op == "=" { "==" } else { op }));
+            rest = &rest[op.len()..];
+        } else if c == '&' || c == '|' {
+            // Only doubled, which the operators above already took.
+            return Err(String::from("expected && or ||"));
+        } else if c == '(' || c == ')' {
+            tokens.push(if c == '(' { Token::Open } else { Token::Close });
+            rest = &rest[1..];
+        } else if c == '"' || c == '/' {
+            let end = rest[1..].find(c).ok_or_else(|| format!("unterminated {}", c))? + 1;
+            let content = rest[1..end].to_string();
+            tokens.push(if c == '"' { Token::Quoted(content) } else { Token::Regex(content) });
+            rest = &rest[end + 1..];
+        } else {
+            let end = rest
+                .find(|c: char| c.is_whitespace() || "()\"&|=!<>~".contains(c))
+                .unwrap_or(rest.len());
+            tokens.push(Token::Word(rest[..end].to_string()));
+            rest = &rest[end..];
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.peek() == Some(&Token::Op("||")) {
+            self.position += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.unary()?;
+        while self.peek() == Some(&Token::Op("&&")) {
+            self.position += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Op("!")) => Ok(Expr::Not(Box::new(self.unary()?))),
+            Some(Token::Open) => {
+                let expr = self.or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(expr),
+                    _ => Err(String::from("missing )")),
+                }
+            }
+            Some(Token::Word(name)) => {
+                let field = Field::parse(&name).ok_or_else(|| format!("unknown field {}", name))?;
+                self.condition(field).map(Expr::Condition)
+            }
+            Some(token) => Err(format!("expected a field, found {:?}", token)),
+            None => Err(String::from("expected a field")),
+        }
+    }
+
+    fn condition(&mut self, field: Field) -> Result<Condition, String> {
+        let op = match self.next() {
+            Some(Token::Op(op)) if !matches!(op, "&&" | "||" | "!") => op,
+            _ => return Err(String::from("expected ==, !=, >, >=, <, <=, ~ or !~ after the field")),
+        };
+        let value = match self.next() {
+            Some(Token::Word(value) | Token::Quoted(value) | Token::Regex(value)) => value,
+            _ => return Err(format!("expected a value after {}", op)),
+        };
+
+        match op {
+            "~" | "!~" => Regex::new(&value)
+                .map(|regex| Condition::Matches(field, op == "~", regex))
+                .map_err(|e| e.to_string()),
+            _ if field.is_numeric() => value
+                .parse()
+                .map(|number| Condition::Number(field, op, number))
+                .map_err(|_| format!("{} is not a number", value)),
+            "==" | "!=" => Ok(Condition::Text(field, op == "==", value)),
+            _ => Err(format!("{} can only be compared with ==, !=, ~ and !~", Field::name(field))),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    /// Case-insensitive substring of the pid, user or command.
+    Text(String),
+    Expr(Expr),
+}
+
+/// A parsed filter, kept by the task list across refreshes.
+#[derive(Debug, Clone)]
+pub struct Filter(Matcher);
+
+impl Filter {
+    /// Reads an expression when the input starts with a field and an
+    /// operator or a `(`/`!`, text otherwise.
+    pub fn parse(input: &str) -> Result<Option<Filter>, String> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Ok(None);
+        }
+        let tokens = tokenize(input)?;
+        let is_expr = match tokens.as_slice() {
+            [Token::Open | Token::Op("!"), ..] => true,
+            [Token::Word(name), Token::Op(_), ..] => Field::parse(name).is_some(),
+            _ => false,
+        };
+        if !is_expr {
+            return Ok(Some(Filter(Matcher::Text(input.to_lowercase()))));
+        }
+
+        let mut parser = Parser { tokens, position: 0 };
+        let expr = parser.or()?;
+        match parser.peek() {
+            None => Ok(Some(Filter(Matcher::Expr(expr)))),
+            Some(token) => Err(format!("unexpected {:?}, join conditions with && or ||", token)),
+        }
+    }
+
+    pub fn matches(&self, process: &ProcessInfo) -> bool {
+        match &self.0 {
+            Matcher::Text(text) => {
+                process.pid.to_string().contains(text.as_str())
+                    || process.user.to_lowercase().contains(text.as_str())
+                    || process.cmd.to_lowercase().contains(text.as_str())
+            }
+            Matcher::Expr(expr) => expr.matches(process),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn processes() -> Vec<ProcessInfo> {
+        let process = |pid, user: &str, cpu, cmd: &str| ProcessInfo {
+            pid,
+            user: user.to_string(),
+            cpu,
+            nice: String::from("0"),
+            cmd: cmd.to_string(),
+            ..Default::default()
+        };
+        vec![
+            process(10, "postgres", 12.0, "postgres: writer"),
+            process(11, "postgres", 1.0, "postgres: checkpointer"),
+            process(20, "app", 80.0, "java -Xmx4g -jar app.jar"),
+            process(21, "app", 2.0, "java -jar tool.jar"),
+        ]
+    }
+
+    fn matching(filter: &str) -> Vec<usize> {
+        let filter = Filter::parse(filter).unwrap().unwrap();
+        processes().iter().filter(|p| filter.matches(p)).map(|p| p.pid).collect()
+    }
+
+    #[test]
+    fn plain_text_matches_pid_user_and_command() {
+        assert_eq!(matching("POSTGRES"), vec![10, 11]);
+        assert_eq!(matching("21"), vec![21]);
+        assert_eq!(matching("tool.jar"), vec![21]);
+    }
+
+    #[test]
+    fn expressions_combine_conditions() {
+        assert_eq!(matching("cpu > 5 && user == postgres"), vec![10]);
+        assert_eq!(matching("cmd ~ /java.*-Xmx/"), vec![20]);
+        assert_eq!(matching("user == app || pid <= 10"), vec![10, 20, 21]);
+        assert_eq!(matching("!(user = postgres) && cmd !~ /Xmx/"), vec![21]);
+        assert_eq!(matching("cmd == \"postgres: writer\""), vec![10]);
+    }
+
+    #[test]
+    fn reports_invalid_expressions() {
+        assert_eq!(Filter::parse("cpu > lots").unwrap_err(), "lots is not a number");
+        assert!(Filter::parse("cmd ~ /(/").is_err());
+        assert!(Filter::parse("cpu > 5 user == x").is_err());
+        assert!(Filter::parse("(cpu > 5").is_err());
+        assert_eq!(Filter::parse("a & b").unwrap_err(), "expected && or ||");
+        assert_eq!(Filter::parse("a|b").unwrap_err(), "expected && or ||");
+        assert_eq!(Filter::parse("user > b").unwrap_err(), "user can only be compared with ==, !=, ~ and !~");
+        assert!(Filter::parse("  ").unwrap().is_none());
+    }
+}
@@ -2,11 +2,12 @@
 use iced::alignment::{self, Alignment};
 use iced::executor;
 use iced::keyboard;
+use iced::window;
 use iced::theme::{self, Theme};
 use iced::widget::canvas::{Cache, Frame, Geometry};
 use iced::widget::pane_grid::{self, PaneGrid};
 use iced::widget::canvas;
-use iced::widget::{button, column, container, row, scrollable, text, pick_list};
+use iced::widget::{button, checkbox, column, container, row, scrollable, slider, text, text_input, pick_list, Column};
 use iced::{
     Application, Color, Command, Element, Length, Settings, Size, Subscription
 };
@@ -18,406 +19,325 @@ use iced_native::{event, subscription, Event};
 use plotters::prelude::ChartBuilder;
 use plotters_iced::plotters_backend::DrawingBackend;
 use plotters_iced::{Chart, ChartWidget};
-
-use std::collections::VecDeque;
+use serde::{Deserialize, Serialize};
+
+use collectors::cpu::{CpuFrequencyPolicy, CpuSample};
+use collectors::CollectorHealth;
+use collectors::diskio::{BlockDevice, DiskIoRates};
+use collectors::disks::PartitionUsage;
+use collectors::dmi::MemoryDevice;
+use collectors::memory::MemoryStats;
+use collectors::network::{NetworkInterface, NetworkRates};
+use collectors::process::ProcessFiles;
+use collectors::process_details::ProcessDetails;
+use collectors::sensors::{Sensor, SensorKind, SensorLevel};
+use collectors::sockets::SocketInfo;
+use collectors::sysinfo::SystemInfo;
+
+use std::collections::{HashMap, VecDeque};
 use std::fmt::Display;
-use std::time::Duration;
-use std::thread;
-use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use std::sync::{Arc, Mutex, PoisonError};
 use std::sync::atomic::{AtomicU64, Ordering};
 
+mod collectors;
+mod config;
+mod control;
+mod export;
+mod filter;
+mod openmetrics;
+mod recording;
+mod remote;
+mod scheduler;
+
 const MAX_POINTS: usize = 30;
 
 pub fn main() -> iced::Result {
     // Example::run(Settings::default())
-    let shared_tick = Arc::new(AtomicU64::new(0));
-    let shared_data = Arc::new(Mutex::new(CollectedData::default()));
-    
-    {
-        let thread_tick = Arc::clone(&shared_tick);
-        let thread_data = Arc::clone(&shared_data);
-    
-        thread::spawn(move || {
-            loop {
-                collect_infos(&thread_data);
-                thread_tick.fetch_add(1, Ordering::SeqCst);
-                thread::sleep(Duration::from_secs(1));
-            }
-        });
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{}", config::USAGE);
+        return Ok(());
     }
+    let config = match config::Config::from_args(args) {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("error: {}\n\n{}", error, config::USAGE);
+            std::process::exit(2);
+        }
+    };
 
-    {
-        let thread_tick = Arc::clone(&shared_tick);
-        let thread_data = Arc::clone(&shared_data);
-    
-        thread::spawn(move || {
-            loop {
-                collect_tasks(&thread_data);
-                thread_tick.fetch_add(1, Ordering::SeqCst);
-                thread::sleep(Duration::from_secs(2));
-            }
-        });
+    if config.headless || config.agent.is_some() {
+        run_headless(&config);
+        return Ok(());
     }
 
-    {
-        let thread_tick = Arc::clone(&shared_tick);
-        let thread_data = Arc::clone(&shared_data);
-    
-        thread::spawn(move || {
-            loop {
-                collect_memory(&thread_data);
-                thread_tick.fetch_add(1, Ordering::SeqCst);
-                thread::sleep(Duration::from_secs(2));
-            }
-        });
-    }
+    let replay = config.replay.as_ref().map(|path| {
+        recording::Recording::load(path).unwrap_or_else(|error| {
+            eprintln!("error: {}", error);
+            std::process::exit(1);
+        })
+    });
 
-    {
-        let thread_tick = Arc::clone(&shared_tick);
-        let thread_data = Arc::clone(&shared_data);
-    
-        thread::spawn(move || {
-            loop {
-                collect_cpu(&thread_data);
-                thread_tick.fetch_add(1, Ordering::SeqCst);
-                thread::sleep(Duration::from_secs(2));
-            }
-        });
+    let shared_tick = Arc::new(AtomicU64::new(0));
+    if !config.connect.is_empty() {
+        // Each agent fills its own data; nothing is collected here.
+        let hosts = config
+            .connect
+            .iter()
+            .map(|address| {
+                let shared_data = Arc::new(Mutex::new(CollectedData::default()));
+                let connection = remote::Connection::spawn(address.clone(), Arc::clone(&shared_data), Arc::clone(&shared_tick));
+                Host::new(shared_data, Some(connection))
+            })
+            .collect();
+        let scheduler = scheduler::Scheduler::new(Arc::new(Mutex::new(CollectedData::default())), Arc::clone(&shared_tick));
+        return run_window(Flags { hosts, shared_tick, scheduler: scheduler.spawn(), player: None, recording: false });
     }
 
-    {
-        let thread_tick = Arc::clone(&shared_tick);
-        let thread_data = Arc::clone(&shared_data);
-    
-        thread::spawn(move || {
-            loop {
-                collect_disks(&thread_data);
-                thread_tick.fetch_add(1, Ordering::SeqCst);
-                thread::sleep(Duration::from_secs(2));
-            }
-        });
+    let shared_data = Arc::new(Mutex::new(match &replay {
+        Some(recording) => CollectedData {
+            memory_devices: recording.memory_devices.clone(),
+            system_info: recording.system_info.clone(),
+            ..Default::default()
+        },
+        None => CollectedData {
+            // The DIMM layout doesn't change while running, read it only once.
+            memory_devices: collectors::dmi::read_memory_devices(&config.roots),
+            system_info: collectors::sysinfo::read_system_info(&config.roots),
+            ..Default::default()
+        },
+    }));
+
+    // When replaying the scheduler has nothing to run.
+    let mut scheduler = scheduler::Scheduler::new(Arc::clone(&shared_data), Arc::clone(&shared_tick));
+    if replay.is_none() {
+        for collector in collectors::all(&config) {
+            scheduler.register(collector);
+        }
+        scheduler.register(Box::new(collectors::process_details::ProcessDetailsCollector::new(config.roots.clone())));
+        let names = scheduler.names();
+        apply_intervals(&mut scheduler, &config, &names);
     }
+    add_recorder(&mut scheduler, &config, &shared_data);
+    serve_openmetrics(&mut scheduler, &config, &shared_data);
+    let scheduler = scheduler.spawn();
+    let player = replay.map(recording::Player::new);
+    let mut host = Host::new(shared_data, None);
+    // PIDs of a recording or of another PID namespace aren't ours to signal.
+    host.controllable = player.is_none() && !config.roots.is_foreign();
+    let hosts = vec![host];
+    let recording = config.record.is_some();
+    run_window(Flags { hosts, shared_tick, scheduler, player, recording })
+}
 
+fn run_window(flags: Flags) -> iced::Result {
     Example::run(Settings {
         window: iced::window::Settings {
             size: (800, 600),
             ..Default::default()
         },
-        flags: (shared_data, shared_tick),
+        // The scheduler is shut down before the window goes away.
+        exit_on_close_request: false,
         text_multithreading: true,
         antialiasing: true,
-        ..Default::default()
+        ..Settings::with_flags(flags)
     })
 }
 
-fn collect_tasks(shared_data: &Arc<Mutex<CollectedData>>) {
-    use std::process::{Command, Stdio};
-    use std::io::Read;
-    let process = Command::new("/usr/bin/ps")
-                        .stdout(Stdio::piped())
-                        .args(["-ew", "-o", "pid,ni,s,user,%cpu,%mem,args"])
-                        .spawn()
-                        .expect("failed to execute process");
-
-    let mut s = String::new();
-    process.stdout.unwrap().read_to_string(&mut s).unwrap();
-    let mut lines = s.lines();
-    lines.next();
-
-    let mut process_list = vec![];
-
-    let mut last_whitespace = false;
-
-    for line in lines {
-        let fields: Vec<_> = line.trim().splitn(7, |c: char| {
-            if c.is_whitespace() {
-                if last_whitespace {
-                    return false
-                }
-                last_whitespace = true;
-                true
-            } else {
-                last_whitespace = false;
-                false
-            }
-        }).map(str::trim).collect();
-        last_whitespace = false;
-        if fields.len() == 7 {
-            // println!("{:?}", fields);
-            process_list.push(ProcessInfo {
-                pid: fields[0].parse().unwrap(),
-                nice: fields[1].to_owned(),
-                status: fields[2].to_owned(),
-                user: fields[3].to_owned(),
-                cpu: fields[4].parse().unwrap(),
-                memory: fields[5].parse().unwrap(),
-                cmd: fields[6].to_owned(),
-            });
+/// `known` are the collectors that may be named, which may include some the
+/// scheduler doesn't run, e.g. those left out by `--metrics`.
+fn apply_intervals(scheduler: &mut scheduler::Scheduler, config: &config::Config, known: &[&str]) {
+    for (name, interval) in &config.intervals {
+        match name {
+            Some(name) if !known.contains(&name.as_str()) => {
+                eprintln!("error: unknown collector {} (expected one of: {})", name, known.join(", "));
+                std::process::exit(2);
+            }
+            Some(name) => {
+                scheduler.set_interval(name, *interval);
+            }
+            None => scheduler.set_all_intervals(*interval),
         }
     }
-
-    let mut data = shared_data.lock().unwrap();
-    data.process_list = process_list;
-    data.updated_tasks = true;
-    data.tick += 1;
 }
 
-fn collect_memory(shared_data: &Arc<Mutex<CollectedData>>) {
-    use std::process::{Command, Stdio};
-    use std::io::Read;
-    let process0 = Command::new("/usr/bin/sudo")
-                        .stdout(Stdio::piped())
-                        .args(["/usr/bin/dmidecode", "-t", "memory"])
-                        .spawn()
-                        .expect("failed to execute process0");
-
-    let mut s0 = String::new();
-    process0.stdout.unwrap().read_to_string(&mut s0).unwrap();
-    let lines = s0.lines();
-    let tech = lines.map(str::trim).find_map(|s| {
-        if s.starts_with("Type: ") {
-            let (_a, b) = s.split_at(6);
-            Some(b.to_string())
-        } else {
-            None
+fn add_recorder(scheduler: &mut scheduler::Scheduler, config: &config::Config, shared_data: &Mutex<CollectedData>) {
+    if let Some(path) = &config.record {
+        let data = shared_data.lock().unwrap_or_else(PoisonError::into_inner);
+        match recording::Recorder::create(path, &data) {
+            Ok(recorder) => {
+                scheduler.add_sink(Box::new(recorder));
+                // A recording keeps everything, whatever the panes show.
+                scheduler.never_pause();
+            }
+            Err(error) => {
+                eprintln!("error: cannot record to {}: {}", path.display(), error);
+                std::process::exit(1);
+            }
         }
-    }).unwrap_or_else(|| String::from("DDR3"));
-
-    let process1 = Command::new("/usr/bin/free")
-                        .stdout(Stdio::piped())
-                        .args(["-b", "-t"])
-                        .spawn()
-                        .expect("failed to execute process1");
-
-    let mut s1 = String::new();
-    process1.stdout.unwrap().read_to_string(&mut s1).unwrap();
-    let mut lines = s1.lines();
-    lines.next();
-    let mem_line = lines.next().unwrap();
-    let swap_line = lines.next().unwrap();
-
-    let mut last_whitespace = false;
+    }
+}
 
-    let mem_fields: Vec<_> = mem_line.trim().splitn(7, |c: char| {
-        if c.is_whitespace() {
-            if last_whitespace {
-                return false
+fn serve_openmetrics(scheduler: &mut scheduler::Scheduler, config: &config::Config, shared_data: &Arc<Mutex<CollectedData>>) {
+    if let Some(address) = &config.openmetrics {
+        match std::net::TcpListener::bind(address) {
+            Ok(listener) => {
+                openmetrics::spawn(listener, Arc::clone(shared_data));
+                // Prometheus scrapes everything, whatever the panes show.
+                scheduler.never_pause();
             }
-            last_whitespace = true;
-            true
-        } else {
-            last_whitespace = false;
-            false
-        }
-    }).map(str::trim).collect();
-    last_whitespace = false;
-    let swap_fields: Vec<_> = swap_line.trim().splitn(4, |c: char| {
-        if c.is_whitespace() {
-            if last_whitespace {
-                return false
-            }
-            last_whitespace = true;
-            true
-        } else {
-            last_whitespace = false;
-            false
-        }
-    }).map(str::trim).collect();
-    let mem_data = if mem_fields.len() == 7 {
-        let total: u64 = mem_fields[1].parse().unwrap();
-        let used: u64 = mem_fields[2].parse().unwrap();
-        let free: u64 = mem_fields[3].parse().unwrap();
-        let buff: u64 = mem_fields[5].parse().unwrap();
-        (total, used, free, buff)
-    } else {
-        panic!("wrong");
-    };
-    let swap_data = if swap_fields.len() == 4 {
-        let total: u64 = swap_fields[1].parse().unwrap();
-        let used: u64 = swap_fields[2].parse().unwrap();
-        let free: u64 = swap_fields[3].parse().unwrap();
-        let buff: u64 = 0;
-        (total, used, free, buff)
-    } else {
-        panic!("panic");
-    };
-
-    let mem_total = format!("{:.2}G", (mem_data.0 as f64) / (1024.*1024.*1024.));
-    let swap_total = format!("{:.2}G", (swap_data.0 as f64) / (1024.*1024.*1024.));
-    let mem_used = ((mem_data.1 as f64) / (mem_data.0 as f64)) * 100.;
-    let mem_buff = ((mem_data.3 as f64) / (mem_data.0 as f64)) * 100.;
-    let swap_used = ((swap_data.1 as f64) / (swap_data.0 as f64)) * 100.;
-    let swap_buff = 0.0_f64;
-
-    let ram_usage = (mem_used, mem_buff, swap_used, swap_buff, tech, mem_total, swap_total);
-
-    let mut data = shared_data.lock().unwrap();
-    data.ram_usage = ram_usage;
-    data.updated_memory = true;
-    data.tick += 1;
-}
-
-fn collect_cpu(shared_data: &Arc<Mutex<CollectedData>>) {
-    use std::process::{Command, Stdio};
-    use std::io::Read;
-    let process0 = Command::new("/usr/bin/top")
-                        .stdout(Stdio::piped())
-                        .args(["-b", "-d", "0.5", "-n", "2"])
-                        .spawn()
-                        .expect("failed to execute process0");
-
-    let mut s0 = String::new();
-    process0.stdout.unwrap().read_to_string(&mut s0).unwrap();
-    let mut a = s0.split("top - ");
-    a.next();
-    let s0 = a.next().unwrap();
-    let lines = s0.lines();
-
-    let mut last_whitespace = false;
-    let usages = lines.filter(|l| l.starts_with("%Cpu")).map(|l| {
-        last_whitespace = false;
-        // println!("{}", l.trim().splitn(7, ',').find(|s| s.contains("id")).trim().unwrap());
-        100. - l.trim().splitn(7, ',').find(|s| s.contains("id")).unwrap().trim().split(' ').next().unwrap().trim().replace(',', ".").parse::<f64>().unwrap()
-    });
-
-    let cpu_usage: Vec<_> = usages.collect();
-
-    let mut data = shared_data.lock().unwrap();
-    while data.cpu_usage.len() < cpu_usage.len() {
-        data.cpu_usage.push(VecDeque::new());
-    }
-
-    for (i, usage) in cpu_usage.into_iter().enumerate() {
-        data.cpu_usage[i].push_front(usage);
-        if data.cpu_usage[i].len() > MAX_POINTS {
-            data.cpu_usage[i].pop_back();
-        }
-    }
-    // println!("{:?}", data.cpu_usage);
-    data.updated_cpu = true;
-    data.tick += 1;
-}
-
-fn collect_disks(shared_data: &Arc<Mutex<CollectedData>>) {
-    use std::process::{Command, Stdio};
-    use std::io::Read;
-    // let process0 = Command::new("/usr/bin/ls")
-    //                     .stdout(Stdio::piped())
-    //                     .args(["/sys/block"])
-    //                     .spawn()
-    //                     .expect("failed to execute process0");
-
-    // let mut s0 = String::new();
-    // process0.stdout.unwrap().read_to_string(&mut s0).unwrap();
-    // let devices = s0.trim().split(|c: char| {
-    //     if c.is_whitespace() {
-    //         if last_whitespace {
-    //             return false
-    //         }
-    //         last_whitespace = true;
-    //         true
-    //     } else {
-    //         last_whitespace = false;
-    //         false
-    //     }
-    // }).map(str::to_string);
-    // last_whitespace = false;
-
-
-    let process1 = Command::new("/usr/bin/df")
-                        .stdout(Stdio::piped())
-                        .args(["-T"])
-                        .spawn()
-                        .expect("failed to execute process1");
-
-    let mut s1 = String::new();
-    process1.stdout.unwrap().read_to_string(&mut s1).unwrap();
-    let mut lines = s1.lines();
-    lines.next();
-
-    let mut last_whitespace = false;
-    let mut v: Vec<Vec<&str>> = vec![];
-    for line in lines.filter(|l| l.contains("ext") || l.contains("fat")) {
-        v.push(line.trim().split(|c: char| {
-            if c.is_whitespace() {
-                if last_whitespace {
-                    return false
-                }
-                last_whitespace = true;
-                true
-            } else {
-                last_whitespace = false;
-                false
+            Err(error) => {
+                eprintln!("error: cannot listen on {}: {}", address, error);
+                std::process::exit(1);
             }
-        }).collect());
-        last_whitespace = false;
+        }
     }
-    let partitions: Vec<_> = v.into_iter().map(|s| {
-        // println!("_{}_ _{}_ _{}_", s[5].trim().split('%').next().unwrap(), s[0], s[2]);
-        (s[5].trim().split('%').next().unwrap().parse::<f64>().unwrap(), s[0].to_string(), format!("{:.0}G", s[2].trim().parse::<f64>().unwrap() / (1024. * 1024.)))
-    }).collect();
+}
 
-    // let cpu_usage: Vec<_> = usages.collect();
+/// Runs the selected collectors without a window, streaming what they collect
+/// and/or serving it to viewers.
+fn run_headless(config: &config::Config) {
+    let collectors = collectors::all(config);
+    let names: Vec<&str> = collectors.iter().map(|c| c.name()).collect();
+    if let Some(unknown) = config.metrics.iter().find(|m| !names.contains(&m.as_str())) {
+        eprintln!("error: unknown metric {} (expected one of: {})", unknown, names.join(", "));
+        std::process::exit(2);
+    }
 
-    let mut data = shared_data.lock().unwrap();
-    data.disk_usage = partitions;
-    data.updated_disks = true;
-    data.tick += 1;
-}
+    let shared_data = Arc::new(Mutex::new(CollectedData {
+        memory_devices: collectors::dmi::read_memory_devices(&config.roots),
+        system_info: collectors::sysinfo::read_system_info(&config.roots),
+        ..Default::default()
+    }));
+    let mut scheduler = scheduler::Scheduler::new(Arc::clone(&shared_data), Arc::new(AtomicU64::new(0)));
+    for collector in collectors {
+        if config.metrics.is_empty() || config.metrics.iter().any(|m| m == collector.name()) {
+            scheduler.register(collector);
+        }
+    }
+    apply_intervals(&mut scheduler, config, &names);
+    add_recorder(&mut scheduler, config, &shared_data);
+    serve_openmetrics(&mut scheduler, config, &shared_data);
+    if let Some(address) = &config.agent {
+        match std::net::TcpListener::bind(address) {
+            Ok(listener) => scheduler.add_sink(Box::new(remote::Agent::spawn(listener, Arc::clone(&shared_data)))),
+            Err(error) => {
+                eprintln!("error: cannot listen on {}: {}", address, error);
+                std::process::exit(1);
+            }
+        }
+    }
+    if !config.headless {
+        scheduler.run();
+        return;
+    }
 
-fn collect_infos(shared_data: &Arc<Mutex<CollectedData>>) {
-    use std::process::{Command, Stdio};
-    use std::io::Read;
-    
-    let process1 = Command::new("/usr/bin/uname")
-                        .stdout(Stdio::piped())
-                        .args(["-a"])
-                        .spawn()
-                        .expect("failed to execute process1");
-    
-    let process2 = Command::new("/usr/bin/lshw")
-                        .stdout(Stdio::piped())
-                        .stderr(Stdio::null())
-                        .args(["-short"])
-                        .spawn()
-                        .expect("failed to execute process1");
-
-    let mut s1 = String::new();
-    process1.stdout.unwrap().read_to_string(&mut s1).unwrap();
-    let lines1 = s1.lines().map(str::to_string);
-    
-    let mut s2 = String::new();
-    process2.stdout.unwrap().read_to_string(&mut s2).unwrap();
-    let lines2 = s2.lines().map(str::to_string);
-    
-    let lines = lines1.chain(std::iter::once(String::from("\n"))).chain(lines2).collect();
-    let mut data = shared_data.lock().unwrap();
-    data.extra_infos = lines;
-    data.tick += 1;
+    let writer: Box<dyn std::io::Write + Send> = match &config.output {
+        Some(path) => match std::fs::File::create(path) {
+            Ok(file) => Box::new(std::io::BufWriter::new(file)),
+            Err(error) => {
+                eprintln!("error: cannot write to {}: {}", path.display(), error);
+                std::process::exit(1);
+            }
+        },
+        None => Box::new(std::io::stdout()),
+    };
+    scheduler.add_sink(Box::new(export::Exporter::new(writer, config.format, config.samples)));
+    scheduler.run();
 }
 
-#[derive(Default, Clone)]
+/// A process of the task list. Fields missing from older recordings,
+/// like `ppid` before the tree view, take their default.
+#[derive(Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ProcessInfo {
     pid: usize,
+    ppid: usize,
     nice: String,
     status: String,
     user: String,
     cpu: f64,
     memory: f64,
     cmd: String,
+    /// Resident, virtual and swapped out bytes.
+    rss: u64,
+    vsz: u64,
+    swap: u64,
+    threads: usize,
+    /// Seconds since the Unix epoch.
+    start: u64,
+    /// Seconds since the process started.
+    elapsed: u64,
+    tty: String,
+    priority: i64,
+    /// CPU the process last ran on.
+    processor: u32,
+    /// Bytes per second, unknown on the first sample or when the kernel
+    /// won't show another user's I/O.
+    io_read: Option<f64>,
+    io_write: Option<f64>,
+    oom_score: Option<i64>,
+    cgroup: String,
 }
 
-#[derive(Default, Clone)]
+/// Everything the collectors produced so far.
+///
+/// Remote snapshots send the newest CPU samples, the DIMM layout and the
+/// system info separately, so those are left out when serializing.
+#[derive(Default, Clone, Serialize, Deserialize)]
 struct CollectedData {
-    cpu_usage: Vec<VecDeque<f64>>,
-    ram_usage: (f64, f64, f64, f64, String, String, String),
-    disk_usage: Vec<(f64, String, String)>,
+    #[serde(skip)]
+    cpu_usage: Vec<VecDeque<CpuSample>>,
+    /// Per core, None without cpufreq. Missing from older recordings.
+    #[serde(default)]
+    cpu_policies: Vec<Option<CpuFrequencyPolicy>>,
+    memory: MemoryStats,
+    #[serde(skip)]
+    memory_devices: Vec<MemoryDevice>,
+    partitions: Vec<PartitionUsage>,
+    /// Missing from recordings made before the Disk I/O pane.
+    #[serde(default)]
+    block_devices: Vec<BlockDevice>,
+    /// Missing from recordings made before the Network pane.
+    #[serde(default)]
+    network: Vec<NetworkInterface>,
+    /// Missing from recordings made before the Sockets pane.
+    #[serde(default)]
+    sockets: Vec<SocketInfo>,
+    /// Missing from recordings made before the Sensors pane.
+    #[serde(default)]
+    sensors: Vec<Sensor>,
     process_list: Vec<ProcessInfo>,
-    extra_infos: Vec<String>,
+    #[serde(skip)]
+    system_info: SystemInfo,
+    /// Keyed by collector name.
+    health: HashMap<String, CollectorHealth>,
+    /// Process followed by the detail pane, picked in the task list.
+    #[serde(skip)]
+    detail_pid: Option<usize>,
+    #[serde(skip)]
+    process_details: Option<ProcessDetails>,
+    /// What the task list's columns need read, set by the window.
+    #[serde(skip)]
+    process_files: ProcessFiles,
+    #[serde(skip)]
     updated_tasks: bool,
+    #[serde(skip)]
     updated_memory: bool,
+    #[serde(skip)]
     updated_cpu: bool,
+    #[serde(skip)]
     updated_disks: bool,
+    #[serde(skip)]
+    updated_diskio: bool,
+    #[serde(skip)]
+    updated_network: bool,
+    #[serde(skip)]
+    updated_sockets: bool,
+    #[serde(skip)]
+    updated_sensors: bool,
+    #[serde(skip)]
     tick: u64,
 }
 
@@ -426,8 +346,194 @@ struct LocalData {
     current_data_copy: CollectedData,
     cpu_charts: Vec<CpuUsageChart>,
     disk_charts: Vec<DiskUsageChart>,
+    /// One chart of each kind per block device.
+    disk_io_charts: Vec<Vec<DiskIoChart>>,
+    /// Whether partitions get charts too, or just a line under their disk.
+    disk_io_partitions: bool,
+    /// One chart of each kind per interface.
+    network_charts: Vec<Vec<NetworkChart>>,
+    sensor_charts: Vec<SensorChart>,
     memory_chart: Option<MemoryUsageChart>,
     tasks_chart: Option<tasks::TasksListChart>,
+    cpu_breakdown: bool,
+    /// Whether the core charts draw the clock over the usage.
+    cpu_frequency: bool,
+    collapsed_info_sections: Vec<InfoSection>,
+    task_actions: TaskActions,
+    task_tree: bool,
+    /// Columns picked for the task list, for hosts whose list appears
+    /// later. Empty until the user picks some.
+    task_columns: Vec<tasks::Column>,
+    /// Whether the column checkboxes are shown above the list.
+    task_column_chooser: bool,
+    task_filter: String,
+    task_filter_error: Option<String>,
+    /// Why the filter was cleared without the user editing it.
+    task_filter_notice: Option<String>,
+    socket_sort: SocketColumn,
+    socket_sort_rev: bool,
+    /// Case-insensitive text matched against every column.
+    socket_filter: String,
+    /// Port typed in the "who uses port N" lookup.
+    socket_port: String,
+    /// Indices into `current_data_copy.sockets`, filtered and sorted.
+    socket_rows: Vec<usize>,
+    /// Recording time when replaying.
+    clock: Option<SystemTime>,
+}
+
+/// Signal and nice value picked in the Tasks pane, and the action waiting
+/// for confirmation with the PID and start time of its target.
+#[derive(Default)]
+struct TaskActions {
+    custom_signal: Option<control::Signal>,
+    nice: Option<i32>,
+    pending: Option<(usize, u64, control::ProcessAction)>,
+    error: Option<String>,
+}
+
+impl LocalData {
+    fn now(&self) -> SystemTime {
+        self.clock.unwrap_or_else(SystemTime::now)
+    }
+
+    /// The optional per-process files the task list's columns need.
+    fn process_files(&self) -> ProcessFiles {
+        let columns = match &self.tasks_chart {
+            Some(tasks_chart) => &tasks_chart.columns[..],
+            None if !self.task_columns.is_empty() => &self.task_columns[..],
+            None => &tasks::Column::DEFAULT[..],
+        };
+        let shown = |column| columns.contains(&column);
+        ProcessFiles {
+            io: shown(tasks::Column::IoRead) || shown(tasks::Column::IoWrite),
+            oom_score: shown(tasks::Column::OomScore),
+            cgroup: shown(tasks::Column::Cgroup),
+        }
+    }
+}
+
+/// A machine the window can show: this one, or an agent.
+struct Host {
+    shared_data: Arc<Mutex<CollectedData>>,
+    connection: Option<remote::Connection>,
+    local_data: LocalData,
+    /// `CollectedData::tick` of the last copy into `local_data`.
+    last_tick: u64,
+    /// Whether the task list may signal and renice its processes.
+    controllable: bool,
+}
+
+impl Host {
+    fn new(shared_data: Arc<Mutex<CollectedData>>, connection: Option<remote::Connection>) -> Self {
+        Self {
+            shared_data,
+            connection,
+            local_data: LocalData::default(),
+            last_tick: 0,
+            controllable: false,
+        }
+    }
+
+    fn name(&self) -> &str {
+        self.connection.as_ref().map_or("local", |connection| connection.address())
+    }
+
+    /// Selects `pid` in the task list and follows it in the Process pane.
+    fn select_task(&mut self, pid: usize) {
+        {
+            let mut data = self.shared_data.lock().unwrap_or_else(PoisonError::into_inner);
+            if data.detail_pid != Some(pid) {
+                data.detail_pid = Some(pid);
+                data.process_details = None;
+            }
+        }
+        if let Some(tasks_chart) = &mut self.local_data.tasks_chart {
+            tasks_chart.select(pid);
+        }
+    }
+
+    /// Copies the shared data and rebuilds the charts if it changed.
+    fn refresh(&mut self) {
+        {
+            let mut data = self.shared_data.lock().unwrap_or_else(PoisonError::into_inner);
+            if data.tick == self.last_tick {
+                return;
+            }
+            self.last_tick = data.tick;
+            self.local_data.current_data_copy = data.clone();
+            data.updated_tasks = false;
+            data.updated_memory = false;
+            data.updated_cpu = false;
+            data.updated_disks = false;
+            data.updated_diskio = false;
+            data.updated_network = false;
+            data.updated_sockets = false;
+            data.updated_sensors = false;
+        }
+        let local_data = &mut self.local_data;
+        local_data.update_cpus();
+        local_data.update_disks();
+
+        if local_data.current_data_copy.updated_cpu {
+            local_data.current_data_copy.updated_cpu = false;
+            local_data.update_cpus();
+        }
+        if local_data.current_data_copy.updated_disks {
+            local_data.current_data_copy.updated_disks = false;
+            local_data.update_disks();
+        }
+        if local_data.current_data_copy.updated_diskio {
+            local_data.current_data_copy.updated_diskio = false;
+            local_data.update_disk_io();
+        }
+        if local_data.current_data_copy.updated_network {
+            local_data.current_data_copy.updated_network = false;
+            local_data.update_network();
+        }
+        if local_data.current_data_copy.updated_sockets {
+            local_data.current_data_copy.updated_sockets = false;
+            local_data.update_sockets();
+        }
+        if local_data.current_data_copy.updated_sensors {
+            local_data.current_data_copy.updated_sensors = false;
+            local_data.update_sensors();
+        }
+        if local_data.current_data_copy.updated_memory {
+            local_data.current_data_copy.updated_memory = false;
+            local_data.update_memory();
+        }
+        if local_data.current_data_copy.updated_tasks {
+            local_data.current_data_copy.updated_tasks = false;
+            local_data.update_tasks();
+        }
+    }
+
+    /// Green when everything is fresh, amber when a collector is failing or
+    /// stale, red when the agent can't be reached. Paused collectors are stale
+    /// by design and don't count.
+    fn health_color(&self) -> Color {
+        if self.connection.as_ref().is_some_and(|connection| !connection.state().is_connected()) {
+            return HEALTH_DOWN_COLOR;
+        }
+        let now = self.local_data.now();
+        let degraded = self
+            .local_data
+            .current_data_copy
+            .health
+            .values()
+            .any(|health| health.is_failing() || (!health.paused && health.is_stale(now)));
+        if degraded { HEALTH_DEGRADED_COLOR } else { HEALTH_OK_COLOR }
+    }
+}
+
+struct Flags {
+    hosts: Vec<Host>,
+    shared_tick: Arc<AtomicU64>,
+    scheduler: scheduler::SchedulerHandle,
+    player: Option<recording::Player>,
+    /// Whether a recording is made, which wants every field.
+    recording: bool,
 }
 
 struct Example {
@@ -436,13 +542,19 @@ struct Example {
     focus: Option<pane_grid::Pane>,
     tasks_pane: Option<pane_grid::Pane>,
     last_tick: u64,
-    shared_data: Arc<Mutex<CollectedData>>,
+    /// Never empty; `current_host` is the one the panes show.
+    hosts: Vec<Host>,
+    current_host: usize,
     shared_tick: Arc<AtomicU64>,
-    local_data: LocalData,
+    scheduler: scheduler::SchedulerHandle,
+    player: Option<recording::Player>,
+    recording: bool,
+    active_collectors: Vec<&'static str>,
+    should_exit: bool,
     show_title_bar: bool,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 enum Message {
     SplitFocused(pane_grid::Axis),
     FocusAdjacent(pane_grid::Direction),
@@ -459,8 +571,39 @@ enum Message {
     ChangeType(pane_grid::Pane, PaneType),
     ChangeTypeFocused(PaneType),
     DraggedTask(usize, f32),
-    SortTasks(usize),
+    SortTasks(tasks::Column),
+    ToggleTaskColumn(tasks::Column, bool),
+    MoveTaskColumn(usize, usize),
+    ToggleTaskColumnChooser,
+    SelectTask(usize),
+    MoveTaskSelection(tasks::Move),
+    ScrollTasks(isize),
+    ScrollTasksTo(usize),
+    ToggleTaskTree(bool),
+    FilterTasks(String),
+    ToggleTaskNode(usize),
+    SortSockets(SocketColumn),
+    FilterSockets(String),
+    LookupPort(String),
+    /// Shows a process in the Tasks pane, opening one if needed.
+    JumpToTask(usize),
+    RequestProcessAction(control::ProcessAction),
+    ConfirmProcessAction,
+    CancelProcessAction,
+    SetCustomSignal(control::Signal),
+    SetNice(i32),
+    DismissProcessError,
     ToggleTitleBar,
+    ToggleCpuBreakdown(bool),
+    ToggleCpuFrequency(bool),
+    ToggleDiskIoPartitions(bool),
+    ToggleInfoSection(InfoSection),
+    SetCollectorInterval(&'static str, Duration),
+    TogglePlayback,
+    SetPlaybackSpeed(PlaybackSpeed),
+    SeekPlayback(u32),
+    SelectHost(usize),
+    CloseRequested,
 }
 
 impl LocalData {
@@ -468,18 +611,68 @@ impl LocalData {
         while self.current_data_copy.cpu_usage.len() > self.cpu_charts.len() {
             self.cpu_charts.push(CpuUsageChart::new(MAX_POINTS));
         }
+        self.cpu_charts.truncate(self.current_data_copy.cpu_usage.len());
         for (i, chart) in self.cpu_charts.iter_mut().enumerate() {
-            chart.set_data(self.current_data_copy.cpu_usage[i].clone().into_iter());
+            chart.set_data(self.current_data_copy.cpu_usage[i].clone().into_iter(), self.cpu_breakdown);
+            chart.set_frequency(self.current_data_copy.cpu_policies.get(i).cloned().flatten(), self.cpu_frequency);
         }
     }
 
     fn update_disks(&mut self) {
-        while self.current_data_copy.disk_usage.len() > self.disk_charts.len() {
+        while self.current_data_copy.partitions.len() > self.disk_charts.len() {
             self.disk_charts.push(DiskUsageChart::new());
         }
-        for (i, chart) in self.disk_charts.iter_mut().enumerate() {
-            let used = &self.current_data_copy.disk_usage[i];
-            chart.set_data((used.0, 100.0 - used.0), used.1.clone(), used.2.clone());
+        self.disk_charts.truncate(self.current_data_copy.partitions.len());
+        for (chart, partition) in self.disk_charts.iter_mut().zip(&self.current_data_copy.partitions) {
+            chart.set_data(partition.clone());
+        }
+    }
+
+    fn update_disk_io(&mut self) {
+        let devices = &self.current_data_copy.block_devices;
+        while devices.len() > self.disk_io_charts.len() {
+            self.disk_io_charts.push(DiskIoChartKind::ALL.iter().map(|&kind| DiskIoChart::new(kind, MAX_POINTS)).collect());
+        }
+        self.disk_io_charts.truncate(devices.len());
+        for (charts, device) in self.disk_io_charts.iter_mut().zip(devices) {
+            for chart in charts {
+                chart.set_data(&device.history);
+            }
+        }
+    }
+
+    fn update_network(&mut self) {
+        let interfaces = &self.current_data_copy.network;
+        while interfaces.len() > self.network_charts.len() {
+            self.network_charts.push(NetworkChartKind::ALL.iter().map(|&kind| NetworkChart::new(kind, MAX_POINTS)).collect());
+        }
+        self.network_charts.truncate(interfaces.len());
+        for (charts, interface) in self.network_charts.iter_mut().zip(interfaces) {
+            for chart in charts {
+                chart.set_data(&interface.history);
+            }
+        }
+    }
+
+    fn update_sockets(&mut self) {
+        let sockets = &self.current_data_copy.sockets;
+        let filter = self.socket_filter.trim().to_lowercase();
+        self.socket_rows = (0..sockets.len()).filter(|&i| sockets[i].matches(&filter)).collect();
+        let (sort, rev) = (self.socket_sort, self.socket_sort_rev);
+        self.socket_rows.sort_by(|&a, &b| {
+            let ordering = sort.compare(&sockets[a], &sockets[b]);
+            if rev { ordering.reverse() } else { ordering }
+        });
+    }
+
+    fn update_sensors(&mut self) {
+        let sensors = &self.current_data_copy.sensors;
+        while sensors.len() > self.sensor_charts.len() {
+            self.sensor_charts.push(SensorChart::new(MAX_POINTS));
+        }
+        self.sensor_charts.truncate(sensors.len());
+        for (chart, sensor) in self.sensor_charts.iter_mut().zip(sensors) {
+            chart.set_data(sensor);
         }
     }
 
@@ -488,18 +681,68 @@ impl LocalData {
             self.memory_chart = Some(MemoryUsageChart::new());
         }
         if let Some(memory_chart) = &mut self.memory_chart {
-            let mem_data = &self.current_data_copy.ram_usage;
-            memory_chart.set_data((mem_data.0, mem_data.1), (mem_data.2, mem_data.3), mem_data.4.clone(), mem_data.5.clone(), mem_data.6.clone());
+            let tech = collectors::dmi::memory_technology(&self.current_data_copy.memory_devices);
+            memory_chart.set_data(self.current_data_copy.memory, tech);
         }
     }
 
     fn update_tasks(&mut self) {
         if self.tasks_chart.is_none() {
-            self.tasks_chart = Some(tasks::TasksListChart::new());
+            let mut tasks_chart = tasks::TasksListChart::new();
+            if !self.task_columns.is_empty() {
+                tasks_chart.set_columns(&self.task_columns);
+            }
+            tasks_chart.filter = filter::Filter::parse(&self.task_filter).ok().flatten();
+            self.tasks_chart = Some(tasks_chart);
         }
         if let Some(tasks_chart) = &mut self.tasks_chart {
             let process_data = &self.current_data_copy.process_list;
+            tasks_chart.tree = self.task_tree;
             tasks_chart.set_data(process_data);
+            // A process picked before the list existed, e.g. from the Sockets pane.
+            if let (None, Some(pid)) = (tasks_chart.selected, self.current_data_copy.detail_pid) {
+                tasks_chart.select(pid);
+            }
+        }
+    }
+}
+
+impl Example {
+    /// Keeps the columns of the current host's task list for the hosts
+    /// whose list doesn't exist yet.
+    fn remember_task_columns(&mut self) {
+        let columns = match &self.hosts[self.current_host].local_data.tasks_chart {
+            Some(tasks_chart) => tasks_chart.columns.clone(),
+            None => return,
+        };
+        for host in &mut self.hosts {
+            host.local_data.task_columns = columns.clone();
+        }
+    }
+
+    /// Lets the task list skip the per-process files no shown column needs,
+    /// unless a recording keeps them all.
+    fn sync_process_files(&mut self) {
+        for host in &self.hosts {
+            let files = if self.recording { ProcessFiles::ALL } else { host.local_data.process_files() };
+            let mut data = host.shared_data.lock().unwrap_or_else(PoisonError::into_inner);
+            data.process_files = files;
+        }
+    }
+
+    /// Pauses the collectors whose data no pane is showing. `force` sends the
+    /// set even when it hasn't changed, as at startup.
+    fn sync_active_collectors(&mut self, force: bool) {
+        let mut active: Vec<&'static str> = self
+            .panes
+            .iter()
+            .flat_map(|(_, pane)| pane.pane_type.collectors().iter().copied())
+            .collect();
+        active.sort_unstable();
+        active.dedup();
+        if force || active != self.active_collectors {
+            self.scheduler.set_active(active.clone());
+            self.active_collectors = active;
         }
     }
 }
@@ -508,31 +751,31 @@ impl Application for Example {
     type Message = Message;
     type Theme = Theme;
     type Executor = executor::Default;
-    type Flags = (Arc<Mutex<CollectedData>>, Arc<AtomicU64>);
+    type Flags = Flags;
 
     fn new(flags: Self::Flags) -> (Self, Command<Self::Message>) {
         let (panes, _) = pane_grid::State::new(Pane::new(0));
 
-        (
-            Example {
-                panes,
-                panes_created: 1,
-                focus: None,
-                tasks_pane: None,
-                last_tick: 0,
-                shared_data: flags.0,
-                shared_tick: flags.1,
-                local_data: LocalData {
-                    current_data_copy: CollectedData::default(),
-                    cpu_charts: Vec::new(),
-                    disk_charts: Vec::new(),
-                    memory_chart: None,
-                    tasks_chart: None,
-                },
-                show_title_bar: false,
-            },
-            Command::none(),
-        )
+        let mut example = Example {
+            panes,
+            panes_created: 1,
+            focus: None,
+            tasks_pane: None,
+            last_tick: 0,
+            hosts: flags.hosts,
+            current_host: 0,
+            shared_tick: flags.shared_tick,
+            scheduler: flags.scheduler,
+            player: flags.player,
+            recording: flags.recording,
+            active_collectors: Vec::new(),
+            should_exit: false,
+            show_title_bar: false,
+        };
+        // Pauses what the first layout doesn't show, as later layout changes do.
+        example.sync_active_collectors(true);
+
+        (example, Command::none())
     }
 
     fn title(&self) -> String {
@@ -609,35 +852,20 @@ impl Application for Example {
                 self.focus = None;
             }
             Message::Tick => {
+                if let Some(player) = &mut self.player {
+                    let host = &mut self.hosts[self.current_host];
+                    if player.advance() || host.local_data.clock.is_none() {
+                        player.apply(&mut host.shared_data.lock().unwrap_or_else(PoisonError::into_inner));
+                        self.shared_tick.fetch_add(1, Ordering::SeqCst);
+                    }
+                    host.local_data.clock = Some(player.time());
+                }
                 let current_tick = self.shared_tick.load(Ordering::SeqCst);
                 if self.last_tick != current_tick {
                     self.last_tick = current_tick;
-                    {
-                        let mut data = self.shared_data.lock().unwrap();
-                        self.local_data.current_data_copy = data.clone();
-                        data.updated_tasks = false;
-                        data.updated_memory = false;
-                        data.updated_cpu = false;
-                        data.updated_disks = false;
-                    }
-                    self.local_data.update_cpus();
-                    self.local_data.update_disks();
-
-                    if self.local_data.current_data_copy.updated_cpu {
-                        self.local_data.current_data_copy.updated_cpu = false;
-                        self.local_data.update_cpus();
-                    }
-                    if self.local_data.current_data_copy.updated_disks {
-                        self.local_data.current_data_copy.updated_disks = false;
-                        self.local_data.update_disks();
-                    }
-                    if self.local_data.current_data_copy.updated_memory {
-                        self.local_data.current_data_copy.updated_memory = false;
-                        self.local_data.update_memory();
-                    }
-                    if self.local_data.current_data_copy.updated_tasks {
-                        self.local_data.current_data_copy.updated_tasks = false;
-                        self.local_data.update_tasks();
+                    // Every host stays current for the overview.
+                    for host in &mut self.hosts {
+                        host.refresh();
                     }
                 }
             }
@@ -673,26 +901,268 @@ impl Application for Example {
                 }
             }
             Message::DraggedTask(selected, new_sep) => {
-                if let Some(tasks_chart) = &mut self.local_data.tasks_chart {
-                    tasks_chart.separators[selected] = new_sep;
+                for host in &mut self.hosts {
+                    // Hosts whose list appeared later may show other columns.
+                    if let Some(separator) = host.local_data.tasks_chart.as_mut().and_then(|chart| chart.separators.get_mut(selected)) {
+                        *separator = new_sep;
+                    }
                 }
             }
             Message::SortTasks(selected) => {
-                if let Some(tasks_chart) = &mut self.local_data.tasks_chart {
-                    tasks_chart.sort_by(selected);
+                for host in &mut self.hosts {
+                    if let Some(tasks_chart) = &mut host.local_data.tasks_chart {
+                        tasks_chart.sort_by(selected);
+                    }
+                }
+            }
+            Message::ToggleTaskColumn(column, shown) => {
+                for host in &mut self.hosts {
+                    if let Some(tasks_chart) = &mut host.local_data.tasks_chart {
+                        tasks_chart.set_column(column, shown);
+                    }
+                }
+                self.remember_task_columns();
+            }
+            Message::MoveTaskColumn(from, to) => {
+                for host in &mut self.hosts {
+                    if let Some(tasks_chart) = &mut host.local_data.tasks_chart {
+                        tasks_chart.move_column(from, to);
+                    }
+                }
+                self.remember_task_columns();
+            }
+            Message::ToggleTaskColumnChooser => {
+                for host in &mut self.hosts {
+                    host.local_data.task_column_chooser = !host.local_data.task_column_chooser;
+                }
+            }
+            Message::SelectTask(pid) => {
+                self.hosts[self.current_host].select_task(pid);
+                // Open a detail pane next to the task list unless one is shown.
+                if !self.panes.iter().any(|(_, pane)| pane.pane_type == PaneType::Process) {
+                    if let Some(tasks_pane) = self.tasks_pane.or(self.focus) {
+                        let mut pane = Pane::new(self.panes_created);
+                        pane.pane_type = PaneType::Process;
+                        if self.panes.split(pane_grid::Axis::Vertical, &tasks_pane, pane).is_some() {
+                            self.panes_created += 1;
+                        }
+                    }
+                }
+            }
+            Message::ToggleTaskTree(tree) => {
+                for host in &mut self.hosts {
+                    host.local_data.task_tree = tree;
+                    if let Some(tasks_chart) = &mut host.local_data.tasks_chart {
+                        tasks_chart.set_tree(tree);
+                    }
+                }
+            }
+            Message::FilterTasks(input) => {
+                let parsed = filter::Filter::parse(&input);
+                for host in &mut self.hosts {
+                    let local_data = &mut host.local_data;
+                    local_data.task_filter = input.clone();
+                    local_data.task_filter_notice = None;
+                    // A half typed expression keeps the last valid filter.
+                    match &parsed {
+                        Ok(filter) => {
+                            local_data.task_filter_error = None;
+                            if let Some(tasks_chart) = &mut local_data.tasks_chart {
+                                tasks_chart.set_filter(filter.clone());
+                            }
+                        }
+                        Err(error) => local_data.task_filter_error = Some(error.clone()),
+                    }
+                }
+            }
+            Message::SortSockets(column) => {
+                for host in &mut self.hosts {
+                    let local_data = &mut host.local_data;
+                    local_data.socket_sort_rev = local_data.socket_sort == column && !local_data.socket_sort_rev;
+                    local_data.socket_sort = column;
+                    local_data.update_sockets();
+                }
+            }
+            Message::FilterSockets(input) => {
+                for host in &mut self.hosts {
+                    host.local_data.socket_filter = input.clone();
+                    host.local_data.update_sockets();
+                }
+            }
+            Message::LookupPort(input) => {
+                // Only digits, so the field never holds something unparsable.
+                if input.chars().all(|c| c.is_ascii_digit()) && input.len() <= 5 {
+                    for host in &mut self.hosts {
+                        host.local_data.socket_port = input.clone();
+                    }
+                }
+            }
+            Message::JumpToTask(pid) => {
+                let tasks_pane = self.tasks_pane.filter(|pane| self.panes.get(pane).is_some());
+                let tasks_pane = match tasks_pane {
+                    Some(pane) => Some(pane),
+                    None => {
+                        let target = self.focus.or_else(|| self.panes.iter().next().map(|(pane, _)| *pane));
+                        let mut pane = Pane::new(self.panes_created);
+                        pane.pane_type = PaneType::Tasks;
+                        let split = target.and_then(|target| self.panes.split(pane_grid::Axis::Vertical, &target, pane));
+                        split.map(|(pane, _)| {
+                            self.panes_created += 1;
+                            pane
+                        })
+                    }
+                };
+                self.tasks_pane = tasks_pane;
+                if tasks_pane.is_some() {
+                    self.focus = tasks_pane;
+                }
+
+                // Clear a filter hiding the process rather than jump to nothing, and say so.
+                let hidden = self.hosts[self.current_host].local_data.tasks_chart.as_ref().is_some_and(|chart| {
+                    chart.all.iter().any(|p| p.pid == pid) && !chart.process_info.iter().any(|p| p.pid == pid)
+                });
+                if hidden {
+                    let filter = self.hosts[self.current_host].local_data.task_filter.clone();
+                    let _ = self.update(Message::FilterTasks(String::new()));
+                    for host in &mut self.hosts {
+                        host.local_data.task_filter_notice = Some(format!("Cleared the filter \"{}\", which hid PID {}", filter, pid));
+                    }
                 }
+                // Without a list yet, it picks the selection up when it appears.
+                return self.update(Message::SelectTask(pid));
+            }
+            Message::MoveTaskSelection(movement) => {
+                // The keys keep their usual meaning in the other panes.
+                let focused = self.focus.and_then(|pane| self.panes.get(&pane));
+                let tasks_focused = focused.is_some_and(|pane| pane.pane_type == PaneType::Tasks);
+                let host = &mut self.hosts[self.current_host];
+                let tasks_chart = host.local_data.tasks_chart.as_mut().filter(|_| tasks_focused);
+                if let Some(pid) = tasks_chart.and_then(|chart| chart.move_selection(movement)) {
+                    host.select_task(pid);
+                }
+            }
+            Message::ScrollTasks(rows) => {
+                if let Some(tasks_chart) = &mut self.hosts[self.current_host].local_data.tasks_chart {
+                    tasks_chart.scroll_to(tasks_chart.scroll.saturating_add_signed(rows));
+                }
+            }
+            Message::ScrollTasksTo(row) => {
+                if let Some(tasks_chart) = &mut self.hosts[self.current_host].local_data.tasks_chart {
+                    tasks_chart.scroll_to(row);
+                }
+            }
+            Message::ToggleTaskNode(pid) => {
+                if let Some(tasks_chart) = &mut self.hosts[self.current_host].local_data.tasks_chart {
+                    tasks_chart.toggle_collapsed(pid);
+                }
+            }
+            Message::RequestProcessAction(action) => {
+                let host = &mut self.hosts[self.current_host];
+                let selected = host.local_data.tasks_chart.as_ref().and_then(|chart| chart.selected);
+                if let (true, Some(pid)) = (host.controllable, selected) {
+                    let actions = &mut host.local_data.task_actions;
+                    match control::check_target(pid).and_then(|()| control::start_time(pid)) {
+                        Ok(start_time) => {
+                            actions.pending = Some((pid, start_time, action));
+                            actions.error = None;
+                        }
+                        Err(error) => actions.error = Some(error),
+                    }
+                }
+            }
+            Message::ConfirmProcessAction => {
+                let actions = &mut self.hosts[self.current_host].local_data.task_actions;
+                if let Some((pid, start_time, action)) = actions.pending.take() {
+                    actions.error = control::apply(pid, start_time, action).err();
+                }
+            }
+            Message::CancelProcessAction => {
+                self.hosts[self.current_host].local_data.task_actions.pending = None;
+            }
+            Message::SetCustomSignal(signal) => {
+                self.hosts[self.current_host].local_data.task_actions.custom_signal = Some(signal);
+            }
+            Message::SetNice(nice) => {
+                self.hosts[self.current_host].local_data.task_actions.nice = Some(nice);
+            }
+            Message::DismissProcessError => {
+                self.hosts[self.current_host].local_data.task_actions.error = None;
             }
             Message::ToggleTitleBar => {
                 self.show_title_bar = !self.show_title_bar;
             }
+            Message::TogglePlayback => {
+                if let Some(player) = &mut self.player {
+                    player.toggle();
+                }
+            }
+            Message::SetPlaybackSpeed(speed) => {
+                if let Some(player) = &mut self.player {
+                    player.set_speed(speed.factor());
+                }
+            }
+            Message::SeekPlayback(position) => {
+                if let Some(player) = &mut self.player {
+                    player.seek(position as usize);
+                    player.apply(&mut self.hosts[self.current_host].shared_data.lock().unwrap_or_else(PoisonError::into_inner));
+                    self.shared_tick.fetch_add(1, Ordering::SeqCst);
+                }
+            }
+            Message::SelectHost(index) => {
+                self.current_host = index;
+            }
+            Message::SetCollectorInterval(name, interval) => {
+                self.scheduler.set_interval(name, interval);
+            }
+            Message::CloseRequested => {
+                self.scheduler.shutdown();
+                self.should_exit = true;
+            }
+            Message::ToggleInfoSection(section) => {
+                for host in &mut self.hosts {
+                    let collapsed = &mut host.local_data.collapsed_info_sections;
+                    if let Some(i) = collapsed.iter().position(|&s| s == section) {
+                        collapsed.remove(i);
+                    } else {
+                        collapsed.push(section);
+                    }
+                }
+            }
+            Message::ToggleCpuBreakdown(show) => {
+                for host in &mut self.hosts {
+                    host.local_data.cpu_breakdown = show;
+                    host.local_data.update_cpus();
+                }
+            }
+            Message::ToggleCpuFrequency(show) => {
+                for host in &mut self.hosts {
+                    host.local_data.cpu_frequency = show;
+                    host.local_data.update_cpus();
+                }
+            }
+            Message::ToggleDiskIoPartitions(show) => {
+                for host in &mut self.hosts {
+                    host.local_data.disk_io_partitions = show;
+                }
+            }
         }
 
+        self.sync_active_collectors(false);
+        self.sync_process_files();
+
         Command::none()
     }
 
+    fn should_exit(&self) -> bool {
+        self.should_exit
+    }
+
     fn subscription(&self) -> Subscription<Self::Message> {
         Subscription::batch([
             subscription::events_with(|event, status| {
+                if let Event::Window(window::Event::CloseRequested) = event {
+                    return Some(Message::CloseRequested);
+                }
                 if let event::Status::Captured = status {
                     return None;
                 }
@@ -707,7 +1177,7 @@ impl Application for Example {
                         } else if modifiers.shift() {
                             handle_hotkey(key_code, false)
                         } else {
-                            None
+                            handle_task_key(key_code)
                         }
                     },
                     _ => None,
@@ -741,6 +1211,9 @@ impl Application for Example {
                     pane.is_pinned,
                     maximized,
                     pane.pane_type,
+                    pane.pane_type.collectors().first().and_then(|&name| {
+                        self.scheduler.interval(name).map(|interval| (name, interval))
+                    }),
                 ))
                 .padding(10)
                 .style(if is_focused {
@@ -756,7 +1229,8 @@ impl Application for Example {
                     pane.is_pinned,
                     size,
                     pane.pane_type,
-                    &self.local_data,
+                    &self.hosts,
+                    self.current_host,
                 )
             }));
 
@@ -783,7 +1257,15 @@ impl Application for Example {
         .on_drag(Message::Dragged)
         .on_resize(10, Message::Resized);
 
-        container(pane_grid)
+        let mut content = column![pane_grid].spacing(10);
+        if self.hosts.iter().any(|host| host.connection.is_some()) {
+            content = content.push(view_hosts(&self.hosts, self.current_host));
+        }
+        if let Some(player) = &self.player {
+            content = content.push(view_playback(player));
+        }
+
+        container(content)
             .width(Length::Fill)
             .height(Length::Fill)
             .padding(10)
@@ -818,12 +1300,36 @@ fn handle_hotkey(key_code: keyboard::KeyCode, control: bool) -> Option<Message>
             KeyCode::T => Some(Message::ChangeTypeFocused(PaneType::Tasks)),
             KeyCode::D => Some(Message::ChangeTypeFocused(PaneType::Disks)),
             KeyCode::I => Some(Message::ChangeTypeFocused(PaneType::Info)),
+            KeyCode::O => Some(Message::ChangeTypeFocused(PaneType::Overview)),
+            KeyCode::P => Some(Message::ChangeTypeFocused(PaneType::Process)),
+            KeyCode::N => Some(Message::ChangeTypeFocused(PaneType::Network)),
+            KeyCode::K => Some(Message::ChangeTypeFocused(PaneType::DiskIo)),
+            KeyCode::H => Some(Message::ChangeTypeFocused(PaneType::Sensors)),
+            KeyCode::S => Some(Message::ChangeTypeFocused(PaneType::Sockets)),
             KeyCode::B => Some(Message::ToggleTitleBar),
             _ => None,
         }
     }
 }
 
+/// Keys acting on the selected task without a modifier.
+fn handle_task_key(key_code: keyboard::KeyCode) -> Option<Message> {
+    use control::{ProcessAction, Signal};
+    use keyboard::KeyCode;
+
+    match key_code {
+        KeyCode::Delete => Some(Message::RequestProcessAction(ProcessAction::Signal(Signal::TERM))),
+        KeyCode::Escape => Some(Message::CancelProcessAction),
+        KeyCode::Up => Some(Message::MoveTaskSelection(tasks::Move::Up)),
+        KeyCode::Down => Some(Message::MoveTaskSelection(tasks::Move::Down)),
+        KeyCode::PageUp => Some(Message::MoveTaskSelection(tasks::Move::PageUp)),
+        KeyCode::PageDown => Some(Message::MoveTaskSelection(tasks::Move::PageDown)),
+        KeyCode::Home => Some(Message::MoveTaskSelection(tasks::Move::Home)),
+        KeyCode::End => Some(Message::MoveTaskSelection(tasks::Move::End)),
+        _ => None,
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum PaneType {
     Selection,
@@ -832,6 +1338,12 @@ enum PaneType {
     Disks,
     Info,
     Tasks,
+    Overview,
+    Process,
+    Network,
+    Sockets,
+    DiskIo,
+    Sensors,
 }
 
 struct Pane {
@@ -877,6 +1389,22 @@ const MEM_FREE_COLOR: Color = Color::from_rgb(
     1.0
 );
 
+const HEALTH_OK_COLOR: Color = Color::from_rgb(
+    120_f32 / 255.0,
+    210_f32 / 255.0,
+    120_f32 / 255.0
+);
+const HEALTH_DEGRADED_COLOR: Color = Color::from_rgb(
+    1.0,
+    190_f32 / 255.0,
+    60_f32 / 255.0
+);
+const HEALTH_DOWN_COLOR: Color = Color::from_rgb(
+    1.0,
+    90_f32 / 255.0,
+    90_f32 / 255.0
+);
+
 #[derive(Debug)]
 struct ColoredRect {
     color: Color,
@@ -931,14 +1459,51 @@ impl canvas::Program<Message> for ColoredRect {
 }
 
 impl PaneType {
-    fn content<'a>(&self, data: &'a LocalData, size: Size) -> Element<'a, Message> {
+    fn content<'a>(&self, hosts: &'a [Host], current_host: usize, size: Size) -> Element<'a, Message> {
+        let data = &hosts[current_host].local_data;
         match *self {
             PaneType::Selection => {
                 text("Select pane type").size(16).into()
             }
             PaneType::Cpu => {
+                let mut legend = row(vec![
+                    checkbox("Breakdown", data.cpu_breakdown, Message::ToggleCpuBreakdown)
+                        .size(16)
+                        .text_size(16)
+                        .into(),
+                ])
+                .spacing(5)
+                .align_items(Alignment::Center);
+                // Only offered where cpufreq reports a clock.
+                if data.cpu_charts.iter().any(CpuUsageChart::has_frequency) {
+                    legend = legend.push(
+                        checkbox("Frequency", data.cpu_frequency, Message::ToggleCpuFrequency)
+                            .size(16)
+                            .text_size(16),
+                    );
+                    if data.cpu_frequency {
+                        let (r, g, b) = CPU_FREQUENCY_COLOR;
+                        legend = legend
+                            .push(canvas(ColoredRect { color: Color::from_rgb8(r, g, b) })
+                                .width(Length::Units(20))
+                                .height(Length::Units(20)))
+                            .push(text("Clock").size(16));
+                    }
+                }
+
+                if data.cpu_breakdown {
+                    for (name, (r, g, b)) in CPU_BREAKDOWN_COLORS {
+                        legend = legend
+                            .push(canvas(ColoredRect { color: Color::from_rgb8(r, g, b) })
+                                .width(Length::Units(20))
+                                .height(Length::Units(20)))
+                            .push(text(name).size(16));
+                    }
+                }
+
                 let mut content = column![
                     text("CPU").size(24),
+                    legend,
                 ]
                 .width(Length::Fill)
                 // .height(Length::Fill)
@@ -949,11 +1514,12 @@ impl PaneType {
 
                 let min_width = 150.0 + (padding as f32);
                 let width = size.width - (padding as f32);
+                // An empty list would make this 0, which chunks() panics on.
                 let items_per_row = std::cmp::min(
                     std::cmp::max((width / min_width).trunc() as usize, 1usize),
                     data.cpu_charts.len()
-                );
-                let width_per_item = (width / (items_per_row as f32)) as u16 - padding;
+                ).max(1);
+                let width_per_item = ((width / (items_per_row as f32)) as u16).saturating_sub(padding);
                 let height_per_item = (width_per_item * 2) / 3;
                 // let max_width = 450;
 
@@ -997,7 +1563,7 @@ impl PaneType {
                             .width(Length::Units(20))
                             .height(Length::Units(20))
                             .into(),
-                        text("Buffered").size(16).into(),
+                        text("Buff/Cache").size(16).into(),
                         canvas(ColoredRect { color: MEM_FREE_COLOR })
                             .width(Length::Units(20))
                             .height(Length::Units(20))
@@ -1016,9 +1582,359 @@ impl PaneType {
                     .height(Length::Units(150))
                     );
                 }
+
+                let stats = &data.current_data_copy.memory;
+                let details = [
+                    ("Total", stats.total),
+                    ("Available", stats.available),
+                    ("Used", stats.used()),
+                    ("Free", stats.free),
+                    ("Buffers", stats.buffers),
+                    ("Cached", stats.cached),
+                    ("Shared", stats.shmem),
+                    ("Slab", stats.slab),
+                    ("Slab reclaimable", stats.s_reclaimable),
+                    ("Anonymous", stats.anon_pages),
+                    ("Dirty", stats.dirty),
+                    ("Writeback", stats.writeback),
+                    ("Huge pages", stats.huge_pages_bytes()),
+                    ("Huge pages free", stats.huge_pages_free * stats.huge_page_size),
+                    ("Swap total", stats.swap_total),
+                    ("Swap used", stats.swap_used()),
+                    ("Swap cached", stats.swap_cached),
+                ];
+                let mut labels = column![].spacing(2).align_items(Alignment::Start);
+                let mut values = column![].spacing(2).align_items(Alignment::End);
+                for (label, bytes) in details {
+                    labels = labels.push(text(label).size(16));
+                    values = values.push(text(format_bytes(bytes)).size(16));
+                }
+                content = content.push(row![labels, values].spacing(20));
+
+                let devices = &data.current_data_copy.memory_devices;
+                if devices.is_empty() {
+                    content = content.push(text("Memory modules: unknown").size(16));
+                } else {
+                    let headers = ["Slot", "Size", "Type", "Speed", "Manufacturer", "Part number"];
+                    let mut columns: Vec<_> = headers
+                        .iter()
+                        .map(|header| column![text(header).size(16)].spacing(2))
+                        .collect();
+                    for device in devices {
+                        let slot = if device.bank_locator.is_empty() {
+                            device.locator.clone()
+                        } else {
+                            format!("{} ({})", device.locator, device.bank_locator)
+                        };
+                        let cells = match device.size {
+                            None => [slot, String::from("Empty"), String::new(), String::new(), String::new(), String::new()],
+                            Some(size) => [
+                                slot,
+                                format_bytes(size),
+                                device.memory_type.clone(),
+                                match (device.configured_speed, device.speed) {
+                                    (Some(current), Some(max)) if current != max => format!("{} MT/s (max {})", current, max),
+                                    (Some(speed), _) | (None, Some(speed)) => format!("{} MT/s", speed),
+                                    (None, None) => String::from("unknown"),
+                                },
+                                device.manufacturer.clone(),
+                                device.part_number.clone(),
+                            ],
+                        };
+                        columns = columns
+                            .into_iter()
+                            .zip(cells)
+                            .map(|(column, cell)| column.push(text(cell).size(16)))
+                            .collect();
+                    }
+                    content = content.push(row(columns.into_iter().map(Element::from).collect()).spacing(15));
+                }
                 
                 content.into()
             }
+            PaneType::Sensors => {
+                let mut content = column![text("Sensors").size(24)]
+                    .width(Length::Fill)
+                    .spacing(5)
+                    .align_items(Alignment::Center);
+
+                let sensors = &data.current_data_copy.sensors;
+                if sensors.is_empty() {
+                    return content
+                        .push(text("No sensors").size(16))
+                        .push(text("Nothing is listed under /sys/class/hwmon or /sys/class/thermal, as is usual in virtual machines").size(14))
+                        .into();
+                }
+
+                let padding = 10;
+                let min_width = 200.0 + (padding as f32);
+                let width = size.width - (padding as f32);
+                let items_per_row = ((width / min_width).trunc() as usize).max(1);
+                let width_per_item = ((width / (items_per_row as f32)) as u16).saturating_sub(padding);
+                let height_per_item = 150;
+
+                // Each chip's sensors together, in the order the kernel lists them.
+                let mut start = 0;
+                while start < sensors.len() {
+                    let chip = &sensors[start].chip;
+                    let len = sensors[start..].iter().take_while(|sensor| &sensor.chip == chip).count();
+                    content = content.push(
+                        container(text(chip).size(20))
+                            .width(Length::Fill)
+                            .padding([5, padding, 0, padding]),
+                    );
+                    for charts in data.sensor_charts.get(start..start + len).unwrap_or_default().chunks(items_per_row) {
+                        let mut row = row(vec![])
+                            .spacing(padding)
+                            .padding(padding)
+                            .width(Length::Fill)
+                            .height(Length::Units(height_per_item));
+                        for chart in charts {
+                            row = row.push(container(chart.view())
+                                .width(Length::Units(width_per_item))
+                                .height(Length::Units(height_per_item)));
+                        }
+                        content = content.push(row);
+                    }
+                    start += len;
+                }
+
+                content.into()
+            }
+            PaneType::DiskIo => {
+                let mut legend = row![].spacing(5).align_items(Alignment::Center);
+                for (name, (r, g, b)) in DISK_IO_COLORS {
+                    legend = legend
+                        .push(canvas(ColoredRect { color: Color::from_rgb8(r, g, b) })
+                            .width(Length::Units(20))
+                            .height(Length::Units(20)))
+                        .push(text(name).size(16));
+                }
+                legend = legend.push(
+                    checkbox("Partition charts", data.disk_io_partitions, Message::ToggleDiskIoPartitions)
+                        .size(16)
+                        .text_size(16),
+                );
+                let mut content = column![text("Disk I/O").size(24), legend.spacing(10)]
+                    .width(Length::Fill)
+                    .spacing(5)
+                    .align_items(Alignment::Center);
+
+                let devices = &data.current_data_copy.block_devices;
+                if devices.is_empty() {
+                    return content.push(text("No block devices").size(16)).into();
+                }
+
+                let padding = 10;
+                let min_width = 200.0 + (padding as f32);
+                let width = size.width - (padding as f32);
+                let items_per_row = ((width / min_width).trunc() as usize).clamp(1, DiskIoChartKind::ALL.len());
+                let width_per_item = ((width / (items_per_row as f32)) as u16).saturating_sub(padding);
+                let height_per_item = 150;
+
+                for (device, charts) in devices.iter().zip(&data.disk_io_charts) {
+                    let summary = match &device.rates {
+                        Some(rates) => format!(
+                            "read {}/s, write {}/s, {:.0} IOPS, {:.0}% busy",
+                            format_bytes(rates.read_bytes as u64),
+                            format_bytes(rates.write_bytes as u64),
+                            rates.reads + rates.writes,
+                            rates.utilization,
+                        ),
+                        None => String::from("measuring..."),
+                    };
+                    // Partitions sit under their disk, indented.
+                    let (name_size, indent) = if device.disk.is_some() { (16, 30) } else { (20, padding) };
+                    content = content.push(
+                        row![text(&device.name).size(name_size), text(summary).size(14)]
+                            .spacing(10)
+                            .align_items(Alignment::Center)
+                            .width(Length::Fill)
+                            .padding([5, padding, 0, indent]),
+                    );
+                    if device.disk.is_some() && !data.disk_io_partitions {
+                        continue;
+                    }
+
+                    for charts in charts.chunks(items_per_row) {
+                        let mut row = row(vec![])
+                            .spacing(padding)
+                            .padding(padding)
+                            .width(Length::Fill)
+                            .height(Length::Units(height_per_item));
+                        for chart in charts {
+                            row = row.push(container(chart.view())
+                                .width(Length::Units(width_per_item))
+                                .height(Length::Units(height_per_item)));
+                        }
+                        content = content.push(row);
+                    }
+                }
+
+                content.into()
+            }
+            PaneType::Network => {
+                let mut legend = row![].spacing(5).align_items(Alignment::Center);
+                for (name, (r, g, b)) in NETWORK_COLORS {
+                    legend = legend
+                        .push(canvas(ColoredRect { color: Color::from_rgb8(r, g, b) })
+                            .width(Length::Units(20))
+                            .height(Length::Units(20)))
+                        .push(text(name).size(16));
+                }
+                let mut content = column![text("Network").size(24), legend]
+                    .width(Length::Fill)
+                    .spacing(5)
+                    .align_items(Alignment::Center);
+
+                let interfaces = &data.current_data_copy.network;
+                if interfaces.is_empty() {
+                    return content.push(text("No interfaces").size(16)).into();
+                }
+
+                let padding = 10;
+                let min_width = 200.0 + (padding as f32);
+                let width = size.width - (padding as f32);
+                let items_per_row = ((width / min_width).trunc() as usize).clamp(1, NetworkChartKind::ALL.len());
+                let width_per_item = ((width / (items_per_row as f32)) as u16).saturating_sub(padding);
+                let height_per_item = 150;
+
+                for (interface, charts) in interfaces.iter().zip(&data.network_charts) {
+                    let mut link = vec![interface.operstate.clone()];
+                    if let Some(speed) = interface.speed {
+                        link.push(format!("{} Mb/s", speed));
+                    }
+                    if let Some(mtu) = interface.mtu {
+                        link.push(format!("MTU {}", mtu));
+                    }
+                    if !interface.mac.is_empty() {
+                        link.push(interface.mac.clone());
+                    }
+                    let state_color = match interface.operstate.as_str() {
+                        "up" => HEALTH_OK_COLOR,
+                        "down" | "lowerlayerdown" => HEALTH_DOWN_COLOR,
+                        _ => Color::BLACK,
+                    };
+                    let mut details = column![
+                        row![
+                            text(&interface.name).size(20),
+                            text(link.join(", ")).size(16).style(state_color),
+                        ]
+                        .spacing(10)
+                        .align_items(Alignment::Center),
+                    ]
+                    .width(Length::Fill)
+                    .padding([5, padding, 0, padding]);
+                    if !interface.addresses.is_empty() {
+                        details = details.push(text(interface.addresses.join("  ")).size(14));
+                    }
+                    content = content.push(details);
+
+                    for charts in charts.chunks(items_per_row) {
+                        let mut row = row(vec![])
+                            .spacing(padding)
+                            .padding(padding)
+                            .width(Length::Fill)
+                            .height(Length::Units(height_per_item));
+                        for chart in charts {
+                            row = row.push(container(chart.view())
+                                .width(Length::Units(width_per_item))
+                                .height(Length::Units(height_per_item)));
+                        }
+                        content = content.push(row);
+                    }
+                }
+
+                content.into()
+            }
+            PaneType::Sockets => {
+                let sockets = &data.current_data_copy.sockets;
+                let mut content = column![
+                    text("Sockets").size(24),
+                    row![
+                        text_input("Filter: address, state, process...", &data.socket_filter, Message::FilterSockets)
+                            .size(16)
+                            .padding(5),
+                        text(format!("{} / {}", data.socket_rows.len(), sockets.len())).size(16),
+                        text("Who uses port").size(16),
+                        text_input("port", &data.socket_port, Message::LookupPort)
+                            .width(Length::Units(80))
+                            .size(16)
+                            .padding(5),
+                    ]
+                    .spacing(10)
+                    .padding(5)
+                    .align_items(Alignment::Center),
+                ]
+                .width(Length::Fill)
+                .spacing(5)
+                .align_items(Alignment::Center);
+
+                if let Ok(port) = data.socket_port.parse::<u16>() {
+                    let mut users: Vec<(usize, &str)> = sockets
+                        .iter()
+                        .filter(|socket| socket.uses_port(port))
+                        .filter_map(|socket| Some((socket.pid?, socket.process.as_str())))
+                        .collect();
+                    users.sort_unstable();
+                    users.dedup();
+                    let anonymous = sockets.iter().any(|socket| socket.uses_port(port) && socket.pid.is_none());
+                    let summary = match (users.is_empty(), anonymous) {
+                        (true, false) => format!("Nothing on this host uses port {}", port),
+                        (true, true) => format!("Port {} is used by processes we may not look into", port),
+                        (false, _) => {
+                            let users: Vec<String> = users.iter().map(|(pid, name)| format!("{} ({})", name, pid)).collect();
+                            format!("Port {} is used by: {}", port, users.join(", "))
+                        }
+                    };
+                    let mut lookup = row![text(summary).size(16)].spacing(10).align_items(Alignment::Center);
+                    if let [(pid, _)] = users.as_slice() {
+                        lookup = lookup.push(button(text("Show").size(14)).padding([1, 6]).on_press(Message::JumpToTask(*pid)));
+                    }
+                    content = content.push(lookup);
+                }
+
+                let mut header = row![].spacing(5);
+                for column in SocketColumn::ALL {
+                    let arrow = match (data.socket_sort == column, data.socket_sort_rev) {
+                        (true, false) => " ^",
+                        (true, true) => " v",
+                        (false, _) => "",
+                    };
+                    header = header.push(
+                        button(text(format!("{}{}", column.title(), arrow)).size(14))
+                            .width(Length::Units(column.width()))
+                            .padding([2, 4])
+                            .on_press(Message::SortSockets(column)),
+                    );
+                }
+                let mut table = column![header].spacing(2).padding(5);
+                // Busy servers hold thousands; the filter narrows them down.
+                const MAX_ROWS: usize = 500;
+                for &i in data.socket_rows.iter().take(MAX_ROWS) {
+                    let socket = &sockets[i];
+                    let process = match socket.pid {
+                        Some(pid) => format!("{} ({})", socket.process, pid),
+                        None => String::from("-"),
+                    };
+                    let cells = [socket.protocol.to_string(), socket.state.clone(), socket.local.to_string(), socket.remote.to_string(), process];
+                    let mut line = row![].spacing(5).align_items(Alignment::Center);
+                    for (column, cell) in SocketColumn::ALL.iter().zip(cells) {
+                        line = line.push(container(text(cell).size(14)).width(Length::Units(column.width())).padding([0, 4]));
+                    }
+                    if let Some(pid) = socket.pid {
+                        line = line.push(button(text("Show").size(12)).padding([1, 6]).on_press(Message::JumpToTask(pid)));
+                    }
+                    table = table.push(line);
+                }
+                if data.socket_rows.len() > MAX_ROWS {
+                    table = table.push(text(format!("{} more, narrow them down with the filter", data.socket_rows.len() - MAX_ROWS)).size(14));
+                }
+                if sockets.is_empty() {
+                    table = table.push(text("No sockets").size(16));
+                }
+                content.push(table).into()
+            }
             PaneType::Disks => {
                 // let c = canvas(ColoredRect { color: USED_COLOR })
                 //     .width(Length::Units(50))
@@ -1048,11 +1964,12 @@ impl PaneType {
 
                 let min_width = 150.0 + (padding as f32);
                 let width = size.width - (padding as f32);
+                // An empty list would make this 0, which chunks() panics on.
                 let items_per_row = std::cmp::min(
                     std::cmp::max((width / min_width).trunc() as usize, 1usize),
                     data.disk_charts.len()
-                );
-                let width_per_item = (width / (items_per_row as f32)) as u16 - padding;
+                ).max(1);
+                let width_per_item = ((width / (items_per_row as f32)) as u16).saturating_sub(padding);
                 let height_per_item = width_per_item;
                 // let max_width = 450;
 
@@ -1092,8 +2009,26 @@ impl PaneType {
                 .spacing(0)
                 .align_items(Alignment::Start);
 
-                for line in &data.current_data_copy.extra_infos {
-                    info_content = info_content.push(text(line).size(16));
+                let info = &data.current_data_copy.system_info;
+                for section in InfoSection::ALL {
+                    let collapsed = data.collapsed_info_sections.contains(&section);
+                    info_content = info_content.push(
+                        button(text(format!("{} {}", if collapsed { "[+]" } else { "[-]" }, section)).size(18))
+                            .style(theme::Button::Text)
+                            .padding(2)
+                            .on_press(Message::ToggleInfoSection(section))
+                    );
+                    if collapsed {
+                        continue;
+                    }
+
+                    let mut labels = column![].spacing(0).align_items(Alignment::Start);
+                    let mut values = column![].spacing(0).align_items(Alignment::Start);
+                    for (label, value) in section.rows(info, &data.current_data_copy.memory_devices, data.now()) {
+                        labels = labels.push(text(label).size(16));
+                        values = values.push(text(value).size(16));
+                    }
+                    info_content = info_content.push(row![labels, values].spacing(15).padding([0, 0, 5, 20]));
                 }
                 content = content.push(info_content);
 
@@ -1101,12 +2036,73 @@ impl PaneType {
             }
             PaneType::Tasks => {
                 let mut content = column![
-                    text("Tasks").size(24),
+                    row![
+                        text("Tasks").size(24),
+                        checkbox("Tree", data.task_tree, Message::ToggleTaskTree)
+                            .size(16)
+                            .text_size(16),
+                        button(text("Columns").size(16))
+                            .padding([2, 8])
+                            .on_press(Message::ToggleTaskColumnChooser),
+                    ]
+                    .spacing(20)
+                    .align_items(Alignment::Center),
                 ]
                 .width(Length::Fill)
                 .spacing(0)
                 .align_items(Alignment::Center);
 
+                if let (true, Some(tasks_chart)) = (data.task_column_chooser, &data.tasks_chart) {
+                    let mut chooser = column![].spacing(5).padding(5);
+                    for columns in tasks::Column::ALL.chunks(7) {
+                        let mut line = row![].spacing(15);
+                        for &column in columns {
+                            let shown = tasks_chart.columns.contains(&column);
+                            line = line.push(
+                                checkbox(column.title(), shown, move |shown| Message::ToggleTaskColumn(column, shown))
+                                    .size(14)
+                                    .text_size(14),
+                            );
+                        }
+                        chooser = chooser.push(line);
+                    }
+                    content = content.push(chooser);
+                }
+
+                let mut filter_bar = row![
+                    text_input(
+                        "Filter: text, or e.g. cpu > 5 && user == postgres, cmd ~ /java.*-Xmx/",
+                        &data.task_filter,
+                        Message::FilterTasks,
+                    )
+                    .size(16)
+                    .padding(5),
+                ]
+                .spacing(10)
+                .padding(5)
+                .align_items(Alignment::Center);
+                if let Some(tasks_chart) = &data.tasks_chart {
+                    filter_bar = filter_bar.push(text(format!("{} / {}", tasks_chart.matching, tasks_chart.all.len())).size(16));
+                }
+                content = content.push(filter_bar);
+                if let Some(error) = &data.task_filter_error {
+                    let banner_style: fn(&Theme) -> iced::widget::container::Appearance = style::banner;
+                    content = content.push(
+                        container(text(format!("invalid filter: {}", error)).size(14))
+                            .width(Length::Fill)
+                            .padding(5)
+                            .style(banner_style),
+                    );
+                }
+                if let Some(notice) = &data.task_filter_notice {
+                    content = content.push(text(notice).size(14));
+                }
+
+                let host = &hosts[current_host];
+                let selected = data.tasks_chart.as_ref().and_then(|chart| chart.selected);
+                if let (true, Some(pid)) = (host.controllable, selected) {
+                    content = content.push(view_task_actions(&data.task_actions, pid));
+                }
 
                 if let Some(tasks_chart) = &data.tasks_chart {
                     content = content.push(canvas(tasks_chart)
@@ -1155,15 +2151,166 @@ impl PaneType {
                 
                 content.into()
             }
+            PaneType::Process => {
+                let mut content = column![]
+                    .width(Length::Fill)
+                    .spacing(5)
+                    .align_items(Alignment::Start);
+
+                let details = match &data.current_data_copy.process_details {
+                    Some(details) => details,
+                    None => {
+                        let message = if hosts[current_host].connection.is_some() {
+                            "Process details are only available for this machine"
+                        } else {
+                            "Select a task in the Tasks pane"
+                        };
+                        return content.push(text(message).size(16)).into();
+                    }
+                };
+
+                let mut title = row![text(format!("{} ({})", details.comm, details.pid)).size(24)]
+                    .spacing(10)
+                    .align_items(Alignment::Center);
+                if details.exited {
+                    title = title.push(text("exited").size(18).style(HEALTH_DOWN_COLOR));
+                } else {
+                    title = title.push(text(format!("state {}", details.state)).size(18));
+                }
+                content = content.push(title).push(text(&details.cmd).size(16));
+
+                let (cpu, rss) = details.history.front().copied().unwrap_or_default();
+                let oldest_first = details.history.iter().rev();
+                let cpu_history: Vec<f32> = oldest_first.clone().map(|&(cpu, _)| cpu as f32).collect();
+                let rss_history: Vec<f32> = oldest_first.map(|&(_, rss)| rss as f32).collect();
+                let max_rss = rss_history.iter().copied().fold(1.0, f32::max);
+                content = content.push(row![
+                    column![
+                        text(format!("CPU {:.1}%", cpu)).size(16),
+                        canvas(Sparkline { values: cpu_history, max: 100.0 })
+                            .width(Length::Fill)
+                            .height(Length::Units(40)),
+                    ]
+                    .width(Length::Fill),
+                    column![
+                        text(format!("RSS {}", format_bytes(rss))).size(16),
+                        canvas(Sparkline { values: rss_history, max: max_rss })
+                            .width(Length::Fill)
+                            .height(Length::Units(40)),
+                    ]
+                    .width(Length::Fill),
+                ]
+                .spacing(10));
+
+                let section = |content: Column<'a, Message>, title: &str, lines: Vec<String>| {
+                    let lines = lines.into_iter().map(|line| text(line).size(14).into()).collect();
+                    content
+                        .push(text(title).size(18))
+                        .push(column(lines).padding([0, 0, 5, 20]))
+                };
+                let or_error = |result: &Result<Vec<String>, String>| {
+                    result.clone().unwrap_or_else(|error| vec![format!("unavailable: {}", error)])
+                };
+                let text_or_error = |result: &Result<String, String>| {
+                    result.clone().unwrap_or_else(|error| format!("unavailable: {}", error))
+                };
+
+                content = section(content, "Parents", details.parents.iter().map(|(pid, comm)| format!("{} ({})", comm, pid)).collect());
+                content = section(content, "Files", vec![
+                    format!("cwd  {}", text_or_error(&details.cwd)),
+                    format!("exe  {}", text_or_error(&details.exe)),
+                ]);
+                content = section(content, &format!("Threads ({})", details.threads.len()), details
+                    .threads
+                    .iter()
+                    .map(|thread| format!("{}  {}  {}", thread.tid, thread.state, thread.comm))
+                    .collect());
+                let fds = details.fds.as_ref().map(|fds| fds.iter().map(|(fd, target)| format!("{}  {}", fd, target)).collect()).map_err(Clone::clone);
+                content = section(content, "Open files", or_error(&fds));
+                content = section(content, "Limits (soft / hard)", details
+                    .limits
+                    .iter()
+                    .map(|limit| format!("{}: {} / {} {}", limit.name, limit.soft, limit.hard, limit.units))
+                    .collect());
+                content = section(content, "Cgroup", details.cgroup.clone());
+                let namespaces = details
+                    .namespaces
+                    .as_ref()
+                    .map(|namespaces| namespaces.iter().map(|(name, target)| format!("{}  {}", name, target)).collect())
+                    .map_err(Clone::clone);
+                content = section(content, "Namespaces", or_error(&namespaces));
+                content = section(content, "Environment", or_error(&details.environ));
+
+                content.into()
+            }
+            PaneType::Overview => {
+                let mut content = column![
+                    text("Hosts").size(24),
+                ]
+                .width(Length::Fill)
+                .spacing(0)
+                .align_items(Alignment::Center);
+
+                let padding = 10;
+
+                let min_width = 200.0 + (padding as f32);
+                let width = size.width - (padding as f32);
+                // An empty list would make this 0, which chunks() panics on.
+                let items_per_row = std::cmp::min(
+                    std::cmp::max((width / min_width).trunc() as usize, 1usize),
+                    hosts.len()
+                ).max(1);
+                let width_per_item = ((width / (items_per_row as f32)) as u16).saturating_sub(padding);
+
+                let mut index = 0;
+                for chunk in hosts.chunks(items_per_row) {
+                    let mut row = row(vec![])
+                        .spacing(padding)
+                        .padding(padding)
+                        .width(Length::Fill);
+
+                    for host in chunk {
+                        row = row.push(view_host_tile(host, index, index == current_host, width_per_item));
+                        index += 1;
+                    }
+                    content = content.push(row);
+                }
+
+                content.into()
+            }
+        }
+    }
+    /// Collectors feeding this pane; the others are paused while it is shown.
+    fn collectors(&self) -> &'static [&'static str] {
+        match *self {
+            PaneType::Selection => &[],
+            PaneType::Cpu => &["cpu"],
+            PaneType::Memory => &["memory"],
+            PaneType::Disks => &["disks"],
+            PaneType::Info => &[],
+            PaneType::Tasks => &["tasks"],
+            PaneType::Overview => &["cpu", "memory", "disks"],
+            PaneType::Process => &["process"],
+            PaneType::Network => &["network"],
+            PaneType::Sockets => &["sockets"],
+            PaneType::DiskIo => &["diskio"],
+            PaneType::Sensors => &["sensors"],
         }
     }
-    const ALL: [PaneType; 6] = [
+
+    const ALL: [PaneType; 12] = [
         PaneType::Selection,
         PaneType::Cpu,
         PaneType::Memory,
         PaneType::Disks,
         PaneType::Info,
         PaneType::Tasks,
+        PaneType::Overview,
+        PaneType::Process,
+        PaneType::Network,
+        PaneType::Sockets,
+        PaneType::DiskIo,
+        PaneType::Sensors,
     ];
 }
 
@@ -1188,10 +2335,477 @@ impl Display for PaneType {
             PaneType::Tasks => {
                 write!(f, "Tasks")
             }
+            PaneType::Overview => {
+                write!(f, "Hosts")
+            }
+            PaneType::Process => {
+                write!(f, "Process")
+            }
+            PaneType::Network => {
+                write!(f, "Network")
+            }
+            PaneType::Sockets => {
+                write!(f, "Sockets")
+            }
+            PaneType::DiskIo => {
+                write!(f, "Disk I/O")
+            }
+            PaneType::Sensors => {
+                write!(f, "Sensors")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InfoSection {
+    System,
+    Processor,
+    Memory,
+    Virtualization,
+    Devices,
+}
+
+impl InfoSection {
+    const ALL: [InfoSection; 5] = [
+        InfoSection::System,
+        InfoSection::Processor,
+        InfoSection::Memory,
+        InfoSection::Virtualization,
+        InfoSection::Devices,
+    ];
+
+    fn rows(&self, info: &SystemInfo, memory_devices: &[MemoryDevice], now: SystemTime) -> Vec<(String, String)> {
+        let row = |label: &str, value: String| (label.to_string(), value);
+        match self {
+            InfoSection::System => vec![
+                row("Hostname", info.hostname.clone()),
+                row("Distribution", info.distribution.clone()),
+                row("Kernel", info.kernel.clone()),
+                row("Build", info.kernel_version.clone()),
+                row("Architecture", info.architecture.clone()),
+                row("Uptime", format_duration(info.uptime(now))),
+                row("Boot time", format_timestamp(info.boot_time)),
+            ],
+            InfoSection::Processor => vec![
+                row("Model", info.cpu_model.clone()),
+                row("Sockets", info.topology.sockets.to_string()),
+                row("Cores", info.topology.cores.to_string()),
+                row("Threads", info.topology.threads.to_string()),
+            ],
+            InfoSection::Memory => {
+                let installed = memory_devices.iter().filter(|d| d.size.is_some()).count();
+                vec![
+                    row("Total", format_bytes(info.total_memory)),
+                    row("Technology", collectors::dmi::memory_technology(memory_devices)),
+                    row("Modules", if memory_devices.is_empty() {
+                        String::from("unknown")
+                    } else {
+                        format!("{} of {} slots", installed, memory_devices.len())
+                    }),
+                ]
+            }
+            InfoSection::Virtualization => vec![
+                row("Hypervisor", info.virtualization.clone().unwrap_or_else(|| String::from("none"))),
+                row("Container", info.container.clone().unwrap_or_else(|| String::from("none"))),
+            ],
+            InfoSection::Devices => info
+                .devices
+                .iter()
+                .map(|device| match device.size {
+                    Some(size) => row(&device.class, format!("{} ({})", device.description, format_bytes(size))),
+                    None => row(&device.class, device.description.clone()),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Display for InfoSection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            InfoSection::System => write!(f, "System"),
+            InfoSection::Processor => write!(f, "Processor"),
+            InfoSection::Memory => write!(f, "Memory"),
+            InfoSection::Virtualization => write!(f, "Virtualization"),
+            InfoSection::Devices => write!(f, "Devices"),
+        }
+    }
+}
+
+/// What the Sockets pane is sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum SocketColumn {
+    Protocol,
+    State,
+    #[default]
+    Local,
+    Remote,
+    Process,
+}
+
+impl SocketColumn {
+    const ALL: [SocketColumn; 5] = [
+        SocketColumn::Protocol,
+        SocketColumn::State,
+        SocketColumn::Local,
+        SocketColumn::Remote,
+        SocketColumn::Process,
+    ];
+
+    fn title(self) -> &'static str {
+        match self {
+            SocketColumn::Protocol => "Proto",
+            SocketColumn::State => "State",
+            SocketColumn::Local => "Local address",
+            SocketColumn::Remote => "Remote address",
+            SocketColumn::Process => "Process",
+        }
+    }
+
+    fn width(self) -> u16 {
+        match self {
+            SocketColumn::Protocol => 60,
+            SocketColumn::State => 110,
+            SocketColumn::Local | SocketColumn::Remote => 260,
+            SocketColumn::Process => 200,
+        }
+    }
+
+    /// Addresses by IP then port, sockets nobody is known to hold last.
+    fn compare(self, a: &SocketInfo, b: &SocketInfo) -> std::cmp::Ordering {
+        match self {
+            SocketColumn::Protocol => a.protocol.cmp(&b.protocol),
+            SocketColumn::State => a.state.cmp(&b.state),
+            SocketColumn::Local => a.local.cmp(&b.local),
+            SocketColumn::Remote => a.remote.cmp(&b.remote),
+            SocketColumn::Process => (a.pid.is_none(), &a.process, a.pid).cmp(&(b.pid.is_none(), &b.process, b.pid)),
+        }
+        .then_with(|| a.local.cmp(&b.local))
+    }
+}
+
+/// Play/pause, speed and seek controls shown under the panes when replaying.
+fn view_playback<'a>(player: &recording::Player) -> Element<'a, Message> {
+    let seconds = player.time().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs();
+    let speed = PlaybackSpeed::ALL
+        .iter()
+        .copied()
+        .find(|speed| speed.factor() == player.speed());
+    row![
+        button(if player.is_playing() { "Pause" } else { "Play" })
+            .on_press(Message::TogglePlayback),
+        pick_list(&PlaybackSpeed::ALL[..], speed, Message::SetPlaybackSpeed),
+        slider(
+            0..=(player.len() - 1) as u32,
+            player.position() as u32,
+            Message::SeekPlayback,
+        )
+        .width(Length::Fill),
+        text(format!(
+            "{}  ({}/{})",
+            format_timestamp(seconds),
+            player.position() + 1,
+            player.len()
+        )),
+    ]
+    .spacing(10)
+    .align_items(Alignment::Center)
+    .into()
+}
+
+/// Signal and renice controls for the selected task, or the confirmation of
+/// the action picked.
+fn view_task_actions(actions: &TaskActions, pid: usize) -> Element<'_, Message> {
+    use control::{ProcessAction, Signal};
+
+    let mut content = column![].spacing(5).padding(5).width(Length::Fill);
+    if let Some(error) = &actions.error {
+        let banner_style: fn(&Theme) -> iced::widget::container::Appearance = style::banner;
+        content = content.push(
+            container(
+                row![
+                    text(error).size(14).width(Length::Fill),
+                    button(text("Dismiss").size(14)).padding(3).on_press(Message::DismissProcessError),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+            )
+            .width(Length::Fill)
+            .padding(5)
+            .style(banner_style),
+        );
+    }
+
+    let controls = match actions.pending {
+        Some((pending_pid, _, action)) => row![
+            text(format!("{} PID {}?", action.describe(), pending_pid)).size(16),
+            button(text("Confirm").size(14))
+                .style(theme::Button::Destructive)
+                .padding(3)
+                .on_press(Message::ConfirmProcessAction),
+            button(text("Cancel").size(14))
+                .style(theme::Button::Secondary)
+                .padding(3)
+                .on_press(Message::CancelProcessAction),
+        ],
+        None => {
+            let mut controls = row![text(format!("PID {}", pid)).size(16)];
+            for signal in Signal::COMMON {
+                controls = controls.push(
+                    button(text(signal.name().unwrap_or_default()).size(14))
+                        .padding(3)
+                        .on_press(Message::RequestProcessAction(ProcessAction::Signal(signal))),
+                );
+            }
+            let mut send = button(text("Send").size(14)).padding(3);
+            if let Some(signal) = actions.custom_signal {
+                send = send.on_press(Message::RequestProcessAction(ProcessAction::Signal(signal)));
+            }
+            let mut renice = button(text("Renice").size(14)).padding(3);
+            if let Some(nice) = actions.nice {
+                renice = renice.on_press(Message::RequestProcessAction(ProcessAction::Renice(nice)));
+            }
+            controls
+                .push(pick_list(&Signal::ALL[..], actions.custom_signal, Message::SetCustomSignal).text_size(14))
+                .push(send)
+                .push(pick_list((-20..=19).collect::<Vec<i32>>(), actions.nice, Message::SetNice).text_size(14))
+                .push(renice)
+        }
+    };
+    content.push(controls.spacing(5).align_items(Alignment::Center)).into()
+}
+
+/// A clickable summary of one host for the overview pane.
+fn view_host_tile(host: &Host, index: usize, selected: bool, width: u16) -> Element<'_, Message> {
+    let data = &host.local_data.current_data_copy;
+    let name = if data.system_info.hostname.is_empty() {
+        host.name().to_string()
+    } else {
+        data.system_info.hostname.clone()
+    };
+    let mut header = row![
+        canvas(ColoredRect { color: host.health_color() })
+            .width(Length::Units(14))
+            .height(Length::Units(14)),
+        text(name).size(18),
+    ]
+    .spacing(5)
+    .align_items(Alignment::Center);
+    if host.connection.is_some() {
+        header = header.push(text(host.name()).size(14));
+    }
+
+    // Average of all cores for each point of the history.
+    let cores = data.cpu_usage.len().max(1) as f32;
+    let points = data.cpu_usage.iter().map(VecDeque::len).max().unwrap_or(0);
+    let history: Vec<f32> = (0..points)
+        .rev()
+        .map(|i| data.cpu_usage.iter().filter_map(|core| core.get(i)).map(|sample| sample.total as f32).sum::<f32>() / cores)
+        .collect();
+    let cpu = history.last().map_or_else(|| String::from("CPU -"), |total| format!("CPU {:.0}%", total));
+
+    let memory = if data.memory.total == 0 {
+        String::from("Memory -")
+    } else {
+        format!("Memory {:.0}%", data.memory.used() as f64 * 100.0 / data.memory.total as f64)
+    };
+    let fullest = data
+        .partitions
+        .iter()
+        .max_by(|a, b| a.used_percent().total_cmp(&b.used_percent()))
+        .map_or_else(|| String::from("Disk -"), |partition| {
+            format!("Disk {:.0}% {}", partition.used_percent(), partition.mount_point)
+        });
+
+    let content = column![
+        header,
+        canvas(Sparkline { values: history, max: 100.0 })
+            .width(Length::Fill)
+            .height(Length::Units(40)),
+        text(cpu).size(16),
+        text(memory).size(16),
+        text(fullest).size(16),
+    ]
+    .spacing(5);
+
+    button(content)
+        .width(Length::Units(width))
+        .padding(8)
+        .style(if selected { theme::Button::Secondary } else { theme::Button::Text })
+        .on_press(Message::SelectHost(index))
+        .into()
+}
+
+/// A bare line chart of the last values, oldest first.
+struct Sparkline {
+    values: Vec<f32>,
+    max: f32,
+}
+
+impl canvas::Program<Message> for Sparkline {
+    type State = ();
+
+    fn draw(&self, _state: &(), _theme: &Theme, bounds: Rectangle, _cursor: Cursor) -> Vec<Geometry> {
+        let mut frame = Frame::new(bounds.size());
+        if self.values.len() > 1 {
+            let step = bounds.width / (MAX_POINTS - 1) as f32;
+            let offset = (MAX_POINTS - self.values.len()) as f32 * step;
+            let line = Path::new(|builder| {
+                for (i, value) in self.values.iter().enumerate() {
+                    let point = Point::new(
+                        offset + i as f32 * step,
+                        bounds.height * (1.0 - (value / self.max).clamp(0.0, 1.0)),
+                    );
+                    if i == 0 {
+                        builder.move_to(point);
+                    } else {
+                        builder.line_to(point);
+                    }
+                }
+            });
+            frame.stroke(&line, Stroke::default().with_color(FREE_COLOR).with_width(2.0));
+        }
+        vec![frame.into_geometry()]
+    }
+}
+
+/// Host picker and the state of the connection to the shown agent.
+fn view_hosts<'a>(hosts: &[Host], current: usize) -> Element<'a, Message> {
+    let choices: Vec<HostChoice> = hosts
+        .iter()
+        .enumerate()
+        .map(|(index, host)| HostChoice { index, name: host.name().to_string() })
+        .collect();
+    let selected = choices[current].clone();
+    let state = hosts[current].connection.as_ref().map(|connection| connection.state());
+    let mut status = container(text(state.as_ref().map(ToString::to_string).unwrap_or_default())).padding(5);
+    if state.is_some_and(|state| !state.is_connected()) {
+        let banner_style: fn(&Theme) -> iced::widget::container::Appearance = style::banner;
+        status = status.style(banner_style);
+    }
+    row![
+        pick_list(choices, Some(selected), |choice| Message::SelectHost(choice.index)),
+        status,
+    ]
+    .spacing(10)
+    .align_items(Alignment::Center)
+    .into()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct HostChoice {
+    index: usize,
+    name: String,
+}
+
+impl Display for HostChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+/// Replay speed, in percent of real time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PlaybackSpeed(u32);
+
+impl PlaybackSpeed {
+    const ALL: [PlaybackSpeed; 7] = [
+        PlaybackSpeed(25),
+        PlaybackSpeed(50),
+        PlaybackSpeed(100),
+        PlaybackSpeed(200),
+        PlaybackSpeed(500),
+        PlaybackSpeed(1000),
+        PlaybackSpeed(6000),
+    ];
+
+    fn factor(self) -> f64 {
+        self.0 as f64 / 100.0
+    }
+}
+
+impl Display for PlaybackSpeed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}x", self.factor())
+    }
+}
+
+/// A collector interval offered in the pane title bar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Interval(Duration);
+
+impl Interval {
+    const ALL: [Interval; 6] = [
+        Interval(Duration::from_millis(500)),
+        Interval(Duration::from_secs(1)),
+        Interval(Duration::from_secs(2)),
+        Interval(Duration::from_secs(5)),
+        Interval(Duration::from_secs(10)),
+        Interval(Duration::from_secs(30)),
+    ];
+}
+
+impl Display for Interval {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.0 < Duration::from_secs(1) {
+            write!(f, "every {}ms", self.0.as_millis())
+        } else {
+            write!(f, "every {}s", self.0.as_secs())
         }
     }
 }
 
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (days, hours, minutes) = (secs / 86400, (secs % 86400) / 3600, (secs % 3600) / 60);
+    if days > 0 {
+        format!("{}d {}h {}m", days, hours, minutes)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m {}s", minutes, secs % 60)
+    }
+}
+
+/// Formats seconds since the Unix epoch as a UTC date and time.
+fn format_timestamp(secs: u64) -> String {
+    // Days to civil date, from Howard Hinnant's date algorithms.
+    let days = (secs / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        (secs % 86400) / 3600,
+        (secs % 3600) / 60,
+        secs % 60,
+    )
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "K", "M", "G", "T"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{}{}", bytes, UNITS[0])
+    } else {
+        format!("{:.2}{}", value, UNITS[unit])
+    }
+}
+
 const PANE_ID_COLOR_UNFOCUSED: Color = Color::from_rgb(
     0xFF as f32 / 255.0,
     0xC7 as f32 / 255.0,
@@ -1209,8 +2823,10 @@ fn view_content<>(
     _is_pinned: bool,
     size: Size,
     pane_type: PaneType,
-    local_data: &'_ LocalData,
+    hosts: &'_ [Host],
+    current_host: usize,
 ) -> Element<'_, Message> {
+    let local_data = &hosts[current_host].local_data;
     // let button = |label, message| {
     //     button(
     //         text(label)
@@ -1258,8 +2874,8 @@ fn view_content<>(
     //     .padding(5)
     //     .center_y()
     //     .into()
-    if pane_type == PaneType::Tasks {
-        container(pane_type.content(local_data,size))
+    let content: Element<_> = if pane_type == PaneType::Tasks {
+        container(pane_type.content(hosts, current_host, size))
             .width(Length::Fill)
             .height(Length::Fill)
             .padding(5)
@@ -1267,15 +2883,67 @@ fn view_content<>(
             .center_y()
             .into()
     } else {
-        container(scrollable(pane_type.content(local_data, size)))
+        container(scrollable(pane_type.content(hosts, current_host, size)))
             .width(Length::Fill)
             .height(Length::Fill)
             .padding(5)
             .center_x()
             .center_y()
             .into()
+    };
+
+    match health_banner(pane_type, &local_data.current_data_copy.health, local_data.now()) {
+        Some(banner) => column![banner, content].into(),
+        None => content,
     }
-    
+}
+
+/// A warning shown above a pane whose collectors are failing or whose data
+/// hasn't been refreshed in a while.
+fn health_banner<'a>(
+    pane_type: PaneType,
+    health: &HashMap<String, CollectorHealth>,
+    now: SystemTime,
+) -> Option<Element<'a, Message>> {
+    let lines: Vec<String> = pane_type
+        .collectors()
+        .iter()
+        .filter_map(|name| {
+            let health = health.get(*name)?;
+            let age = health
+                .last_success
+                .map(|at| format!("last update {} ago", format_duration(now.duration_since(at).unwrap_or_default())))
+                .unwrap_or_else(|| String::from("no data yet"));
+            match &health.last_error {
+                Some(error) => Some(format!(
+                    "{} failing ({} in a row, {}): {}",
+                    name, health.consecutive_failures, age, error
+                )),
+                None if health.is_stale(now) => Some(format!("{} stale ({})", name, age)),
+                None => None,
+            }
+        })
+        .chain(
+            // Sinks, e.g. a recording, belong to no pane, so every banner says
+            // when one stopped.
+            health
+                .iter()
+                .filter(|(name, _)| !PaneType::ALL.iter().any(|pane_type| pane_type.collectors().contains(&name.as_str())))
+                .filter_map(|(name, health)| Some(format!("{} stopped: {}", name, health.last_error.as_ref()?))),
+        )
+        .collect();
+    if lines.is_empty() {
+        return None;
+    }
+
+    let banner_style: fn(&Theme) -> iced::widget::container::Appearance = style::banner;
+    Some(
+        container(column(lines.into_iter().map(|line| text(line).size(14).into()).collect()))
+            .width(Length::Fill)
+            .padding(5)
+            .style(banner_style)
+            .into(),
+    )
 }
 
 fn view_controls<'a>(
@@ -1284,6 +2952,7 @@ fn view_controls<'a>(
     is_pinned: bool,
     is_maximized: bool,
     pane_type: PaneType,
+    collector: Option<(&'static str, Duration)>,
 ) -> Element<'a, Message> {
     let mut row = row![].spacing(5);
 
@@ -1295,6 +2964,14 @@ fn view_controls<'a>(
 
     row = row.push(pick_list);
 
+    if let Some((name, interval)) = collector {
+        row = row.push(iced::widget::pick_list(
+            &Interval::ALL[..],
+            Some(Interval(interval)),
+            move |Interval(interval)| Message::SetCollectorInterval(name, interval),
+        ));
+    }
+
     if total_panes > 1 {
         let toggle = {
             let (content, message) = if is_maximized {
@@ -1326,142 +3003,673 @@ fn view_controls<'a>(
     if total_panes > 1 && !is_pinned {
         close = close.on_press(Message::Close(pane));
     }
-
-    row.push(close).into()
+
+    row.push(close).into()
+}
+
+
+// Stacking order matches `CpuSample::parts`.
+const CPU_BREAKDOWN_COLORS: [(&str, (u8, u8, u8)); 8] = [
+    ("user", (0, 175, 255)),
+    ("nice", (120, 210, 120)),
+    ("system", (255, 120, 120)),
+    ("irq", (200, 150, 255)),
+    ("softirq", (160, 110, 220)),
+    ("guest", (120, 200, 200)),
+    ("iowait", (255, 200, 80)),
+    ("steal", (90, 90, 90)),
+];
+
+/// The clock line over a core's usage.
+const CPU_FREQUENCY_COLOR: (u8, u8, u8) = (230, 90, 160);
+
+/// `2.40 GHz`, or `800 MHz` below a gigahertz.
+fn format_frequency(mhz: f64) -> String {
+    if mhz >= 1000.0 {
+        format!("{:.2} GHz", mhz / 1000.0)
+    } else {
+        format!("{:.0} MHz", mhz)
+    }
+}
+
+struct CpuUsageChart {
+    cache: Cache,
+    data_points: VecDeque<CpuSample>,
+    max_points: usize,
+    breakdown: bool,
+    policy: Option<CpuFrequencyPolicy>,
+    /// Draw the clock history on a second axis.
+    frequency: bool,
+}
+
+impl CpuUsageChart {
+    fn new(max_points: usize) -> Self {
+        Self {
+            cache: Cache::new(),
+            data_points: VecDeque::new(),
+            max_points,
+            breakdown: false,
+            policy: None,
+            frequency: false,
+        }
+    }
+
+    fn set_frequency(&mut self, policy: Option<CpuFrequencyPolicy>, show: bool) {
+        self.policy = policy;
+        self.frequency = show;
+        self.cache.clear();
+    }
+
+    fn has_frequency(&self) -> bool {
+        self.data_points.front().is_some_and(|sample| sample.frequency.is_some())
+    }
+
+    /// Current clock, the governor's range and the governor.
+    fn badge(&self) -> Option<String> {
+        let current = self.data_points.front()?.frequency?;
+        Some(match &self.policy {
+            Some(policy) => format!(
+                "{} ({}-{}) {}",
+                format_frequency(current),
+                format_frequency(policy.min),
+                format_frequency(policy.max),
+                policy.governor
+            ),
+            None => format_frequency(current),
+        })
+    }
+
+    fn set_data(&mut self, value: impl Iterator<Item = CpuSample>, breakdown: bool) {
+        self.data_points = value.collect();
+        self.breakdown = breakdown;
+
+        while self.data_points.len() > self.max_points {
+            self.data_points.pop_back();
+        }
+
+        self.cache.clear();
+    }
+
+    fn view(&self, idx: usize) -> Element<'_, Message> {
+        let mut content = column(Vec::new())
+            .width(Length::Fill)
+            .height(Length::Shrink)
+            .spacing(0)
+            .padding(0)
+            .push(text(format!("Core {}", self.data_points.front().map_or(idx, |sample| sample.id))));
+        if let Some(badge) = self.badge() {
+            content = content.push(text(badge).size(12));
+        }
+        container(
+            content
+                .push(
+                    ChartWidget::new(self).height(Length::Fill),
+                )
+                .align_items(Alignment::Center),
+        )
+        .width(Length::Fill)
+        .height(Length::Shrink)
+        .align_x(alignment::Horizontal::Center)
+        .align_y(alignment::Vertical::Center)
+        .into()
+    }
+}
+
+impl Chart<Message> for CpuUsageChart {
+    type State = ();
+    // fn update(
+    //     &mut self,
+    //     event: Event,
+    //     bounds: Rectangle,
+    //     cursor: Cursor,
+    // ) -> (event::Status, Option<Message>) {
+    //     self.cache.clear();
+    //     (event::Status::Ignored, None)
+    // }
+
+    #[inline]
+    fn draw<F: Fn(&mut Frame)>(&self, bounds: Size, draw_fn: F) -> Geometry {
+        self.cache.draw(bounds, draw_fn)
+    }
+
+    fn build_chart<DB: DrawingBackend>(&self, _state: &Self::State, mut chart: ChartBuilder<DB>) {
+        use plotters::{prelude::*, style::Color};
+
+        const PLOT_LINE_COLOR: RGBColor = RGBColor(0, 175, 255);
+        let end = self.max_points;
+        let frequency = self.frequency && self.has_frequency();
+        // Up to the governor's ceiling, so a throttled core sits visibly below it.
+        let max_frequency = self
+            .data_points
+            .iter()
+            .filter_map(|sample| sample.frequency)
+            .chain(self.policy.as_ref().map(|policy| policy.max))
+            .fold(1.0, f64::max)
+            * 1.05;
+
+        let mut chart = chart
+            .x_label_area_size(0)
+            .y_label_area_size(24)
+            .right_y_label_area_size(if frequency { 24 } else { 0 })
+            .margin(5)
+            .build_cartesian_2d(1..self.max_points, 0f64..100.0)
+            .expect("failed to build chart")
+            .set_secondary_coord(1..self.max_points, 0f64..max_frequency);
+
+        chart
+            .configure_mesh()
+            .bold_line_style(plotters::style::colors::BLUE.mix(0.1))
+            .light_line_style(plotters::style::colors::BLUE.mix(0.05))
+            .axis_style(ShapeStyle::from(plotters::style::colors::BLUE.mix(0.45)).stroke_width(1))
+            .y_labels(10)
+            .y_label_style(
+                ("sans-serif", 12)
+                    .into_font()
+                    .color(&plotters::style::colors::BLUE.mix(0.65))
+                    .transform(FontTransform::Rotate90),
+            )
+            .y_label_formatter(&|y| format!("{}%", y))
+            .draw()
+            .expect("failed to draw chart mesh");
+
+        if self.breakdown {
+            // Each band is drawn as the area under the running sum of the parts
+            // below it, topmost first, so the smaller sums paint over it.
+            for band in (0..CPU_BREAKDOWN_COLORS.len()).rev() {
+                let (_, (r, g, b)) = CPU_BREAKDOWN_COLORS[band];
+                chart
+                    .draw_series(
+                        AreaSeries::new(
+                            self.data_points.iter().enumerate().map(|(x, sample)| {
+                                let stacked = sample.parts()[..=band].iter().map(|(_, v)| v).sum::<f64>();
+                                (end - x, stacked)
+                            }),
+                            0.0,
+                            RGBColor(r, g, b).filled(),
+                        ),
+                    )
+                    .expect("failed to draw chart data");
+            }
+        }
+
+        chart
+            .draw_series(
+                AreaSeries::new(
+                    self.data_points.iter().enumerate().map(|(x, sample)| (end - x, sample.total)),
+                    0.0,
+                    PLOT_LINE_COLOR.mix(if self.breakdown { 0.0 } else { 0.175 }),
+                )
+                .border_style(ShapeStyle::from(PLOT_LINE_COLOR).stroke_width(2)),
+            )
+            .expect("failed to draw chart data");
+
+        if frequency {
+            let (r, g, b) = CPU_FREQUENCY_COLOR;
+            chart
+                .configure_secondary_axes()
+                .axis_style(ShapeStyle::from(plotters::style::colors::BLUE.mix(0.45)).stroke_width(1))
+                .y_labels(5)
+                .label_style(
+                    ("sans-serif", 12)
+                        .into_font()
+                        .color(&RGBColor(r, g, b))
+                        .transform(FontTransform::Rotate90),
+                )
+                .y_label_formatter(&|mhz| format!("{:.1}G", mhz / 1000.0))
+                .draw()
+                .expect("failed to draw chart axis");
+            chart
+                .draw_secondary_series(LineSeries::new(
+                    self.data_points
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(x, sample)| Some((end - x, sample.frequency?))),
+                    ShapeStyle::from(RGBColor(r, g, b)).stroke_width(2),
+                ))
+                .expect("failed to draw chart data");
+        }
+    }
+}
+
+
+/// The plot of the Network, Disk I/O and Sensors charts: the last samples,
+/// oldest at 1 and newest at the number of points shown.
+type HistoryPlot<'a, DB> = plotters::chart::ChartContext<
+    'a,
+    DB,
+    plotters::coord::cartesian::Cartesian2d<plotters::coord::types::RangedCoordusize, plotters::coord::types::RangedCoordf64>,
+>;
+
+/// Lays out a history plot of `points` samples over `y` and draws its mesh.
+fn history_plot<'a, DB: DrawingBackend>(
+    mut chart: ChartBuilder<'a, '_, DB>,
+    points: usize,
+    y: std::ops::Range<f64>,
+    y_format: &dyn Fn(&f64) -> String,
+) -> HistoryPlot<'a, DB> {
+    use plotters::{prelude::*, style::Color};
+
+    let mut plot = chart
+        .x_label_area_size(0)
+        .y_label_area_size(24)
+        .margin(5)
+        .build_cartesian_2d(1..points, y)
+        .expect("failed to build chart");
+
+    plot.configure_mesh()
+        .bold_line_style(plotters::style::colors::BLUE.mix(0.1))
+        .light_line_style(plotters::style::colors::BLUE.mix(0.05))
+        .axis_style(ShapeStyle::from(plotters::style::colors::BLUE.mix(0.45)).stroke_width(1))
+        .y_labels(5)
+        .y_label_style(
+            ("sans-serif", 12)
+                .into_font()
+                .color(&plotters::style::colors::BLUE.mix(0.65))
+                .transform(FontTransform::Rotate90),
+        )
+        .y_label_formatter(y_format)
+        .draw()
+        .expect("failed to draw chart mesh");
+    plot
+}
+
+/// Draws a history, newest first, as a line of `color` across the plot.
+fn draw_history<DB: DrawingBackend>(
+    plot: &mut HistoryPlot<'_, DB>,
+    points: usize,
+    history: impl Iterator<Item = f64>,
+    (r, g, b): (u8, u8, u8),
+) {
+    use plotters::prelude::*;
+
+    plot.draw_series(LineSeries::new(
+        history.take(points).enumerate().map(|(x, value)| (points - x, value)),
+        ShapeStyle::from(RGBColor(r, g, b)).stroke_width(2),
+    ))
+    .expect("failed to draw chart data");
+}
+
+/// Legend of the Network pane's charts, received then transmitted.
+const NETWORK_COLORS: [(&str, (u8, u8, u8)); 2] = [("Receive", (0, 175, 255)), ("Transmit", (255, 140, 0))];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NetworkChartKind {
+    Throughput,
+    Packets,
+    Errors,
+    Drops,
+}
+
+impl NetworkChartKind {
+    const ALL: [NetworkChartKind; 4] = [
+        NetworkChartKind::Throughput,
+        NetworkChartKind::Packets,
+        NetworkChartKind::Errors,
+        NetworkChartKind::Drops,
+    ];
+
+    /// Received and transmitted per second.
+    fn values(self, rates: &NetworkRates) -> (f64, f64) {
+        match self {
+            NetworkChartKind::Throughput => (rates.rx_bytes, rates.tx_bytes),
+            NetworkChartKind::Packets => (rates.rx_packets, rates.tx_packets),
+            NetworkChartKind::Errors => (rates.rx_errors, rates.tx_errors),
+            NetworkChartKind::Drops => (rates.rx_drops, rates.tx_drops),
+        }
+    }
+
+    fn format(self, value: f64) -> String {
+        match self {
+            NetworkChartKind::Throughput => format!("{}/s", format_bytes(value as u64)),
+            _ => format!("{:.1}/s", value),
+        }
+    }
+}
+
+impl Display for NetworkChartKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            NetworkChartKind::Throughput => write!(f, "Throughput"),
+            NetworkChartKind::Packets => write!(f, "Packets"),
+            NetworkChartKind::Errors => write!(f, "Errors"),
+            NetworkChartKind::Drops => write!(f, "Drops"),
+        }
+    }
+}
+
+const DISK_IO_COLORS: [(&str, (u8, u8, u8)); 2] = [("Read", (0, 175, 255)), ("Write", (255, 140, 0))];
+/// For the charts with a single series.
+const DISK_IO_BUSY_COLOR: (u8, u8, u8) = (150, 90, 200);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DiskIoChartKind {
+    Throughput,
+    Iops,
+    Await,
+    Queue,
+    Utilization,
+}
+
+impl DiskIoChartKind {
+    const ALL: [DiskIoChartKind; 5] = [
+        DiskIoChartKind::Throughput,
+        DiskIoChartKind::Iops,
+        DiskIoChartKind::Await,
+        DiskIoChartKind::Queue,
+        DiskIoChartKind::Utilization,
+    ];
+
+    /// Whether reads and writes are counted apart, so `values` has both.
+    fn has_pair(self) -> bool {
+        matches!(self, DiskIoChartKind::Throughput | DiskIoChartKind::Iops)
+    }
+
+    /// Reads and writes, or a single value for the whole device.
+    fn values(self, rates: &DiskIoRates) -> (f64, Option<f64>) {
+        match self {
+            DiskIoChartKind::Throughput => (rates.read_bytes, Some(rates.write_bytes)),
+            DiskIoChartKind::Iops => (rates.reads, Some(rates.writes)),
+            DiskIoChartKind::Await => (rates.await_ms, None),
+            DiskIoChartKind::Queue => (rates.queue, None),
+            DiskIoChartKind::Utilization => (rates.utilization, None),
+        }
+    }
+
+    fn format(self, value: f64) -> String {
+        match self {
+            DiskIoChartKind::Throughput => format!("{}/s", format_bytes(value as u64)),
+            DiskIoChartKind::Iops => format!("{:.1}/s", value),
+            DiskIoChartKind::Await => format!("{:.1} ms", value),
+            DiskIoChartKind::Queue => format!("{:.2}", value),
+            DiskIoChartKind::Utilization => format!("{:.0}%", value),
+        }
+    }
+}
+
+impl Display for DiskIoChartKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            DiskIoChartKind::Throughput => write!(f, "Throughput"),
+            DiskIoChartKind::Iops => write!(f, "IOPS"),
+            DiskIoChartKind::Await => write!(f, "Await"),
+            DiskIoChartKind::Queue => write!(f, "Queue"),
+            DiskIoChartKind::Utilization => write!(f, "Utilization"),
+        }
+    }
+}
+
+/// One of the rates of a block device over time, reads against writes
+/// where they are counted apart.
+struct DiskIoChart {
+    cache: Cache,
+    kind: DiskIoChartKind,
+    data_points: VecDeque<DiskIoRates>,
+    max_points: usize,
+}
+
+impl DiskIoChart {
+    fn new(kind: DiskIoChartKind, max_points: usize) -> Self {
+        Self {
+            cache: Cache::new(),
+            kind,
+            data_points: VecDeque::new(),
+            max_points,
+        }
+    }
+
+    fn set_data(&mut self, history: &VecDeque<DiskIoRates>) {
+        self.data_points = history.iter().take(self.max_points).copied().collect();
+        self.cache.clear();
+    }
+
+    fn view(&self) -> Element<'_, Message> {
+        let title = match self.data_points.front().map(|rates| self.kind.values(rates)) {
+            Some((read, Some(write))) => format!("{}  read {}  write {}", self.kind, self.kind.format(read), self.kind.format(write)),
+            Some((value, None)) => format!("{}  {}", self.kind, self.kind.format(value)),
+            None => self.kind.to_string(),
+        };
+        column![
+            text(title).size(14),
+            ChartWidget::new(self).height(Length::Fill),
+        ]
+        .width(Length::Fill)
+        .align_items(Alignment::Center)
+        .into()
+    }
+}
+
+impl Chart<Message> for DiskIoChart {
+    type State = ();
+
+    #[inline]
+    fn draw<F: Fn(&mut Frame)>(&self, bounds: Size, draw_fn: F) -> Geometry {
+        self.cache.draw(bounds, draw_fn)
+    }
+
+    fn build_chart<DB: DrawingBackend>(&self, _state: &Self::State, chart: ChartBuilder<DB>) {
+        let kind = self.kind;
+        // Utilization has a ceiling; the rest scale to the busiest point.
+        let max = match kind {
+            DiskIoChartKind::Utilization => 100.0,
+            _ => {
+                self.data_points
+                    .iter()
+                    .map(|rates| {
+                        let (first, second) = kind.values(rates);
+                        first.max(second.unwrap_or(0.0))
+                    })
+                    .fold(1.0, f64::max)
+                    * 1.1
+            }
+        };
+
+        let mut plot = history_plot(chart, self.max_points, 0f64..max, &|y| match kind {
+            DiskIoChartKind::Throughput => format_bytes(*y as u64),
+            DiskIoChartKind::Queue => format!("{:.1}", y),
+            _ => format!("{:.0}", y),
+        });
+
+        let values = self.data_points.iter().map(|rates| kind.values(rates));
+        if kind.has_pair() {
+            let [(_, read), (_, write)] = DISK_IO_COLORS;
+            draw_history(&mut plot, self.max_points, values.clone().map(|(read, _)| read), read);
+            draw_history(&mut plot, self.max_points, values.map(|(_, write)| write.unwrap_or(0.0)), write);
+        } else {
+            draw_history(&mut plot, self.max_points, values.map(|(value, _)| value), DISK_IO_BUSY_COLOR);
+        }
+    }
 }
 
+/// Readings drawn in blue, then amber and red as they near `crit`.
+fn sensor_color(level: SensorLevel) -> (u8, u8, u8) {
+    match level {
+        SensorLevel::Normal => (0, 175, 255),
+        SensorLevel::High => (255, 190, 60),
+        SensorLevel::Critical => (255, 90, 90),
+    }
+}
 
-struct CpuUsageChart {
+/// A sensor's readings over time, with its thresholds as flat lines.
+struct SensorChart {
     cache: Cache,
-    data_points: VecDeque<f64>,
+    sensor: Sensor,
     max_points: usize,
 }
 
-impl CpuUsageChart {
+impl SensorChart {
     fn new(max_points: usize) -> Self {
         Self {
             cache: Cache::new(),
-            data_points: VecDeque::new(),
+            sensor: Sensor::default(),
             max_points,
         }
     }
 
-    fn set_data(&mut self, value: impl Iterator<Item = f64>) {
-        self.data_points = value.collect();
-
-        while self.data_points.len() > self.max_points {
-            self.data_points.pop_back();
-        }
-
+    fn set_data(&mut self, sensor: &Sensor) {
+        self.sensor = sensor.clone();
+        self.sensor.history.truncate(self.max_points);
         self.cache.clear();
     }
 
-    fn view(&self, idx: usize) -> Element<Message> {
-        container(
-            column(Vec::new())
-                .width(Length::Fill)
-                .height(Length::Shrink)
-                .spacing(0)
-                .padding(0)
-                .push(text(format!("Core {}", idx)))
-                .push(
-                    ChartWidget::new(self).height(Length::Fill),
-                )
-                .align_items(Alignment::Center),
-        )
+    fn view(&self) -> Element<'_, Message> {
+        let sensor = &self.sensor;
+        let kind = sensor.kind;
+        let mut thresholds = vec![];
+        if let Some(max) = sensor.max {
+            thresholds.push(format!("high {}", kind.format(max)));
+        }
+        if let Some(crit) = sensor.crit {
+            thresholds.push(format!("crit {}", kind.format(crit)));
+        }
+        let (r, g, b) = sensor_color(sensor.level());
+        column![
+            row![
+                text(&sensor.label).size(14),
+                text(kind.format(sensor.value)).size(14).style(Color::from_rgb8(r, g, b)),
+                text(thresholds.join(", ")).size(12),
+            ]
+            .spacing(8)
+            .align_items(Alignment::Center),
+            ChartWidget::new(self).height(Length::Fill),
+        ]
         .width(Length::Fill)
-        .height(Length::Shrink)
-        .align_x(alignment::Horizontal::Center)
-        .align_y(alignment::Vertical::Center)
+        .align_items(Alignment::Center)
         .into()
     }
 }
 
-impl Chart<Message> for CpuUsageChart {
+impl Chart<Message> for SensorChart {
     type State = ();
-    // fn update(
-    //     &mut self,
-    //     event: Event,
-    //     bounds: Rectangle,
-    //     cursor: Cursor,
-    // ) -> (event::Status, Option<Message>) {
-    //     self.cache.clear();
-    //     (event::Status::Ignored, None)
-    // }
 
     #[inline]
     fn draw<F: Fn(&mut Frame)>(&self, bounds: Size, draw_fn: F) -> Geometry {
         self.cache.draw(bounds, draw_fn)
     }
 
-    fn build_chart<DB: DrawingBackend>(&self, _state: &Self::State, mut chart: ChartBuilder<DB>) {
+    fn build_chart<DB: DrawingBackend>(&self, _state: &Self::State, chart: ChartBuilder<DB>) {
         use plotters::{prelude::*, style::Color};
 
-        const PLOT_LINE_COLOR: RGBColor = RGBColor(0, 175, 255);
         let end = self.max_points;
+        let sensor = &self.sensor;
+        // Room for the thresholds, so the reading shows how far off they are,
+        // and for readings below zero, like negative voltage rails.
+        let values = || sensor.history.iter().copied().chain(sensor.max).chain(sensor.crit);
+        let low = values().fold(f64::INFINITY, f64::min);
+        let high = values().fold(f64::NEG_INFINITY, f64::max);
+        let (low, high) = if low <= high { (low, high) } else { (0.0, 1.0) };
+        let margin = ((high - low) * 0.1).max(high.abs().max(low.abs()) * 0.05).max(0.01);
+
+        let mut plot = history_plot(chart, end, (low - margin)..(high + margin), &|y| match sensor.kind {
+            SensorKind::Voltage => format!("{:.2}", y),
+            _ => format!("{:.0}", y),
+        });
 
-        let mut chart = chart
-            .x_label_area_size(0)
-            .y_label_area_size(24)
-            .margin(5)
-            .build_cartesian_2d(1..self.max_points, 0f64..100.0)
-            .expect("failed to build chart");
+        let thresholds = [(sensor.max, SensorLevel::High), (sensor.crit, SensorLevel::Critical)];
+        for (value, level) in thresholds {
+            if let Some(value) = value {
+                let (r, g, b) = sensor_color(level);
+                plot
+                    .draw_series(LineSeries::new(
+                        [(1, value), (end, value)],
+                        ShapeStyle::from(RGBColor(r, g, b).mix(0.6)).stroke_width(1),
+                    ))
+                    .expect("failed to draw chart threshold");
+            }
+        }
 
-        chart
-            .configure_mesh()
-            .bold_line_style(plotters::style::colors::BLUE.mix(0.1))
-            .light_line_style(plotters::style::colors::BLUE.mix(0.05))
-            .axis_style(ShapeStyle::from(plotters::style::colors::BLUE.mix(0.45)).stroke_width(1))
-            .y_labels(10)
-            .y_label_style(
-                ("sans-serif", 12)
-                    .into_font()
-                    .color(&plotters::style::colors::BLUE.mix(0.65))
-                    .transform(FontTransform::Rotate90),
-            )
-            .y_label_formatter(&|y| format!("{}%", y))
-            .draw()
-            .expect("failed to draw chart mesh");
+        draw_history(&mut plot, end, sensor.history.iter().copied(), sensor_color(sensor.level()));
+    }
+}
 
-        chart
-            .draw_series(
-                AreaSeries::new(
-                    self.data_points.iter().enumerate().map(|(x, y)| (end - x, *y)),
-                    0.0,
-                    PLOT_LINE_COLOR.mix(0.175),
-                )
-                .border_style(ShapeStyle::from(PLOT_LINE_COLOR).stroke_width(2)),
-            )
-            .expect("failed to draw chart data");
+/// One of the rates of an interface over time, received against
+/// transmitted.
+struct NetworkChart {
+    cache: Cache,
+    kind: NetworkChartKind,
+    data_points: VecDeque<NetworkRates>,
+    max_points: usize,
+}
+
+impl NetworkChart {
+    fn new(kind: NetworkChartKind, max_points: usize) -> Self {
+        Self {
+            cache: Cache::new(),
+            kind,
+            data_points: VecDeque::new(),
+            max_points,
+        }
+    }
+
+    fn set_data(&mut self, history: &VecDeque<NetworkRates>) {
+        self.data_points = history.iter().take(self.max_points).copied().collect();
+        self.cache.clear();
+    }
+
+    fn view(&self) -> Element<'_, Message> {
+        let (rx, tx) = self.data_points.front().map(|rates| self.kind.values(rates)).unwrap_or_default();
+        column![
+            text(format!("{}  rx {}  tx {}", self.kind, self.kind.format(rx), self.kind.format(tx))).size(14),
+            ChartWidget::new(self).height(Length::Fill),
+        ]
+        .width(Length::Fill)
+        .align_items(Alignment::Center)
+        .into()
     }
 }
 
+impl Chart<Message> for NetworkChart {
+    type State = ();
+
+    #[inline]
+    fn draw<F: Fn(&mut Frame)>(&self, bounds: Size, draw_fn: F) -> Geometry {
+        self.cache.draw(bounds, draw_fn)
+    }
+
+    fn build_chart<DB: DrawingBackend>(&self, _state: &Self::State, chart: ChartBuilder<DB>) {
+        let kind = self.kind;
+        // Scaled to the busiest point shown, so that idle links stay flat.
+        let max = self
+            .data_points
+            .iter()
+            .map(|rates| {
+                let (rx, tx) = kind.values(rates);
+                rx.max(tx)
+            })
+            .fold(1.0, f64::max)
+            * 1.1;
+
+        let mut plot = history_plot(chart, self.max_points, 0f64..max, &|y| match kind {
+            NetworkChartKind::Throughput => format_bytes(*y as u64),
+            _ => format!("{:.0}", y),
+        });
+
+        let [(_, receive), (_, transmit)] = NETWORK_COLORS;
+        let values = self.data_points.iter().map(|rates| kind.values(rates));
+        draw_history(&mut plot, self.max_points, values.clone().map(|(rx, _)| rx), receive);
+        draw_history(&mut plot, self.max_points, values.map(|(_, tx)| tx), transmit);
+    }
+}
 
 struct DiskUsageChart {
     cache: Cache,
-    data_points: (f64, f64),
-    tech: String,
-    capacity: String,
+    partition: PartitionUsage,
 }
 
 impl DiskUsageChart {
     fn new() -> Self {
         Self {
             cache: Cache::new(),
-            data_points: (0.0, 100.0),
-            tech: String::from(""),
-            capacity: String::from(""),
+            partition: PartitionUsage::default(),
         }
     }
 
-    fn set_data(&mut self, value: (f64, f64), tech: String, cap: String) {
-        self.data_points = value;
-        self.tech = tech;
-        self.capacity = cap;
+    fn set_data(&mut self, partition: PartitionUsage) {
+        self.partition = partition;
         self.cache.clear();
     }
 
-    fn view(&self, _idx: usize) -> Element<Message> {
+    fn view(&self, _idx: usize) -> Element<'_, Message> {
+        let partition = &self.partition;
         // container(
         //     column(Vec::new())
         //         .width(Length::Fill)
@@ -1484,10 +3692,27 @@ impl DiskUsageChart {
                 .height(Length::Shrink)
                 .spacing(0)
                 .padding(0)
-                .push(text(format!("Partition {} ({})", self.tech, self.capacity)))
+                .push(text(format!("{} ({}, {})", partition.mount_point, partition.device, partition.fs_type)))
                 .push(
                     ChartWidget::new(self).height(Length::Fill),
                 )
+                .push(text(format!(
+                    "{} used of {}, {} free, {} available",
+                    format_bytes(partition.used()),
+                    format_bytes(partition.total),
+                    format_bytes(partition.free),
+                    format_bytes(partition.available),
+                )).size(14))
+                .push(text(if partition.inodes_total == 0 {
+                    String::from("No inode limit")
+                } else {
+                    format!(
+                        "Inodes: {} used of {} ({:.0}%)",
+                        partition.inodes_used(),
+                        partition.inodes_total,
+                        partition.inodes_used() as f64 * 100.0 / partition.inodes_total as f64,
+                    )
+                }).size(14))
                 .align_items(Alignment::Center),
         )
         .width(Length::Fill)
@@ -1542,7 +3767,8 @@ impl Chart<Message> for DiskUsageChart {
         let dims = area.dim_in_pixel();
         let center = (dims.0 as i32 / 2, dims.1 as i32 / 2);
         let radius = (dims.1 / 2) as f64;
-        let sizes = vec![self.data_points.1, self.data_points.0];
+        let used = self.partition.used_percent();
+        let sizes = vec![100.0 - used, used];
         let colors = vec![FREE_COLOR, USED_COLOR];
         let labels = vec!["", ""];
 
@@ -1558,42 +3784,38 @@ impl Chart<Message> for DiskUsageChart {
 
 struct MemoryUsageChart {
     cache: Cache,
-    memory_points: (f64, f64),
-    swap_points: (f64, f64),
+    stats: MemoryStats,
     tech: String,
-    capacity: String,
-    swap_capacity: String,
 }
 
 impl MemoryUsageChart {
     fn new() -> Self {
         Self {
             cache: Cache::new(),
-            memory_points: (0.0, 100.0),
-            swap_points: (0.0, 100.0),
+            stats: MemoryStats::default(),
             tech: String::from(""),
-            capacity: String::from(""),
-            swap_capacity: String::from(""),
         }
     }
 
-    fn set_data(&mut self, memory_value: (f64, f64), swap_value: (f64, f64), tech: String, cap: String, swap_cap: String) {
-        self.memory_points = memory_value;
-        self.swap_points = swap_value;
+    fn set_data(&mut self, stats: MemoryStats, tech: String) {
+        self.stats = stats;
         self.tech = tech;
-        self.capacity = cap;
-        self.swap_capacity = swap_cap;
         self.cache.clear();
     }
 
-    fn view(&self) -> Element<Message> {
+    fn view(&self) -> Element<'_, Message> {
         container(
             column(Vec::new())
                 .width(Length::Fill)
                 .height(Length::Shrink)
                 .spacing(0)
                 .padding(0)
-                .push(text(format!("Memory ({}, {}) - Swap ({})", self.tech, self.capacity, self.swap_capacity)))
+                .push(text(format!(
+                    "Memory ({}, {}) - Swap ({})",
+                    self.tech,
+                    format_bytes(self.stats.total),
+                    format_bytes(self.stats.swap_total),
+                )))
                 .push(
                     ChartWidget::new(self).height(Length::Fill),
                 )
@@ -1658,13 +3880,21 @@ impl Chart<Message> for MemoryUsageChart {
             .draw()
             .expect("failed to draw chart mesh");
         
+        let percent = |part: u64, total: u64| if total == 0 { 0.0 } else { part as f64 * 100.0 / total as f64 };
+        let stats = &self.stats;
+        let memory_points = (percent(stats.used(), stats.total), percent(stats.buff_cache(), stats.total));
+        // Swapped-out pages still cached in RAM count towards swap_used().
+        let swap_cached = stats.swap_cached.min(stats.swap_used());
+        let swap_points =
+            (percent(stats.swap_used() - swap_cached, stats.swap_total), percent(swap_cached, stats.swap_total));
+
         let data = [
-            (0.0, self.swap_points.0, 1, USED_COLOR),
-            (self.swap_points.0, self.swap_points.0 + self.swap_points.1, 1, BUFF_COLOR),
-            (self.swap_points.0 + self.swap_points.1, 100.0, 1, FREE_COLOR),
-            (0.0, self.memory_points.0, 2, USED_COLOR),
-            (self.memory_points.0, self.memory_points.0 + self.memory_points.1, 2, BUFF_COLOR),
-            (self.memory_points.0 + self.memory_points.1, 100.0, 2, FREE_COLOR),
+            (0.0, swap_points.0, 1, USED_COLOR),
+            (swap_points.0, swap_points.0 + swap_points.1, 1, BUFF_COLOR),
+            (swap_points.0 + swap_points.1, 100.0, 1, FREE_COLOR),
+            (0.0, memory_points.0, 2, USED_COLOR),
+            (memory_points.0, memory_points.0 + memory_points.1, 2, BUFF_COLOR),
+            (memory_points.0 + memory_points.1, 100.0, 2, FREE_COLOR),
         ];
 
         chart
@@ -1693,91 +3923,536 @@ impl Chart<Message> for MemoryUsageChart {
 mod tasks {
     use crate::*;
     use iced::widget::canvas::{event::{self, Event}};
+    use crate::filter::Filter;
+    use std::cell::Cell;
+    use std::cmp::Ordering;
+    use std::collections::HashSet;
+
+    /// Rough width of a character of the list's text, to fit the cells.
+    const CHAR_WIDTH: f32 = 6.0;
+    const PADDING: f32 = 5.;
+    const LINE_HEIGHT: f32 = 18.;
+    const SCROLLBAR_WIDTH: f32 = 10.;
+    /// Rows moved by one notch of the mouse wheel.
+    const WHEEL_ROWS: f32 = 3.;
+
+    /// Keyboard moves of the selection.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Move {
+        Up,
+        Down,
+        PageUp,
+        PageDown,
+        Home,
+        End,
+    }
     
     pub struct TasksListChart {
+        /// The processes shown, after filtering.
         pub process_info: Vec<ProcessInfo>,
+        /// Everything from the last refresh.
+        pub all: Vec<ProcessInfo>,
+        pub filter: Option<Filter>,
+        /// Processes matching the filter itself, without the ancestors.
+        pub matching: usize,
+        /// Shown from left to right.
+        pub columns: Vec<Column>,
+        /// Where each column but the first starts, as a share of the width.
         pub separators: Vec<f32>,
-        pub item_sort: ItemSort,
+        pub item_sort: Column,
         pub rev: bool,
+        /// PID shown in the detail pane.
+        pub selected: Option<usize>,
+        /// Nest processes under their parent.
+        pub tree: bool,
+        /// PIDs whose children are hidden in tree mode.
+        pub collapsed: HashSet<usize>,
+        /// What is drawn, in order.
+        pub rows: Vec<Row>,
+        /// First row shown.
+        pub scroll: usize,
+        /// Rows that fit the last drawn size, for paging.
+        pub visible_rows: Cell<usize>,
+    }
+
+    /// A line of the list: a process, with its place in the tree.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct Row {
+        /// Index into `process_info`.
+        pub index: usize,
+        pub depth: usize,
+        pub children: usize,
+        pub collapsed: bool,
+        /// Including the hidden descendants of a collapsed process.
+        pub cpu: f64,
+        pub memory: f64,
+    }
+
+    /// Orders the process list by a column, siblings only in tree mode.
+    fn compare(item_sort: Column, rev: bool, a: &ProcessInfo, b: &ProcessInfo) -> Ordering {
+        let ordering = item_sort.compare(a, b);
+        if rev { ordering.reverse() } else { ordering }
+    }
+
+    /// Lays out sorted processes as a tree under their parents; processes
+    /// whose parent isn't listed are roots.
+    pub fn tree_rows(process_info: &[ProcessInfo], collapsed: &HashSet<usize>) -> Vec<Row> {
+        let indices: HashMap<usize, usize> = process_info.iter().enumerate().map(|(i, p)| (p.pid, i)).collect();
+        let mut children: HashMap<usize, Vec<usize>> = HashMap::new();
+        let mut roots = vec![];
+        for (i, info) in process_info.iter().enumerate() {
+            if info.ppid != info.pid && indices.contains_key(&info.ppid) {
+                children.entry(info.ppid).or_default().push(i);
+            } else {
+                roots.push(i);
+            }
+        }
+
+        fn subtree_usage(i: usize, process_info: &[ProcessInfo], children: &HashMap<usize, Vec<usize>>) -> (f64, f64) {
+            let info = &process_info[i];
+            children.get(&info.pid).into_iter().flatten().fold((info.cpu, info.memory), |(cpu, memory), &child| {
+                let (child_cpu, child_memory) = subtree_usage(child, process_info, children);
+                (cpu + child_cpu, memory + child_memory)
+            })
+        }
+
+        let mut rows = Vec::with_capacity(process_info.len());
+        let mut stack: Vec<(usize, usize)> = roots.into_iter().rev().map(|i| (i, 0)).collect();
+        while let Some((i, depth)) = stack.pop() {
+            let info = &process_info[i];
+            let kids = children.get(&info.pid).map_or(&[][..], Vec::as_slice);
+            let is_collapsed = !kids.is_empty() && collapsed.contains(&info.pid);
+            let (cpu, memory) = if is_collapsed {
+                subtree_usage(i, process_info, &children)
+            } else {
+                (info.cpu, info.memory)
+            };
+            rows.push(Row { index: i, depth, children: kids.len(), collapsed: is_collapsed, cpu, memory });
+            if !is_collapsed {
+                stack.extend(kids.iter().rev().map(|&child| (child, depth + 1)));
+            }
+        }
+        rows
     }
 
-    #[derive(PartialEq, Eq)]
-    pub enum ItemSort {
+    /// A column the task list can show, picked in the column chooser.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub enum Column {
         Pid,
+        Ppid,
         Nice,
+        Priority,
         Status,
         User,
         Cpu,
         Memory,
+        Rss,
+        Vsz,
+        Swap,
+        Threads,
+        Start,
+        Elapsed,
+        Tty,
+        Processor,
+        IoRead,
+        IoWrite,
+        OomScore,
+        Cgroup,
         Cmd,
     }
 
-    impl ItemSort {
-        pub fn by(u: usize) -> Self {
-            match u {
-                0 => ItemSort::Pid,
-                1 => ItemSort::Nice,
-                2 => ItemSort::Status,
-                3 => ItemSort::User,
-                4 => ItemSort::Cpu,
-                5 => ItemSort::Memory,
-                6 => ItemSort::Cmd,
-                _ => ItemSort::Nice,
+    impl Column {
+        pub const ALL: [Column; 21] = [
+            Column::Pid,
+            Column::Ppid,
+            Column::Nice,
+            Column::Priority,
+            Column::Status,
+            Column::User,
+            Column::Cpu,
+            Column::Memory,
+            Column::Rss,
+            Column::Vsz,
+            Column::Swap,
+            Column::Threads,
+            Column::Start,
+            Column::Elapsed,
+            Column::Tty,
+            Column::Processor,
+            Column::IoRead,
+            Column::IoWrite,
+            Column::OomScore,
+            Column::Cgroup,
+            Column::Cmd,
+        ];
+
+        pub const DEFAULT: [Column; 7] = [
+            Column::Pid,
+            Column::Nice,
+            Column::Status,
+            Column::User,
+            Column::Cpu,
+            Column::Memory,
+            Column::Cmd,
+        ];
+
+        pub fn title(self) -> &'static str {
+            match self {
+                Column::Pid => "PID",
+                Column::Ppid => "PPID",
+                Column::Nice => "Nice",
+                Column::Priority => "Prio",
+                Column::Status => "Status",
+                Column::User => "User",
+                Column::Cpu => "CPU%",
+                Column::Memory => "MEM%",
+                Column::Rss => "RSS",
+                Column::Vsz => "VSZ",
+                Column::Swap => "Swap",
+                Column::Threads => "Threads",
+                Column::Start => "Start",
+                Column::Elapsed => "Elapsed",
+                Column::Tty => "TTY",
+                Column::Processor => "Last CPU",
+                Column::IoRead => "Read/s",
+                Column::IoWrite => "Write/s",
+                Column::OomScore => "OOM",
+                Column::Cgroup => "Cgroup",
+                Column::Cmd => "Command",
+            }
+        }
+
+        /// Share of the list's width a column gets when added.
+        fn default_width(self) -> f32 {
+            match self {
+                Column::Cmd => 6. / 12.,
+                Column::Cgroup => 3. / 12.,
+                Column::Start | Column::Elapsed => 1.5 / 12.,
+                _ => 1. / 12.,
+            }
+        }
+
+        /// Whether sorting by the column starts with the largest values.
+        fn descending(self) -> bool {
+            !matches!(
+                self,
+                Column::Pid | Column::Ppid | Column::Nice | Column::User | Column::Start | Column::Tty | Column::Cgroup | Column::Cmd
+            )
+        }
+
+        /// The cell of a process; CPU and memory come from the row, which
+        /// sums up collapsed subtrees.
+        fn text(self, info: &ProcessInfo, row: &Row) -> String {
+            let rate = |rate: Option<f64>| rate.map_or_else(|| String::from("-"), |rate| format!("{}/s", format_bytes(rate as u64)));
+            match self {
+                Column::Pid => info.pid.to_string(),
+                Column::Ppid => info.ppid.to_string(),
+                Column::Nice => info.nice.clone(),
+                Column::Priority => info.priority.to_string(),
+                Column::Status => info.status.clone(),
+                Column::User => info.user.clone(),
+                Column::Cpu => format!("{:.1}", row.cpu),
+                Column::Memory => format!("{:.1}", row.memory),
+                Column::Rss => format_bytes(info.rss),
+                Column::Vsz => format_bytes(info.vsz),
+                Column::Swap => format_bytes(info.swap),
+                Column::Threads => info.threads.to_string(),
+                // "MM-DD HH:MM" out of the full UTC timestamp.
+                Column::Start => format_timestamp(info.start)[5..16].to_string(),
+                Column::Elapsed => format_duration(Duration::from_secs(info.elapsed)),
+                Column::Tty => info.tty.clone(),
+                Column::Processor => info.processor.to_string(),
+                Column::IoRead => rate(info.io_read),
+                Column::IoWrite => rate(info.io_write),
+                Column::OomScore => info.oom_score.map_or_else(|| String::from("-"), |score| score.to_string()),
+                Column::Cgroup => info.cgroup.clone(),
+                Column::Cmd => info.cmd.clone(),
+            }
+        }
+
+        fn compare(self, a: &ProcessInfo, b: &ProcessInfo) -> Ordering {
+            // Unknown rates sort below every known one.
+            let rate = |a: Option<f64>, b: Option<f64>| a.unwrap_or(-1.).total_cmp(&b.unwrap_or(-1.));
+            match self {
+                Column::Pid => a.pid.cmp(&b.pid),
+                Column::Ppid => a.ppid.cmp(&b.ppid),
+                Column::Nice => a.nice.cmp(&b.nice),
+                Column::Priority => a.priority.cmp(&b.priority),
+                Column::Status => a.status.cmp(&b.status),
+                Column::User => a.user.cmp(&b.user),
+                Column::Cpu => a.cpu.total_cmp(&b.cpu),
+                Column::Memory => a.memory.total_cmp(&b.memory),
+                Column::Rss => a.rss.cmp(&b.rss),
+                Column::Vsz => a.vsz.cmp(&b.vsz),
+                Column::Swap => a.swap.cmp(&b.swap),
+                Column::Threads => a.threads.cmp(&b.threads),
+                Column::Start => a.start.cmp(&b.start),
+                Column::Elapsed => a.elapsed.cmp(&b.elapsed),
+                Column::Tty => a.tty.cmp(&b.tty),
+                Column::Processor => a.processor.cmp(&b.processor),
+                Column::IoRead => rate(a.io_read, b.io_read),
+                Column::IoWrite => rate(a.io_write, b.io_write),
+                Column::OomScore => a.oom_score.cmp(&b.oom_score),
+                Column::Cgroup => a.cgroup.cmp(&b.cgroup),
+                Column::Cmd => a.cmd.cmp(&b.cmd),
             }
         }
     }
-    
+
+    impl std::fmt::Display for Column {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{}", self.title())
+        }
+    }
+
     impl TasksListChart {
         pub fn new() -> Self {
             TasksListChart {
                 process_info: vec![],
-                separators: (1..7).into_iter().map(|i| (i as f32) / 12.0).collect(),
+                all: vec![],
+                filter: None,
+                matching: 0,
+                columns: Column::DEFAULT.to_vec(),
+                separators: (1..7).map(|i| (i as f32) / 12.0).collect(),
                 // separators: vec![9., 17., 25., 37., 44., 51.],
-                item_sort: ItemSort::Memory,
+                item_sort: Column::Memory,
                 rev: true,
+                selected: None,
+                tree: false,
+                collapsed: HashSet::new(),
+                rows: vec![],
+                scroll: 0,
+                visible_rows: Cell::new(0),
             }
         }
     
         pub fn set_data(&mut self, process_info: &[ProcessInfo]) {
-            self.process_info = process_info.to_owned();
+            self.all = process_info.to_owned();
+            self.apply_filter();
+        }
+
+        pub fn set_filter(&mut self, filter: Option<Filter>) {
+            self.filter = filter;
+            self.apply_filter();
+        }
+
+        /// Keeps the matching processes, with their ancestors in tree mode
+        /// so they still hang under whatever started them.
+        fn apply_filter(&mut self) {
+            let filter = match &self.filter {
+                Some(filter) => filter,
+                None => {
+                    self.process_info = self.all.clone();
+                    self.matching = self.all.len();
+                    self.sort();
+                    return;
+                }
+            };
+            let mut keep: HashSet<usize> = self.all.iter().filter(|p| filter.matches(p)).map(|p| p.pid).collect();
+            self.matching = keep.len();
+            if self.tree {
+                let parents: HashMap<usize, usize> = self.all.iter().map(|p| (p.pid, p.ppid)).collect();
+                for pid in keep.clone() {
+                    let mut pid = parents.get(&pid).copied().unwrap_or(0);
+                    while pid != 0 && keep.insert(pid) {
+                        pid = parents.get(&pid).copied().unwrap_or(0);
+                    }
+                }
+            }
+            self.process_info = self.all.iter().filter(|p| keep.contains(&p.pid)).cloned().collect();
             self.sort();
         }
         
-        pub fn sort_by(&mut self, u: usize) {
-            let new_sort = ItemSort::by(u);
+        pub fn sort_by(&mut self, new_sort: Column) {
             if self.item_sort == new_sort {
                 self.rev = !self.rev;
             } else {
-                self.rev = match new_sort {
-                    ItemSort::Pid => false,
-                    ItemSort::Nice => false,
-                    ItemSort::Status => true,
-                    ItemSort::User => false,
-                    ItemSort::Cpu => true,
-                    ItemSort::Memory => true,
-                    ItemSort::Cmd => false,
-                }
+                self.rev = new_sort.descending();
             }
             self.item_sort = new_sort;
             self.sort();
+            if let Some(row) = self.selected_row() {
+                self.reveal(row);
+            }
         }
 
         pub fn sort(&mut self) {
-            self.process_info.sort_unstable_by(|a, b| {
-                match self.item_sort {
-                    ItemSort::Pid => { a.pid.cmp(&b.pid) }
-                    ItemSort::Nice => { a.nice.cmp(&b.nice) }
-                    ItemSort::Status => { a.status.cmp(&b.status) }
-                    ItemSort::User => { a.user.cmp(&b.user) }
-                    ItemSort::Cpu => { a.cpu.partial_cmp(&b.cpu).unwrap() }
-                    ItemSort::Memory => { a.memory.partial_cmp(&b.memory).unwrap() }
-                    ItemSort::Cmd => { a.cmd.cmp(&b.cmd) }
+            let (item_sort, rev) = (self.item_sort, self.rev);
+            self.process_info.sort_unstable_by(|a, b| compare(item_sort, rev, a, b));
+            self.rows = if self.tree {
+                tree_rows(&self.process_info, &self.collapsed)
+            } else {
+                self.process_info
+                    .iter()
+                    .enumerate()
+                    .map(|(index, info)| Row {
+                        index,
+                        depth: 0,
+                        children: 0,
+                        collapsed: false,
+                        cpu: info.cpu,
+                        memory: info.memory,
+                    })
+                    .collect()
+            };
+            self.scroll_to(self.scroll);
+        }
+
+        pub fn set_tree(&mut self, tree: bool) {
+            self.tree = tree;
+            self.apply_filter();
+        }
+
+        pub fn toggle_collapsed(&mut self, pid: usize) {
+            if !self.collapsed.remove(&pid) {
+                self.collapsed.insert(pid);
+            }
+            self.sort();
+        }
+
+        /// Each column's share of the width.
+        fn widths(&self) -> Vec<f32> {
+            let edges: Vec<f32> = std::iter::once(0.).chain(self.separators.iter().copied()).chain(std::iter::once(1.)).collect();
+            edges.windows(2).map(|edge| edge[1] - edge[0]).collect()
+        }
+
+        fn set_widths(&mut self, widths: &[f32]) {
+            let total: f32 = widths.iter().sum();
+            let mut edge = 0.;
+            self.separators = widths[..widths.len() - 1]
+                .iter()
+                .map(|width| {
+                    edge += width / total;
+                    edge
+                })
+                .collect();
+        }
+
+        /// Adds a column, before the command when that is last, or removes
+        /// it. The others keep their proportions and the last one stays.
+        pub fn set_column(&mut self, column: Column, shown: bool) {
+            let mut widths = self.widths();
+            match self.columns.iter().position(|&c| c == column) {
+                Some(i) if !shown && self.columns.len() > 1 => {
+                    self.columns.remove(i);
+                    widths.remove(i);
                 }
-            });
-            if self.rev {
-                self.process_info.reverse();
+                None if shown => {
+                    let i = if self.columns.last() == Some(&Column::Cmd) { self.columns.len() - 1 } else { self.columns.len() };
+                    self.columns.insert(i, column);
+                    widths.insert(i, column.default_width());
+                }
+                _ => return,
+            }
+            self.set_widths(&widths);
+        }
+
+        /// Shows these columns, at their default widths.
+        pub fn set_columns(&mut self, columns: &[Column]) {
+            self.columns = columns.to_vec();
+            let widths: Vec<f32> = columns.iter().map(|column| column.default_width()).collect();
+            self.set_widths(&widths);
+        }
+
+        /// Moves a column, with its width, to `to` in the new order.
+        pub fn move_column(&mut self, from: usize, to: usize) {
+            if from >= self.columns.len() || to >= self.columns.len() {
+                return;
+            }
+            let mut widths = self.widths();
+            let width = widths.remove(from);
+            widths.insert(to, width);
+            let column = self.columns.remove(from);
+            self.columns.insert(to, column);
+            self.set_widths(&widths);
+        }
+
+        /// The column under `x`, a share of the width.
+        fn column_at(&self, x: f32) -> usize {
+            self.separators.iter().filter(|&&sep| sep < x).count()
+        }
+
+        fn page(&self) -> usize {
+            self.visible_rows.get().max(1)
+        }
+
+        fn max_scroll(&self) -> usize {
+            self.rows.len().saturating_sub(self.page())
+        }
+
+        pub fn scroll_to(&mut self, row: usize) {
+            self.scroll = row.min(self.max_scroll());
+        }
+
+        /// Scrolls just enough for `row` to be shown.
+        fn reveal(&mut self, row: usize) {
+            if row < self.scroll {
+                self.scroll_to(row);
+            } else if row >= self.scroll + self.page() {
+                self.scroll_to(row + 1 - self.page());
+            }
+        }
+
+        /// Where the selected PID is now, if it is still listed.
+        fn selected_row(&self) -> Option<usize> {
+            let pid = self.selected?;
+            self.rows.iter().position(|row| self.process_info[row.index].pid == pid)
+        }
+
+        /// Moves the selection and returns the PID it lands on. Starts from
+        /// the top when nothing listed is selected.
+        pub fn move_selection(&mut self, movement: Move) -> Option<usize> {
+            let last = self.rows.len().checked_sub(1)?;
+            let row = match (self.selected_row(), movement) {
+                (_, Move::Home) => 0,
+                (_, Move::End) => last,
+                (None, _) => 0,
+                (Some(row), Move::Up) => row.saturating_sub(1),
+                (Some(row), Move::Down) => (row + 1).min(last),
+                (Some(row), Move::PageUp) => row.saturating_sub(self.page()),
+                (Some(row), Move::PageDown) => (row + self.page()).min(last),
+            };
+            let pid = self.process_info[self.rows[row].index].pid;
+            self.selected = Some(pid);
+            self.reveal(row);
+            Some(pid)
+        }
+
+        /// Selects a PID picked elsewhere, opening the collapsed subtrees
+        /// it is hidden in and scrolling to it.
+        pub fn select(&mut self, pid: usize) {
+            self.selected = Some(pid);
+            let parents: HashMap<usize, usize> = self.process_info.iter().map(|p| (p.pid, p.ppid)).collect();
+            let mut parent = parents.get(&pid).copied().unwrap_or(0);
+            let mut expanded = false;
+            while parent != 0 {
+                expanded |= self.collapsed.remove(&parent);
+                parent = parents.get(&parent).copied().unwrap_or(0);
+            }
+            if expanded {
+                self.sort();
+            }
+            if let Some(row) = self.selected_row() {
+                self.reveal(row);
             }
         }
+
+        /// The scrollbar thumb's top and height in a track, when the rows
+        /// don't all fit.
+        fn thumb(&self, visible_rows: usize, track_top: f32, track_height: f32) -> Option<(f32, f32)> {
+            if self.rows.len() <= visible_rows {
+                return None;
+            }
+            let height = (track_height * visible_rows as f32 / self.rows.len() as f32).max(LINE_HEIGHT / 2.);
+            let max_scroll = (self.rows.len() - visible_rows) as f32;
+            let top = track_top + (track_height - height) * (self.scroll as f32 / max_scroll).min(1.);
+            Some((top, height))
+        }
+    }
+
+    /// Rows that fit under the header.
+    fn visible_rows(height: f32) -> usize {
+        ((height - LINE_HEIGHT) / LINE_HEIGHT).max(0.) as usize
     }
     
     #[derive(Debug, Clone, Copy)]
@@ -1786,11 +4461,22 @@ mod tasks {
             selected: usize,
             diff: f32,
         },
+        /// Dragging the scrollbar thumb, grabbed `grab` below its top.
+        Scrollbar {
+            grab: f32,
+        },
+        /// Pressed on a column's title: a click sorts, a drag moves it.
+        Header {
+            column: usize,
+            x: f32,
+        },
         // Two { from: Point, to: Point },
     }
     
     impl canvas::Program<Message> for TasksListChart {
-        type State = (Option<Pending>, mouse::Interaction);
+        /// The drag in progress, the cursor, and the rows scrolled by the
+        /// wheel that don't add up to a whole one yet.
+        type State = (Option<Pending>, mouse::Interaction, f32);
     
         fn update(
             &self,
@@ -1814,16 +4500,33 @@ mod tasks {
                 if let Some(position) = cursor.position_from(bounds.position()) {
                     position
                 } else {
+                    // A drag released outside the list ends there.
+                    if let Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) = event {
+                        state.0 = None;
+                    }
                     return (event::Status::Ignored, None);
                 };
     
     
-            let padding = 5.;
+            let padding = PADDING;
             let start = padding;
-            let width = bounds.width - (2. * padding);
-            let _height = bounds.height - (2. * padding);
+            let width = bounds.width - (2. * padding) - SCROLLBAR_WIDTH;
+            let height = bounds.height - (2. * padding);
 
-            let line_height = 18.;
+            let line_height = LINE_HEIGHT;
+
+            let visible = visible_rows(height);
+            let track_top = start + line_height;
+            let track_height = height - line_height;
+            let thumb = self.thumb(visible, track_top, track_height);
+            let on_scrollbar = cursor_position.x >= start + width
+                && cursor_position.x <= start + width + SCROLLBAR_WIDTH
+                && cursor_position.y >= track_top;
+            // The first row shown with the thumb's top at `top`.
+            let scroll_at = |top: f32, thumb_height: f32| {
+                let max_scroll = self.rows.len().saturating_sub(visible) as f32;
+                ((top - track_top) / (track_height - thumb_height) * max_scroll).round().max(0.) as usize
+            };
     
             match event {
                 Event::Mouse(mouse_event) => {
@@ -1838,7 +4541,7 @@ mod tasks {
                                         && cursor_position.y <= start + line_height
                                     });
                                     
-                                    let iter = once(&0.).chain(self.separators.iter()).chain(once(&100.));
+                                    let iter = once(&0.).chain(self.separators.iter()).chain(once(&1.));
                                     let mut iter2 = iter.clone();
                                     iter2.next();
                                     let iter = iter.zip(iter2);
@@ -1850,7 +4553,16 @@ mod tasks {
                                     });
     
                                     
-                                    if let Some((selected, &sep)) = selected {
+                                    if let (true, Some((top, thumb_height))) = (on_scrollbar, thumb) {
+                                        // Outside the thumb, jump so that it is centered on the cursor.
+                                        let grab = if cursor_position.y >= top && cursor_position.y <= top + thumb_height {
+                                            cursor_position.y - top
+                                        } else {
+                                            thumb_height / 2.
+                                        };
+                                        state.0 = Some(Pending::Scrollbar { grab });
+                                        Some(Message::ScrollTasksTo(scroll_at(cursor_position.y - grab, thumb_height)))
+                                    } else if let Some((selected, &sep)) = selected {
                                         state.0 = Some(Pending::One {
                                             selected,
                                             diff: cursor_position.x - (start + sep*width),
@@ -1858,9 +4570,26 @@ mod tasks {
                                         state.1 = mouse::Interaction::ResizingHorizontally;
 
                                         None
-                                    } else if let Some((selected, _)) = over {
-                                        state.1 = mouse::Interaction::Pointer;
-                                        Some(Message::SortTasks(selected))
+                                    } else if let Some((column, _)) = over {
+                                        state.0 = Some(Pending::Header { column, x: cursor_position.x });
+                                        state.1 = mouse::Interaction::Grabbing;
+                                        None
+                                    } else if cursor_position.y > start + line_height && cursor_position.x < start + width {
+                                        let row = self.scroll + ((cursor_position.y - start) / line_height) as usize - 1;
+                                        self.rows.get(row).map(|row| {
+                                            let pid = self.process_info[row.index].pid;
+                                            // The [+]/[-] in front of the command.
+                                            let on_toggle = self.columns.iter().position(|&c| c == Column::Cmd).is_some_and(|cmd| {
+                                                let cmd_start = if cmd == 0 { 0. } else { self.separators[cmd - 1] };
+                                                let toggle_start = start + cmd_start * width + (row.depth * 2) as f32 * CHAR_WIDTH;
+                                                cursor_position.x >= toggle_start && cursor_position.x <= toggle_start + 4. * CHAR_WIDTH
+                                            });
+                                            if row.children > 0 && on_toggle {
+                                                Message::ToggleTaskNode(pid)
+                                            } else {
+                                                Message::SelectTask(pid)
+                                            }
+                                        })
                                     } else {
                                         state.1 = mouse::Interaction::Idle;
                                         None
@@ -1872,11 +4601,15 @@ mod tasks {
                             }
                         }
                         mouse::Event::ButtonReleased(mouse::Button::Left) => {
-                            match state.0 {
-                                Some(Pending::One { .. }) => {
-                                    state.0 = None;
-    
-                                    None
+                            match state.0.take() {
+                                Some(Pending::Header { column, x }) => {
+                                    state.1 = mouse::Interaction::Pointer;
+                                    let target = self.column_at((cursor_position.x - start) / width);
+                                    if (cursor_position.x - x).abs() < 5. || target == column {
+                                        Some(Message::SortTasks(self.columns[column]))
+                                    } else {
+                                        Some(Message::MoveTaskColumn(column, target))
+                                    }
                                 }
                                 _ => {
                                     None
@@ -1884,12 +4617,13 @@ mod tasks {
                             }
                         }
                         mouse::Event::CursorMoved { position } => {
-                            let pos_x = if let Some(position) = Cursor::Available(position).position_from(bounds.position()) {
-                                position.x
-                            } else {
-                                cursor_position.x
-                            };
+                            let moved_to = Cursor::Available(position).position_from(bounds.position()).unwrap_or(cursor_position);
+                            let pos_x = moved_to.x;
                             match state.0 {
+                                Some(Pending::Scrollbar { grab }) => {
+                                    thumb.map(|(_, thumb_height)| Message::ScrollTasksTo(scroll_at(moved_to.y - grab, thumb_height)))
+                                }
+                                Some(Pending::Header { .. }) => None,
                                 Some(Pending::One { selected, diff }) => {
                                     let pos_x = pos_x - diff;
                                     let prev = if selected == 0 {
@@ -1897,7 +4631,7 @@ mod tasks {
                                     } else {
                                         Some(selected - 1)
                                     };
-                                    let next = if selected == 5 {
+                                    let next = if selected + 1 == self.separators.len() {
                                         None
                                     } else {
                                         Some(selected + 1)
@@ -1951,6 +4685,17 @@ mod tasks {
                                 }
                             }
                         }
+                        mouse::Event::WheelScrolled { delta } => {
+                            let rows = match delta {
+                                mouse::ScrollDelta::Lines { y, .. } => -y * WHEEL_ROWS,
+                                mouse::ScrollDelta::Pixels { y, .. } => -y / line_height,
+                            };
+                            // Touchpads send a few pixels at a time.
+                            state.2 += rows;
+                            let whole = state.2.trunc();
+                            state.2 -= whole;
+                            (whole != 0.0).then_some(Message::ScrollTasks(whole as isize))
+                        }
                         _ => None,
                     };
     
@@ -1971,19 +4716,21 @@ mod tasks {
     
         fn draw(
             &self,
-            _state: &Self::State,
+            state: &Self::State,
             _theme: &Theme,
             bounds: Rectangle,
-            _cursor: Cursor
+            cursor: Cursor
         ) -> Vec<Geometry>{
             let mut frame = Frame::new(bounds.size());
     
-            let padding = 5.;
+            let padding = PADDING;
             let start = padding;
-            let width = bounds.width - (2. * padding);
+            let width = bounds.width - (2. * padding) - SCROLLBAR_WIDTH;
             let height = bounds.height - (2. * padding);
 
-            let line_height = 18.;
+            let line_height = LINE_HEIGHT;
+            let visible = visible_rows(height);
+            self.visible_rows.set(visible);
     
             frame.fill(&Path::rectangle(
                     Point {
@@ -1997,6 +4744,18 @@ mod tasks {
                 ),
                 Color::WHITE,
             );
+
+            // Where a dragged title would land.
+            if let (Some(Pending::Header { .. }), Some(position)) = (state.0, cursor.position_from(bounds.position())) {
+                let target = self.column_at((position.x - start) / width);
+                let widths = self.widths();
+                let left: f32 = widths[..target].iter().sum();
+                frame.fill_rectangle(
+                    Point::new(start + left * width, start),
+                    Size::new(widths[target] * width, line_height),
+                    FREE_COLOR,
+                );
+            }
     
             for sep in &self.separators {
                 frame.stroke(&Path::line(
@@ -2027,7 +4786,7 @@ mod tasks {
 
             let write: Vec<_> = std::iter::once(&0.).chain(self.separators.iter()).enumerate().map(|(i, &sep)| {
                 let sp2 = *self.separators.get(i).unwrap_or(&1.);
-                let max_str_size = std::cmp::max(((sp2 - sep)*width/6.0) as usize, 2) - 2;
+                let max_str_size = std::cmp::max(((sp2 - sep)*width/CHAR_WIDTH) as usize, 2) - 2;
                 move |y, mut str: String| {
                     if str.len() > max_str_size {
                         str.truncate(max_str_size);
@@ -2042,30 +4801,159 @@ mod tasks {
                 }
             }).collect();
             
-            frame.fill_text(write[0](start, String::from("PID")));
-            frame.fill_text(write[1](start, String::from("Nice")));
-            frame.fill_text(write[2](start, String::from("Status")));
-            frame.fill_text(write[3](start, String::from("User")));
-            frame.fill_text(write[4](start, String::from("CPU%")));
-            frame.fill_text(write[5](start, String::from("MEM%")));
-            frame.fill_text(write[6](start, String::from("Command")));
-            for (i, info) in self.process_info.iter().enumerate() {
+            for (write, column) in write.iter().zip(&self.columns) {
+                frame.fill_text(write(start, String::from(column.title())));
+            }
+            for (i, row) in self.rows.iter().skip(self.scroll).take(visible).enumerate() {
+                let info = &self.process_info[row.index];
                 let y = start + ((i + 1) as f32) * line_height;
-                if y > start + height - line_height {
-                    break;
+                if self.selected == Some(info.pid) {
+                    frame.fill_rectangle(
+                        Point::new(start, y),
+                        Size::new(width, line_height),
+                        FREE_COLOR,
+                    );
                 }
-                frame.fill_text(write[0](y, format!("{}", info.pid)));
-                frame.fill_text(write[1](y, info.nice.to_string()));
-                frame.fill_text(write[2](y, info.status.to_string()));
-                frame.fill_text(write[3](y, info.user.to_string()));
-                frame.fill_text(write[4](y, format!("{}", info.cpu)));
-                frame.fill_text(write[5](y, format!("{}", info.memory)));
-                frame.fill_text(write[6](y, info.cmd.to_string()));
+                let toggle = match (self.tree, row.children, row.collapsed) {
+                    (false, _, _) => "",
+                    (true, 0, _) => "    ",
+                    (true, _, true) => "[+] ",
+                    (true, _, false) => "[-] ",
+                };
+                for (write, &column) in write.iter().zip(&self.columns) {
+                    let cell = if column == Column::Cmd {
+                        format!("{}{}{}", "  ".repeat(row.depth), toggle, info.cmd)
+                    } else {
+                        column.text(info, row)
+                    };
+                    frame.fill_text(write(y, cell));
+                }
+            }
+
+            if let Some((top, thumb_height)) = self.thumb(visible, start + line_height, height - line_height) {
+                frame.fill_rectangle(
+                    Point::new(start + width, start + line_height),
+                    Size::new(SCROLLBAR_WIDTH, height - line_height),
+                    Color::from_rgb8(0xEE, 0xEE, 0xEE),
+                );
+                frame.fill_rectangle(
+                    Point::new(start + width + 2., top),
+                    Size::new(SCROLLBAR_WIDTH - 4., thumb_height),
+                    Color::from_rgb8(0x99, 0x99, 0x99),
+                );
             }
     
             vec![frame.into_geometry()]
         }
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn process(pid: usize, ppid: usize, cpu: f64) -> ProcessInfo {
+            ProcessInfo { pid, ppid, cpu, memory: 1.0, ..Default::default() }
+        }
+
+        #[test]
+        fn nests_and_sorts_siblings() {
+            let mut chart = TasksListChart::new();
+            chart.item_sort = Column::Cpu;
+            chart.tree = true;
+            // 30 is an orphan whose parent isn't listed.
+            chart.set_data(&[process(1, 0, 0.0), process(10, 1, 1.0), process(11, 1, 5.0), process(12, 11, 2.0), process(30, 99, 3.0)]);
+            let layout: Vec<(usize, usize)> = chart.rows.iter().map(|row| (chart.process_info[row.index].pid, row.depth)).collect();
+            assert_eq!(layout, vec![(30, 0), (1, 0), (11, 1), (12, 2), (10, 1)]);
+
+            chart.toggle_collapsed(1);
+            assert_eq!(chart.rows.len(), 2);
+            let init = chart.rows[1];
+            assert!(init.collapsed);
+            assert_eq!((init.cpu, init.memory), (8.0, 4.0));
+        }
+
+        #[test]
+        fn filter_survives_refreshes_and_keeps_ancestors() {
+            let mut chart = TasksListChart::new();
+            chart.set_filter(Filter::parse("cpu >= 2").unwrap());
+            chart.set_data(&[process(1, 0, 0.0), process(10, 1, 1.0), process(11, 1, 5.0), process(12, 11, 2.0)]);
+            assert_eq!((chart.matching, chart.rows.len()), (2, 2));
+            chart.set_data(&[process(1, 0, 0.0), process(11, 1, 0.5), process(12, 11, 2.0)]);
+            assert_eq!((chart.matching, chart.rows.len()), (1, 1));
+
+            chart.set_tree(true);
+            let pids: Vec<usize> = chart.rows.iter().map(|row| chart.process_info[row.index].pid).collect();
+            assert_eq!(pids, vec![1, 11, 12]);
+            assert_eq!(chart.matching, 1);
+        }
+
+        #[test]
+        fn columns_can_be_added_moved_and_sorted_by() {
+            let mut chart = TasksListChart::new();
+            chart.set_column(Column::Rss, true);
+            assert_eq!(chart.columns[6..], [Column::Rss, Column::Cmd]);
+            assert_eq!(chart.separators.len(), 7);
+            // Everything shrinks by the same factor, 12/13.
+            assert!((chart.widths()[6] - 1. / 13.).abs() < 1e-6);
+
+            chart.move_column(6, 0);
+            assert_eq!(chart.columns[..2], [Column::Rss, Column::Pid]);
+            assert!((chart.widths()[7] - 6. / 13.).abs() < 1e-6);
+            assert_eq!(chart.column_at(0.5), 6);
+
+            chart.set_data(&[ProcessInfo { pid: 1, rss: 10, ..Default::default() }, ProcessInfo { pid: 2, rss: 20, ..Default::default() }]);
+            chart.sort_by(Column::Rss);
+            assert_eq!(chart.process_info[0].pid, 2);
+
+            chart.set_column(Column::Rss, false);
+            assert_eq!(chart.columns, Column::DEFAULT);
+            assert!((chart.separators[0] - 1. / 12.).abs() < 1e-6);
+        }
+
+        #[test]
+        fn selection_follows_the_pid_and_stays_in_view() {
+            let mut chart = TasksListChart::new();
+            chart.visible_rows.set(3);
+            chart.item_sort = Column::Pid;
+            chart.rev = false;
+            chart.set_data(&(1..=10).map(|pid| process(pid, 0, pid as f64)).collect::<Vec<_>>());
+            assert_eq!(chart.move_selection(Move::Down), Some(1));
+            assert_eq!(chart.move_selection(Move::PageDown), Some(4));
+            assert_eq!(chart.scroll, 1);
+            assert_eq!(chart.move_selection(Move::End), Some(10));
+            assert_eq!(chart.scroll, 7);
+            chart.scroll_to(100);
+            assert_eq!(chart.scroll, 7);
+
+            // Sorting by CPU, highest first, brings PID 10 back to the top.
+            chart.sort_by(Column::Cpu);
+            assert_eq!((chart.selected, chart.scroll), (Some(10), 0));
+            assert_eq!(chart.move_selection(Move::Down), Some(9));
+
+            // Fewer processes after a refresh clamp the scroll.
+            chart.scroll_to(7);
+            chart.set_data(&(1..=4).map(|pid| process(pid, 0, pid as f64)).collect::<Vec<_>>());
+            assert_eq!(chart.scroll, 1);
+            assert_eq!(chart.move_selection(Move::Up), Some(4));
+        }
+
+        #[test]
+        fn selecting_a_hidden_child_expands_its_ancestors() {
+            let mut chart = TasksListChart::new();
+            chart.visible_rows.set(2);
+            chart.tree = true;
+            chart.set_data(&[process(1, 0, 0.0), process(10, 1, 1.0), process(11, 1, 5.0), process(12, 11, 2.0), process(30, 99, 3.0)]);
+            chart.toggle_collapsed(11);
+            chart.toggle_collapsed(1);
+            assert_eq!(chart.rows.len(), 2);
+
+            chart.select(12);
+            assert!(chart.collapsed.is_empty());
+            // 30, 1, 11, 12, 10 by CPU, highest first.
+            assert_eq!(chart.selected_row(), Some(3));
+            assert_eq!(chart.scroll, 2);
+        }
+    }
 }
 
 mod style {
@@ -2103,6 +4991,16 @@ mod style {
         }
     }
 
+    pub fn banner(theme: &Theme) -> container::Appearance {
+        let pallete = theme.extended_palette();
+
+        container::Appearance {
+            text_color: Some(pallete.danger.strong.text),
+            background: Some(pallete.danger.strong.color.into()),
+            ..Default::default()
+        }
+    }
+
     pub fn pane_focused(theme: &Theme) -> container::Appearance {
         let pallete = theme.extended_palette();
 
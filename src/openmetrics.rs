@@ -0,0 +1,261 @@
+// A small HTTP server exposing the latest snapshot in the OpenMetrics text
+// format, for Prometheus to scrape.
+
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::CollectedData;
+
+const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Escapes a label value as the exposition format requires.
+fn escape(value: &str) -> String {
+    value.replace('\\', r"\\").replace('"', "\\\"").replace('\n', r"\n")
+}
+
+/// Writes one metric family: its metadata, then a sample per label set.
+struct Family<'a> {
+    out: &'a mut String,
+    name: &'a str,
+}
+
+impl<'a> Family<'a> {
+    fn new(out: &'a mut String, name: &'a str, unit: Option<&str>, help: &str) -> Self {
+        let _ = writeln!(out, "# TYPE {} gauge", name);
+        if let Some(unit) = unit {
+            let _ = writeln!(out, "# UNIT {} {}", name, unit);
+        }
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        Self { out, name }
+    }
+
+    fn sample(&mut self, labels: &[(&str, &str)], value: f64) {
+        let labels: Vec<String> = labels
+            .iter()
+            .map(|(name, value)| format!("{}=\"{}\"", name, escape(value)))
+            .collect();
+        if labels.is_empty() {
+            let _ = writeln!(self.out, "{} {}", self.name, value);
+        } else {
+            let _ = writeln!(self.out, "{}{{{}}} {}", self.name, labels.join(","), value);
+        }
+    }
+}
+
+/// Renders the snapshot as an OpenMetrics exposition.
+pub fn render(data: &CollectedData) -> String {
+    let mut out = String::new();
+
+    let mut family = Family::new(&mut out, "dashboard_cpu_usage_percent", Some("percent"), "Share of a core's time in each mode since the previous sample.");
+    for history in &data.cpu_usage {
+        if let Some(sample) = history.front() {
+            let core = sample.id.to_string();
+            family.sample(&[("cpu", &core), ("mode", "total")], sample.total);
+            for (mode, value) in sample.parts() {
+                family.sample(&[("cpu", &core), ("mode", mode)], value);
+            }
+        }
+    }
+
+    // Without cpufreq there is nothing to report, so leave the family out.
+    let frequencies: Vec<_> = data
+        .cpu_usage
+        .iter()
+        .filter_map(|history| history.front())
+        .filter_map(|sample| Some((sample.id, sample.frequency?)))
+        .collect();
+    if !frequencies.is_empty() {
+        let mut family = Family::new(&mut out, "dashboard_cpu_frequency_hertz", Some("hertz"), "Current clock of a core, where cpufreq reports it.");
+        for (core, mhz) in frequencies {
+            family.sample(&[("cpu", &core.to_string())], mhz * 1e6);
+        }
+    }
+
+    let memory = &data.memory;
+    let mut family = Family::new(&mut out, "dashboard_memory_bytes", Some("bytes"), "Physical memory by use, as free reports it.");
+    for (kind, value) in [
+        ("total", memory.total),
+        ("used", memory.used()),
+        ("free", memory.free),
+        ("available", memory.available),
+        ("buff_cache", memory.buff_cache()),
+    ] {
+        family.sample(&[("kind", kind)], value as f64);
+    }
+    let mut family = Family::new(&mut out, "dashboard_swap_bytes", Some("bytes"), "Swap space by use.");
+    family.sample(&[("kind", "total")], memory.swap_total as f64);
+    family.sample(&[("kind", "used")], memory.swap_used() as f64);
+
+    let mut family = Family::new(&mut out, "dashboard_partition_bytes", Some("bytes"), "Filesystem space by use.");
+    for partition in &data.partitions {
+        for (kind, value) in [
+            ("total", partition.total),
+            ("used", partition.used()),
+            ("available", partition.available),
+        ] {
+            family.sample(
+                &[
+                    ("device", &partition.device),
+                    ("mountpoint", &partition.mount_point),
+                    ("fstype", &partition.fs_type),
+                    ("kind", kind),
+                ],
+                value as f64,
+            );
+        }
+    }
+    let mut family = Family::new(&mut out, "dashboard_partition_inodes", None, "Filesystem inodes by use.");
+    for partition in &data.partitions {
+        for (kind, value) in [("total", partition.inodes_total), ("used", partition.inodes_used())] {
+            family.sample(&[("mountpoint", &partition.mount_point), ("kind", kind)], value as f64);
+        }
+    }
+
+    // Only the program name, the full command line makes for huge labels.
+    let program = |cmd: &str| cmd.split_whitespace().next().unwrap_or_default().to_string();
+    let mut family = Family::new(&mut out, "dashboard_process_cpu_percent", Some("percent"), "Share of one core used by the process.");
+    for process in &data.process_list {
+        let pid = process.pid.to_string();
+        family.sample(&[("pid", &pid), ("user", &process.user), ("command", &program(&process.cmd))], process.cpu);
+    }
+    let mut family = Family::new(&mut out, "dashboard_process_memory_percent", Some("percent"), "Resident memory of the process against the total.");
+    for process in &data.process_list {
+        let pid = process.pid.to_string();
+        family.sample(&[("pid", &pid), ("user", &process.user), ("command", &program(&process.cmd))], process.memory);
+    }
+
+    let mut names: Vec<&String> = data.health.keys().collect();
+    names.sort();
+    let mut family = Family::new(&mut out, "dashboard_collector_up", None, "Whether the collector's last run succeeded.");
+    for name in &names {
+        family.sample(&[("collector", name)], if data.health[*name].is_failing() { 0.0 } else { 1.0 });
+    }
+    let mut family = Family::new(&mut out, "dashboard_collector_consecutive_failures", None, "Failed runs since the last successful one.");
+    for name in &names {
+        family.sample(&[("collector", name)], data.health[*name].consecutive_failures as f64);
+    }
+
+    out.push_str("# EOF\n");
+    out
+}
+
+fn respond(mut stream: TcpStream, shared_data: &Mutex<CollectedData>) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Skip the headers, nothing in them changes the response.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (status, content_type, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => {
+            let data = shared_data.lock().unwrap_or_else(PoisonError::into_inner);
+            ("200 OK", CONTENT_TYPE, render(&data))
+        }
+        (Some("GET"), _) => ("404 Not Found", "text/plain", String::from("try /metrics\n")),
+        _ => ("405 Method Not Allowed", "text/plain", String::from("only GET is supported\n")),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )
+}
+
+/// Serves `/metrics` on its own thread, one connection at a time.
+pub fn spawn(listener: TcpListener, shared_data: Arc<Mutex<CollectedData>>) -> JoinHandle<()> {
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let result = stream.and_then(|stream| respond(stream, &shared_data));
+            if let Err(error) = result {
+                eprintln!("warning: metrics request failed: {}", error);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collectors::cpu::CpuSample;
+    use crate::collectors::memory::MemoryStats;
+    use crate::collectors::CollectorHealth;
+    use crate::ProcessInfo;
+    use std::io::Read;
+
+    fn data() -> CollectedData {
+        CollectedData {
+            cpu_usage: vec![[CpuSample { total: 25.0, user: 20.0, system: 5.0, frequency: Some(2400.0), ..Default::default() }].into()],
+            memory: MemoryStats { total: 1000, free: 400, ..Default::default() },
+            process_list: vec![ProcessInfo {
+                pid: 7,
+                user: String::from("postgres"),
+                cmd: String::from("/usr/bin/pg \"main\""),
+                cpu: 1.5,
+                ..Default::default()
+            }],
+            health: [(String::from("cpu"), CollectorHealth { consecutive_failures: 2, ..Default::default() })].into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn renders_families_and_samples() {
+        let text = render(&data());
+        assert!(text.contains("# TYPE dashboard_cpu_usage_percent gauge\n# UNIT dashboard_cpu_usage_percent percent\n"));
+        assert!(text.contains("dashboard_cpu_usage_percent{cpu=\"0\",mode=\"total\"} 25\n"));
+        assert!(text.contains("dashboard_cpu_usage_percent{cpu=\"0\",mode=\"system\"} 5\n"));
+        assert!(text.contains("dashboard_cpu_frequency_hertz{cpu=\"0\"} 2400000000\n"));
+        assert!(text.contains("dashboard_memory_bytes{kind=\"used\"} 600\n"));
+        assert!(text.contains("dashboard_process_cpu_percent{pid=\"7\",user=\"postgres\",command=\"/usr/bin/pg\"} 1.5\n"));
+        assert!(text.contains("dashboard_collector_up{collector=\"cpu\"} 0\n"));
+        assert!(text.ends_with("# EOF\n"));
+    }
+
+    #[test]
+    fn labels_cores_by_id_and_leaves_out_missing_frequencies() {
+        let data = CollectedData {
+            cpu_usage: vec![[CpuSample { id: 3, total: 25.0, ..Default::default() }].into()],
+            ..Default::default()
+        };
+        let text = render(&data);
+        assert!(text.contains("dashboard_cpu_usage_percent{cpu=\"3\",mode=\"total\"} 25\n"));
+        assert!(!text.contains("dashboard_cpu_frequency_hertz"));
+    }
+
+    #[test]
+    fn escapes_label_values() {
+        assert_eq!(escape("a\"b\\c\nd"), r#"a\"b\\c\nd"#);
+    }
+
+    #[test]
+    fn serves_metrics_over_http() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        spawn(listener, Arc::new(Mutex::new(data())));
+
+        let get = |path: &str| {
+            let mut stream = TcpStream::connect(address).unwrap();
+            write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+        let response = get("/metrics");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains(CONTENT_TYPE));
+        assert!(response.ends_with("# EOF\n"));
+        assert!(get("/").starts_with("HTTP/1.1 404"));
+    }
+}
@@ -0,0 +1,458 @@
+// Sessions saved as JSON lines: a header with what doesn't change while
+// running, then one frame after every collection pass with what that pass
+// collected.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::ops::ControlFlow;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::collectors::cpu::{self, CpuFrequencyPolicy, CpuSample};
+use crate::collectors::diskio::BlockDevice;
+use crate::collectors::disks::PartitionUsage;
+use crate::collectors::dmi::MemoryDevice;
+use crate::collectors::memory::MemoryStats;
+use crate::collectors::network::NetworkInterface;
+use crate::collectors::sensors::Sensor;
+use crate::collectors::sockets::SocketInfo;
+use crate::collectors::sysinfo::SystemInfo;
+use crate::collectors::{merge_samples, CollectorHealth};
+use crate::scheduler::Sink;
+use crate::{CollectedData, ProcessInfo, MAX_POINTS};
+
+const FORMAT_VERSION: u32 = 2;
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+    version: u32,
+    system_info: SystemInfo,
+    memory_devices: Vec<MemoryDevice>,
+}
+
+/// What one collection pass changed: the fields of the collectors that
+/// didn't run are left out. Borrowed when recording, owned when loaded.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Frame<'a> {
+    /// Milliseconds since the Unix epoch.
+    pub time: u64,
+    /// Newest sample of each core; the history is rebuilt from earlier
+    /// frames, as for the devices below.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu: Option<Vec<CpuSample>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_policies: Option<Cow<'a, [Option<CpuFrequencyPolicy>]>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory: Option<MemoryStats>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub partitions: Option<Cow<'a, [PartitionUsage]>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_devices: Option<Cow<'a, [BlockDevice]>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network: Option<Cow<'a, [NetworkInterface]>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sockets: Option<Cow<'a, [SocketInfo]>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sensors: Option<Cow<'a, [Sensor]>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub process_list: Option<Cow<'a, [ProcessInfo]>>,
+    /// Of the collectors that ran.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub health: HashMap<String, CollectorHealth>,
+}
+
+impl<'a> Frame<'a> {
+    /// Borrows what the collectors named in `collected` produced.
+    fn new(collected: &[&'static str], data: &'a CollectedData) -> Self {
+        let ran = |name| collected.contains(&name);
+        Frame {
+            time: millis_since_epoch(SystemTime::now()),
+            cpu: ran("cpu").then(|| data.cpu_usage.iter().filter_map(|history| history.front().copied()).collect()),
+            cpu_policies: ran("cpu").then_some(Cow::Borrowed(&data.cpu_policies[..])),
+            memory: ran("memory").then_some(data.memory),
+            partitions: ran("disks").then_some(Cow::Borrowed(&data.partitions[..])),
+            block_devices: ran("diskio").then_some(Cow::Borrowed(&data.block_devices[..])),
+            network: ran("network").then_some(Cow::Borrowed(&data.network[..])),
+            sockets: ran("sockets").then_some(Cow::Borrowed(&data.sockets[..])),
+            sensors: ran("sensors").then_some(Cow::Borrowed(&data.sensors[..])),
+            process_list: ran("tasks").then_some(Cow::Borrowed(&data.process_list[..])),
+            health: data
+                .health
+                .iter()
+                .filter(|(name, _)| ran(name.as_str()))
+                .map(|(name, health)| (name.clone(), health.clone()))
+                .collect(),
+        }
+    }
+
+    /// Whether it has each charted series, in the order `apply` counts them.
+    fn series(&self) -> [bool; 4] {
+        [self.cpu.is_some(), self.block_devices.is_some(), self.network.is_some(), self.sensors.is_some()]
+    }
+
+    /// Whether it has each of the other fields.
+    fn snapshots(&self) -> [bool; 6] {
+        [
+            self.cpu_policies.is_some(),
+            self.memory.is_some(),
+            self.partitions.is_some(),
+            self.sockets.is_some(),
+            self.process_list.is_some(),
+            !self.health.is_empty(),
+        ]
+    }
+}
+
+pub fn millis_since_epoch(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+}
+
+/// Appends a frame to a recording file after every collection pass.
+pub struct Recorder {
+    writer: BufWriter<File>,
+}
+
+impl Recorder {
+    pub fn create(path: impl AsRef<Path>, data: &CollectedData) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        let header = Header {
+            version: FORMAT_VERSION,
+            system_info: data.system_info.clone(),
+            memory_devices: data.memory_devices.clone(),
+        };
+        serde_json::to_writer(&mut writer, &header)?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+        Ok(Self { writer })
+    }
+
+    /// `collected` names the collectors that ran since the last frame.
+    pub fn record(&mut self, collected: &[&'static str], data: &CollectedData) -> io::Result<()> {
+        serde_json::to_writer(&mut self.writer, &Frame::new(collected, data))?;
+        self.writer.write_all(b"\n")?;
+        // Flush every frame so the file is usable if we are killed.
+        self.writer.flush()
+    }
+}
+
+impl Sink for Recorder {
+    fn name(&self) -> &'static str {
+        "recording"
+    }
+
+    fn write(&mut self, collected: &[&'static str], data: &CollectedData) -> io::Result<ControlFlow<()>> {
+        self.record(collected, data)?;
+        Ok(ControlFlow::Continue(()))
+    }
+}
+
+/// A recording loaded in memory for replay.
+pub struct Recording {
+    pub system_info: SystemInfo,
+    pub memory_devices: Vec<MemoryDevice>,
+    pub frames: Vec<Frame<'static>>,
+}
+
+impl Recording {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|e| format!("cannot open {}: {}", path.display(), e))?;
+        let mut lines = BufReader::new(file).lines();
+        let line_error = |number: usize, error: &dyn std::fmt::Display| {
+            format!("{}:{}: {}", path.display(), number, error)
+        };
+
+        let header = lines
+            .next()
+            .ok_or_else(|| format!("{} is empty", path.display()))?
+            .map_err(|e| line_error(1, &e))?;
+        let header: Header = serde_json::from_str(&header).map_err(|e| line_error(1, &e))?;
+        if header.version != FORMAT_VERSION {
+            return Err(format!(
+                "{} is a version {} recording, expected version {}",
+                path.display(),
+                header.version,
+                FORMAT_VERSION
+            ));
+        }
+
+        let mut frames = vec![];
+        for (index, line) in lines.enumerate() {
+            let line = line.map_err(|e| line_error(index + 2, &e))?;
+            // A recording cut short ends with a partial line; keep what came before.
+            match serde_json::from_str(&line) {
+                Ok(frame) => frames.push(frame),
+                Err(e) if e.is_eof() => break,
+                Err(e) => return Err(line_error(index + 2, &e)),
+            }
+        }
+        if frames.is_empty() {
+            return Err(format!("{} has no frames", path.display()));
+        }
+
+        Ok(Self {
+            system_info: header.system_info,
+            memory_devices: header.memory_devices,
+            frames,
+        })
+    }
+}
+
+/// Feeds a recording into the UI in place of the live collectors.
+pub struct Player {
+    recording: Recording,
+    position: usize,
+    playing: bool,
+    speed: f64,
+    /// Playback position in recording time, milliseconds since the epoch.
+    clock: f64,
+    last_advance: Instant,
+}
+
+impl Player {
+    pub fn new(recording: Recording) -> Self {
+        let clock = recording.frames[0].time as f64;
+        Self {
+            recording,
+            position: 0,
+            playing: true,
+            speed: 1.0,
+            clock,
+            last_advance: Instant::now(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.recording.frames.len()
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    /// Recording time of the playback position.
+    pub fn time(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_millis(self.clock as u64)
+    }
+
+    pub fn toggle(&mut self) {
+        if !self.playing && self.position + 1 == self.len() {
+            self.seek(0);
+        }
+        self.playing = !self.playing;
+        self.last_advance = Instant::now();
+    }
+
+    pub fn set_speed(&mut self, speed: f64) {
+        self.speed = speed;
+    }
+
+    pub fn seek(&mut self, position: usize) {
+        self.position = position.min(self.len() - 1);
+        self.clock = self.recording.frames[self.position].time as f64;
+    }
+
+    /// Moves the playback clock by the wall time elapsed since the last call,
+    /// scaled by the speed. Returns true when a new frame is due.
+    pub fn advance(&mut self) -> bool {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_advance);
+        self.last_advance = now;
+        if !self.playing {
+            return false;
+        }
+
+        self.clock += elapsed.as_secs_f64() * 1000.0 * self.speed;
+        let frames = &self.recording.frames;
+        let mut position = self.position;
+        while position + 1 < frames.len() && frames[position + 1].time as f64 <= self.clock {
+            position += 1;
+        }
+        if position + 1 == frames.len() {
+            self.playing = false;
+            self.clock = self.clock.min(frames[position].time as f64);
+        }
+        let changed = position != self.position;
+        self.position = position;
+        changed
+    }
+
+    /// Writes what was known at the playback position into `data`: the
+    /// newest value of each field and the charted histories, going back as
+    /// many frames as it takes to fill them.
+    pub fn apply(&self, data: &mut CollectedData) {
+        let frames = &self.recording.frames[..=self.position];
+        let mut series = [0; 4];
+        let mut snapshots = [false; 6];
+        let mut first = self.position;
+        loop {
+            let frame = &frames[first];
+            for (count, has) in series.iter_mut().zip(frame.series()) {
+                *count += usize::from(has);
+            }
+            for (seen, has) in snapshots.iter_mut().zip(frame.snapshots()) {
+                *seen |= has;
+            }
+            if first == 0 || (series.iter().all(|&count| count >= MAX_POINTS) && snapshots.iter().all(|&seen| seen)) {
+                break;
+            }
+            first -= 1;
+        }
+
+        data.cpu_usage.clear();
+        data.block_devices.clear();
+        data.network.clear();
+        data.sensors.clear();
+        data.health.clear();
+        for frame in &frames[first..] {
+            if let Some(cpu) = &frame.cpu {
+                cpu::push_samples(&mut data.cpu_usage, cpu.clone());
+            }
+            if let Some(policies) = &frame.cpu_policies {
+                data.cpu_policies = policies.to_vec();
+            }
+            if let Some(memory) = frame.memory {
+                data.memory = memory;
+            }
+            if let Some(partitions) = &frame.partitions {
+                data.partitions = partitions.to_vec();
+            }
+            if let Some(block_devices) = &frame.block_devices {
+                merge_samples(&mut data.block_devices, block_devices.to_vec());
+            }
+            if let Some(network) = &frame.network {
+                merge_samples(&mut data.network, network.to_vec());
+            }
+            if let Some(sockets) = &frame.sockets {
+                data.sockets = sockets.to_vec();
+            }
+            if let Some(sensors) = &frame.sensors {
+                merge_samples(&mut data.sensors, sensors.to_vec());
+            }
+            if let Some(process_list) = &frame.process_list {
+                data.process_list = process_list.to_vec();
+            }
+            data.health.extend(frame.health.clone());
+        }
+
+        data.updated_tasks = true;
+        data.updated_memory = true;
+        data.updated_cpu = true;
+        data.updated_disks = true;
+        data.updated_diskio = true;
+        data.updated_network = true;
+        data.updated_sockets = true;
+        data.updated_sensors = true;
+        data.tick += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collectors::memory::MemoryStats;
+    use std::fs;
+
+    fn sample(total: f64) -> CpuSample {
+        CpuSample { total, user: total, ..Default::default() }
+    }
+
+    #[test]
+    fn replays_what_was_recorded() {
+        let path = std::env::temp_dir().join(format!("recording-{}.jsonl", std::process::id()));
+        let mut data = CollectedData {
+            system_info: SystemInfo { hostname: String::from("db1"), ..Default::default() },
+            ..Default::default()
+        };
+        let mut recorder = Recorder::create(&path, &data).unwrap();
+        for i in 1..=3 {
+            cpu::push_samples(&mut data.cpu_usage, vec![sample(i as f64 * 10.0)]);
+            data.memory = MemoryStats { total: 1000, free: 1000 - i * 100, ..Default::default() };
+            recorder.record(&["cpu", "memory"], &data).unwrap();
+        }
+        // Memory alone doesn't repeat the last CPU sample.
+        data.memory.free = 500;
+        recorder.record(&["memory"], &data).unwrap();
+        drop(recorder);
+
+        let recording = Recording::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(recording.system_info.hostname, "db1");
+        assert_eq!(recording.frames.len(), 4);
+
+        let mut player = Player::new(recording);
+        let mut replayed = CollectedData::default();
+        player.seek(1);
+        player.apply(&mut replayed);
+        assert_eq!(replayed.memory.free, 800);
+        let history: Vec<f64> = replayed.cpu_usage[0].iter().map(|s| s.total).collect();
+        assert_eq!(history, vec![20.0, 10.0]);
+        assert!(replayed.updated_cpu);
+
+        player.seek(10);
+        assert_eq!(player.position(), 3);
+        player.apply(&mut replayed);
+        assert_eq!(replayed.memory.free, 500);
+        let history: Vec<f64> = replayed.cpu_usage[0].iter().map(|s| s.total).collect();
+        assert_eq!(history, vec![30.0, 20.0, 10.0]);
+    }
+
+    #[test]
+    fn frames_keep_only_what_ran_and_replay_full_histories() {
+        let path = std::env::temp_dir().join(format!("sparse-{}.jsonl", std::process::id()));
+        let mut data = CollectedData::default();
+        let mut recorder = Recorder::create(&path, &data).unwrap();
+        for i in 0..MAX_POINTS + 5 {
+            cpu::push_samples(&mut data.cpu_usage, vec![sample(i as f64)]);
+            data.memory.free = i as u64;
+            recorder.record(&["cpu", "memory"], &data).unwrap();
+            // The task list runs more often than the charted collectors.
+            for _ in 0..3 {
+                recorder.record(&["tasks"], &data).unwrap();
+            }
+        }
+        drop(recorder);
+
+        let content = fs::read_to_string(&path).unwrap();
+        let last = content.lines().last().unwrap();
+        assert!(last.contains("process_list") && !last.contains("memory") && !last.contains("cpu"));
+
+        let recording = Recording::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let mut player = Player::new(recording);
+        player.seek(usize::MAX);
+        let mut replayed = CollectedData::default();
+        player.apply(&mut replayed);
+        assert_eq!(replayed.cpu_usage[0].len(), MAX_POINTS);
+        assert_eq!(replayed.cpu_usage[0][0].total, (MAX_POINTS + 4) as f64);
+        assert_eq!(replayed.memory.free, (MAX_POINTS + 4) as u64);
+    }
+
+    #[test]
+    fn truncated_last_line_is_ignored() {
+        let path = std::env::temp_dir().join(format!("truncated-{}.jsonl", std::process::id()));
+        let data = CollectedData::default();
+        let mut recorder = Recorder::create(&path, &data).unwrap();
+        recorder.record(&[], &data).unwrap();
+        drop(recorder);
+        let mut content = fs::read_to_string(&path).unwrap();
+        content.push_str("{\"time\":12");
+        fs::write(&path, content).unwrap();
+
+        let recording = Recording::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(recording.unwrap().frames.len(), 1);
+    }
+}
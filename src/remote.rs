@@ -0,0 +1,550 @@
+// Agent/viewer split: an agent runs the collectors and streams snapshots over
+// TCP, and the GUI connects to one or more agents instead of collecting
+// locally.
+//
+// Every message is a frame: a big-endian u32 length followed by that many
+// bytes of JSON. The viewer opens with a `ClientHello`; the agent answers with
+// a `Hello` (or an `Error` when the versions differ) and then sends a
+// `Snapshot` after every collection pass.
+
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::collectors::cpu::{self, CpuSample};
+use crate::collectors::dmi::MemoryDevice;
+use crate::collectors::merge_samples;
+use crate::collectors::sysinfo::SystemInfo;
+use crate::scheduler::Sink;
+use crate::CollectedData;
+
+const PROTOCOL: &str = "trabalho_so";
+pub const PROTOCOL_VERSION: u32 = 1;
+// Far above any real snapshot, only there to reject garbage lengths.
+const MAX_FRAME_LEN: u32 = 64 * 1024 * 1024;
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+// Agents send a snapshot every few seconds; silence this long means it's gone.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+// Snapshots waiting for a slow viewer; once full the viewer is dropped.
+const VIEWER_QUEUE: usize = 4;
+const MIN_RETRY: Duration = Duration::from_secs(1);
+const MAX_RETRY: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ClientHello {
+    protocol: String,
+    version: u32,
+}
+
+/// Messages from the agent. `D` is borrowed when sending and owned when
+/// receiving, so snapshots are serialized without a copy.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Frame<D> {
+    Hello {
+        protocol: String,
+        version: u32,
+        system_info: Box<SystemInfo>,
+        memory_devices: Vec<MemoryDevice>,
+    },
+    Error {
+        message: String,
+    },
+    Snapshot {
+        /// Newest sample of each core when the CPU collector ran since the
+        /// previous snapshot, empty otherwise; viewers keep the history.
+        cpu: Vec<CpuSample>,
+        data: D,
+    },
+}
+
+fn write_frame(writer: &mut impl Write, body: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(body.len())
+        .ok()
+        .filter(|&len| len <= MAX_FRAME_LEN)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "frame too large"))?;
+    writer.write_all(&len.to_be_bytes())?;
+    writer.write_all(body)?;
+    writer.flush()
+}
+
+fn read_frame(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut len = [0; 4];
+    reader.read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len);
+    if len > MAX_FRAME_LEN {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("frame of {} bytes is too large", len)));
+    }
+    let mut body = vec![0; len as usize];
+    reader.read_exact(&mut body)?;
+    Ok(body)
+}
+
+fn encode<T: Serialize>(value: &T) -> io::Result<Vec<u8>> {
+    serde_json::to_vec(value).map_err(io::Error::from)
+}
+
+fn decode<'a, T: Deserialize<'a>>(body: &'a [u8]) -> io::Result<T> {
+    serde_json::from_slice(body).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn snapshot(data: &CollectedData, with_cpu: bool) -> io::Result<Vec<u8>> {
+    let cpu = if with_cpu {
+        data.cpu_usage.iter().filter_map(|history| history.front().copied()).collect()
+    } else {
+        vec![]
+    };
+    encode(&Frame::Snapshot { cpu, data })
+}
+
+/// The snapshots waiting for one viewer's writer thread.
+type ViewerQueue = SyncSender<Arc<[u8]>>;
+
+/// Serves snapshots to every connected viewer.
+pub struct Agent {
+    clients: Arc<Mutex<Vec<ViewerQueue>>>,
+}
+
+impl Agent {
+    /// Accepts viewers on `listener` from a background thread.
+    pub fn spawn(listener: TcpListener, shared_data: Arc<Mutex<CollectedData>>) -> Self {
+        let clients = Arc::new(Mutex::new(vec![]));
+        let accepted = Arc::clone(&clients);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => spawn_viewer(stream, Arc::clone(&shared_data), Arc::clone(&accepted)),
+                    Err(error) => eprintln!("warning: cannot accept a viewer: {}", error),
+                }
+            }
+        });
+        Self { clients }
+    }
+}
+
+/// Greets a viewer and writes it the queued snapshots from its own thread, so
+/// a slow viewer holds up neither collection nor the other viewers. The
+/// thread ends when the viewer goes away or the agent drops its queue.
+fn spawn_viewer(mut stream: TcpStream, shared_data: Arc<Mutex<CollectedData>>, clients: Arc<Mutex<Vec<ViewerQueue>>>) {
+    thread::spawn(move || {
+        let receiver = match greet(&mut stream, &shared_data, &clients) {
+            Ok(receiver) => receiver,
+            Err(error) => {
+                eprintln!("warning: viewer rejected: {}", error);
+                return;
+            }
+        };
+        // Viewers give up on an agent after READ_TIMEOUT of silence anyway.
+        if stream.set_write_timeout(Some(READ_TIMEOUT)).is_err() {
+            return;
+        }
+        for frame in receiver {
+            if write_frame(&mut stream, &frame).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// Checks the viewer's hello, then sends ours and the latest snapshot and
+/// returns the queue of the snapshots after it.
+fn greet(
+    stream: &mut TcpStream,
+    shared_data: &Mutex<CollectedData>,
+    clients: &Mutex<Vec<ViewerQueue>>,
+) -> io::Result<Receiver<Arc<[u8]>>> {
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    stream.set_write_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let hello: ClientHello = decode(&read_frame(stream)?)?;
+    if hello.protocol != PROTOCOL || hello.version != PROTOCOL_VERSION {
+        let message = format!(
+            "agent speaks {} protocol version {}, viewer sent {} version {}",
+            PROTOCOL, PROTOCOL_VERSION, hello.protocol, hello.version
+        );
+        write_frame(stream, &encode(&Frame::<()>::Error { message: message.clone() })?)?;
+        return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+    }
+
+    let (sender, receiver) = mpsc::sync_channel(VIEWER_QUEUE);
+    let (hello, snapshot) = {
+        let data = shared_data.lock().unwrap_or_else(PoisonError::into_inner);
+        let hello = Frame::<()>::Hello {
+            protocol: PROTOCOL.to_string(),
+            version: PROTOCOL_VERSION,
+            system_info: Box::new(data.system_info.clone()),
+            memory_devices: data.memory_devices.clone(),
+        };
+        // Listed while the data is locked, so the queue starts with the pass
+        // after this snapshot.
+        clients.lock().unwrap_or_else(PoisonError::into_inner).push(sender);
+        (encode(&hello)?, snapshot(&data, true)?)
+    };
+    write_frame(stream, &hello)?;
+    write_frame(stream, &snapshot)?;
+    Ok(receiver)
+}
+
+impl Sink for Agent {
+    fn name(&self) -> &'static str {
+        "agent"
+    }
+
+    fn write(&mut self, collected: &[&'static str], data: &CollectedData) -> io::Result<ControlFlow<()>> {
+        // Repeating the newest CPU sample would stretch the viewers' history.
+        let frame: Arc<[u8]> = snapshot(data, collected.contains(&"cpu"))?.into();
+        // A viewer that went away or fell too far behind is dropped; it reconnects.
+        self.clients
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|queue| queue.try_send(Arc::clone(&frame)).is_ok());
+        Ok(ControlFlow::Continue(()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionState {
+    Connecting,
+    Connected,
+    Disconnected { error: String, retry_at: Instant },
+    /// The agent speaks another protocol version; retried slowly in case it
+    /// gets upgraded.
+    Incompatible { error: String, retry_at: Instant },
+}
+
+impl ConnectionState {
+    pub fn is_connected(&self) -> bool {
+        *self == ConnectionState::Connected
+    }
+}
+
+impl std::fmt::Display for ConnectionState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let retry = |retry_at: &Instant| retry_at.saturating_duration_since(Instant::now()).as_secs();
+        match self {
+            ConnectionState::Connecting => write!(f, "connecting"),
+            ConnectionState::Connected => write!(f, "connected"),
+            ConnectionState::Disconnected { error, retry_at } => {
+                write!(f, "disconnected ({}), retrying in {}s", error, retry(retry_at))
+            }
+            ConnectionState::Incompatible { error, retry_at } => {
+                write!(f, "incompatible ({}), retrying in {}s", error, retry(retry_at))
+            }
+        }
+    }
+}
+
+enum SessionError {
+    Io(io::Error),
+    Incompatible(String),
+}
+
+impl From<io::Error> for SessionError {
+    fn from(error: io::Error) -> Self {
+        SessionError::Io(error)
+    }
+}
+
+/// A viewer's link to one agent, kept up by a background thread.
+pub struct Connection {
+    address: String,
+    state: Arc<Mutex<ConnectionState>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl Connection {
+    /// Connects to `address` and keeps `shared_data` updated with what the
+    /// agent sends, reconnecting with a backoff when the link drops.
+    pub fn spawn(address: String, shared_data: Arc<Mutex<CollectedData>>, shared_tick: Arc<AtomicU64>) -> Self {
+        let state = Arc::new(Mutex::new(ConnectionState::Connecting));
+        let stop = Arc::new(AtomicBool::new(false));
+        let connection = Self {
+            address: address.clone(),
+            state: Arc::clone(&state),
+            stop: Arc::clone(&stop),
+        };
+
+        thread::spawn(move || {
+            let set_state = |new_state| *state.lock().unwrap_or_else(PoisonError::into_inner) = new_state;
+            let mut backoff = MIN_RETRY;
+            while !stop.load(Ordering::SeqCst) {
+                set_state(ConnectionState::Connecting);
+                let error = match run_session(&address, &shared_data, &shared_tick, &set_state, &stop) {
+                    Ok(()) => break,
+                    Err(error) => error,
+                };
+                let wait = match error {
+                    SessionError::Io(error) => {
+                        // A session that got through the handshake starts the backoff over.
+                        if state.lock().unwrap_or_else(PoisonError::into_inner).is_connected() {
+                            backoff = MIN_RETRY;
+                        }
+                        set_state(ConnectionState::Disconnected {
+                            error: error.to_string(),
+                            retry_at: Instant::now() + backoff,
+                        });
+                        let wait = backoff;
+                        backoff = (backoff * 2).min(MAX_RETRY);
+                        wait
+                    }
+                    SessionError::Incompatible(error) => {
+                        set_state(ConnectionState::Incompatible { error, retry_at: Instant::now() + MAX_RETRY });
+                        MAX_RETRY
+                    }
+                };
+                let until = Instant::now() + wait;
+                while Instant::now() < until && !stop.load(Ordering::SeqCst) {
+                    thread::sleep(Duration::from_millis(100));
+                }
+            }
+        });
+
+        connection
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    pub fn state(&self) -> ConnectionState {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Runs one connection until it fails or the viewer stops.
+fn run_session(
+    address: &str,
+    shared_data: &Mutex<CollectedData>,
+    shared_tick: &AtomicU64,
+    set_state: &dyn Fn(ConnectionState),
+    stop: &AtomicBool,
+) -> Result<(), SessionError> {
+    let socket_address = address
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "address resolved to nothing"))?;
+    let mut stream = TcpStream::connect_timeout(&socket_address, HANDSHAKE_TIMEOUT)?;
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    stream.set_write_timeout(Some(HANDSHAKE_TIMEOUT))?;
+
+    let hello = ClientHello {
+        protocol: PROTOCOL.to_string(),
+        version: PROTOCOL_VERSION,
+    };
+    write_frame(&mut stream, &encode(&hello)?)?;
+
+    // Check the version before parsing the rest, whose layout may differ.
+    let body = read_frame(&mut stream)?;
+    let value: serde_json::Value = decode(&body)?;
+    if value["type"] == "error" {
+        return Err(SessionError::Incompatible(value["message"].as_str().unwrap_or_default().to_string()));
+    }
+    if value["protocol"] != PROTOCOL || value["version"] != PROTOCOL_VERSION {
+        // Strings without their JSON quotes.
+        let plain = |value: &serde_json::Value| value.as_str().map_or_else(|| value.to_string(), str::to_string);
+        return Err(SessionError::Incompatible(format!(
+            "agent speaks {} protocol version {}, expected {} version {}",
+            plain(&value["protocol"]),
+            plain(&value["version"]),
+            PROTOCOL,
+            PROTOCOL_VERSION
+        )));
+    }
+    match decode::<Frame<CollectedData>>(&body)? {
+        Frame::Hello { system_info, memory_devices, .. } => {
+            let mut data = shared_data.lock().unwrap_or_else(PoisonError::into_inner);
+            data.system_info = *system_info;
+            data.memory_devices = memory_devices;
+            // The history from before a reconnect would have a gap in it.
+            data.cpu_usage.clear();
+            data.block_devices.clear();
+            data.network.clear();
+            data.sensors.clear();
+        }
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "expected a hello").into()),
+    }
+    set_state(ConnectionState::Connected);
+
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    while !stop.load(Ordering::SeqCst) {
+        match decode::<Frame<CollectedData>>(&read_frame(&mut stream)?)? {
+            Frame::Snapshot { cpu, data: snapshot } => {
+                let mut data = shared_data.lock().unwrap_or_else(PoisonError::into_inner);
+                cpu::push_samples(&mut data.cpu_usage, cpu);
+                data.cpu_policies = snapshot.cpu_policies;
+                data.memory = snapshot.memory;
+                data.partitions = snapshot.partitions;
+                merge_samples(&mut data.block_devices, snapshot.block_devices);
+                merge_samples(&mut data.network, snapshot.network);
+                data.sockets = snapshot.sockets;
+                merge_samples(&mut data.sensors, snapshot.sensors);
+                data.process_list = snapshot.process_list;
+                data.health = snapshot.health;
+                data.updated_tasks = true;
+                data.updated_memory = true;
+                data.updated_cpu = true;
+                data.updated_disks = true;
+                data.updated_diskio = true;
+                data.updated_network = true;
+                data.updated_sockets = true;
+                data.updated_sensors = true;
+                data.tick += 1;
+                drop(data);
+                shared_tick.fetch_add(1, Ordering::SeqCst);
+            }
+            Frame::Error { message } => return Err(io::Error::other(message).into()),
+            Frame::Hello { .. } => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collectors::memory::MemoryStats;
+
+    fn wait_for(condition: impl Fn() -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !condition() {
+            assert!(Instant::now() < deadline, "timed out");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn frames_round_trip() {
+        let mut buffer = vec![];
+        write_frame(&mut buffer, b"{}").unwrap();
+        write_frame(&mut buffer, b"[1]").unwrap();
+        assert_eq!(&buffer[..4], &[0, 0, 0, 2]);
+        let mut reader = &buffer[..];
+        assert_eq!(read_frame(&mut reader).unwrap(), b"{}");
+        assert_eq!(read_frame(&mut reader).unwrap(), b"[1]");
+        assert!(read_frame(&mut reader).is_err());
+        assert!(read_frame(&mut &[0xff, 0xff, 0xff, 0xff][..]).is_err());
+    }
+
+    #[test]
+    fn viewer_receives_the_agents_snapshots() {
+        let agent_data = Arc::new(Mutex::new(CollectedData {
+            system_info: SystemInfo { hostname: String::from("db1"), ..Default::default() },
+            memory: MemoryStats { total: 1000, ..Default::default() },
+            ..Default::default()
+        }));
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let mut agent = Agent::spawn(listener, Arc::clone(&agent_data));
+
+        let viewer_data = Arc::new(Mutex::new(CollectedData::default()));
+        let tick = Arc::new(AtomicU64::new(0));
+        let connection = Connection::spawn(address, Arc::clone(&viewer_data), Arc::clone(&tick));
+        wait_for(|| connection.state().is_connected() && tick.load(Ordering::SeqCst) == 1);
+        assert_eq!(viewer_data.lock().unwrap().system_info.hostname, "db1");
+        assert_eq!(viewer_data.lock().unwrap().memory.total, 1000);
+
+        let mut data = agent_data.lock().unwrap().clone();
+        data.memory.total = 2000;
+        data.cpu_usage = vec![[CpuSample { total: 50.0, ..Default::default() }].into()];
+        // The agent lists the viewer just after greeting it.
+        wait_for(|| agent.clients.lock().unwrap().len() == 1);
+        assert_eq!(agent.write(&["cpu", "memory"], &data).unwrap(), ControlFlow::Continue(()));
+        wait_for(|| tick.load(Ordering::SeqCst) == 2);
+        {
+            let viewer = viewer_data.lock().unwrap();
+            assert_eq!(viewer.memory.total, 2000);
+            assert_eq!(viewer.cpu_usage[0][0].total, 50.0);
+            assert!(viewer.updated_memory);
+        }
+
+        // A pass without the CPU collector doesn't repeat its sample.
+        data.memory.total = 3000;
+        assert_eq!(agent.write(&["memory"], &data).unwrap(), ControlFlow::Continue(()));
+        wait_for(|| tick.load(Ordering::SeqCst) == 3);
+        let viewer = viewer_data.lock().unwrap();
+        assert_eq!(viewer.memory.total, 3000);
+        assert_eq!(viewer.cpu_usage[0].len(), 1);
+    }
+
+    #[test]
+    fn agent_rejects_other_versions() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        Agent::spawn(listener, Arc::new(Mutex::new(CollectedData::default())));
+
+        let mut stream = TcpStream::connect(address).unwrap();
+        let hello = ClientHello { protocol: PROTOCOL.to_string(), version: PROTOCOL_VERSION + 1 };
+        write_frame(&mut stream, &encode(&hello).unwrap()).unwrap();
+        let reply: Frame<CollectedData> = decode(&read_frame(&mut stream).unwrap()).unwrap();
+        match reply {
+            Frame::Error { message } => assert!(message.contains("viewer sent trabalho_so version 2")),
+            _ => panic!("expected an error"),
+        }
+    }
+
+    #[test]
+    fn unreachable_agent_is_retried() {
+        // Bind and drop to get a port nothing listens on.
+        let address = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string();
+        let connection = Connection::spawn(
+            address,
+            Arc::new(Mutex::new(CollectedData::default())),
+            Arc::new(AtomicU64::new(0)),
+        );
+        wait_for(|| matches!(connection.state(), ConnectionState::Disconnected { .. }));
+        assert!(connection.state().to_string().starts_with("disconnected ("));
+    }
+
+    #[test]
+    fn a_silent_viewer_does_not_hold_up_the_others() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        Agent::spawn(listener, Arc::new(Mutex::new(CollectedData::default())));
+
+        // Connects but never says hello.
+        let _silent = TcpStream::connect(address).unwrap();
+        let tick = Arc::new(AtomicU64::new(0));
+        let connection = Connection::spawn(address.to_string(), Arc::new(Mutex::new(CollectedData::default())), Arc::clone(&tick));
+        let started = Instant::now();
+        wait_for(|| connection.state().is_connected());
+        assert!(started.elapsed() < HANDSHAKE_TIMEOUT);
+    }
+
+    #[test]
+    fn viewer_names_the_agents_version_plainly() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let _: ClientHello = decode(&read_frame(&mut stream).unwrap()).unwrap();
+            let hello = Frame::<()>::Hello {
+                protocol: PROTOCOL.to_string(),
+                version: PROTOCOL_VERSION + 1,
+                system_info: Box::default(),
+                memory_devices: vec![],
+            };
+            write_frame(&mut stream, &encode(&hello).unwrap()).unwrap();
+        });
+
+        let connection = Connection::spawn(address.to_string(), Arc::new(Mutex::new(CollectedData::default())), Arc::new(AtomicU64::new(0)));
+        wait_for(|| matches!(connection.state(), ConnectionState::Incompatible { .. }));
+        match connection.state() {
+            ConnectionState::Incompatible { error, .. } => {
+                assert_eq!(error, "agent speaks trabalho_so protocol version 2, expected trabalho_so version 1")
+            }
+            state => panic!("unexpected state {}", state),
+        }
+    }
+}
@@ -0,0 +1,409 @@
+use std::collections::HashMap;
+use std::io;
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::collectors::Collector;
+use crate::CollectedData;
+
+// How long to wait for a command when every collector is paused.
+const IDLE_WAIT: Duration = Duration::from_secs(3600);
+
+pub enum SchedulerCommand {
+    SetInterval(&'static str, Duration),
+    /// Runs only the named collectors and pauses the others.
+    SetActive(Vec<&'static str>),
+    Shutdown,
+}
+
+struct Entry {
+    collector: Box<dyn Collector>,
+    interval: Duration,
+    next_run: Instant,
+    paused: bool,
+}
+
+/// Gets the collected data after every collection pass, e.g. to save it.
+pub trait Sink: Send {
+    /// Used in error messages.
+    fn name(&self) -> &'static str;
+
+    /// `collected` names the collectors that ran in this pass. Breaking stops
+    /// the scheduler.
+    fn write(&mut self, collected: &[&'static str], data: &CollectedData) -> io::Result<ControlFlow<()>>;
+}
+
+/// Runs every registered collector on one thread, each at its own interval.
+pub struct Scheduler {
+    entries: Vec<Entry>,
+    shared_data: Arc<Mutex<CollectedData>>,
+    shared_tick: Arc<AtomicU64>,
+    sinks: Vec<Box<dyn Sink>>,
+    /// Set when something outside the UI needs every collector's data.
+    never_pause: bool,
+}
+
+impl Scheduler {
+    pub fn new(shared_data: Arc<Mutex<CollectedData>>, shared_tick: Arc<AtomicU64>) -> Self {
+        Self {
+            entries: vec![],
+            shared_data,
+            shared_tick,
+            sinks: vec![],
+            never_pause: false,
+        }
+    }
+
+    /// Keeps every collector running even when no pane shows its data.
+    pub fn never_pause(&mut self) {
+        self.never_pause = true;
+    }
+
+    pub fn add_sink(&mut self, sink: Box<dyn Sink>) {
+        self.sinks.push(sink);
+    }
+
+    pub fn register(&mut self, collector: Box<dyn Collector>) {
+        self.entries.push(Entry {
+            interval: collector.default_interval(),
+            collector,
+            next_run: Instant::now(),
+            paused: false,
+        });
+    }
+
+    pub fn set_all_intervals(&mut self, interval: Duration) {
+        for entry in &mut self.entries {
+            entry.interval = interval;
+        }
+    }
+
+    /// Returns false if no collector has that name.
+    pub fn set_interval(&mut self, name: &str, interval: Duration) -> bool {
+        match self.entries.iter_mut().find(|e| e.collector.name() == name) {
+            Some(entry) => {
+                entry.interval = interval;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.entries.iter().map(|e| e.collector.name()).collect()
+    }
+
+    pub fn spawn(self) -> SchedulerHandle {
+        let intervals = self
+            .entries
+            .iter()
+            .map(|e| (e.collector.name(), e.interval))
+            .collect();
+        let (sender, receiver) = mpsc::channel();
+        let thread = thread::spawn(move || {
+            let mut scheduler = self;
+            while let Some(timeout) = scheduler.run_due() {
+                match receiver.recv_timeout(timeout) {
+                    Ok(SchedulerCommand::Shutdown) | Err(RecvTimeoutError::Disconnected) => break,
+                    Ok(command) => scheduler.handle(command),
+                    Err(RecvTimeoutError::Timeout) => {}
+                }
+            }
+        });
+
+        SchedulerHandle {
+            sender,
+            thread: Some(thread),
+            intervals,
+        }
+    }
+
+    /// Runs the collectors on the calling thread until a sink stops them or
+    /// the last one fails.
+    pub fn run(mut self) {
+        while let Some(timeout) = self.run_due() {
+            if self.sinks.is_empty() {
+                break;
+            }
+            thread::sleep(timeout);
+        }
+    }
+
+    /// Runs the collectors that are due and returns how long until the next
+    /// one is, or `None` once a sink asked to stop.
+    fn run_due(&mut self) -> Option<Duration> {
+        let mut collected = vec![];
+        for entry in self.entries.iter_mut().filter(|e| !e.paused) {
+            if entry.next_run <= Instant::now() {
+                let result = entry.collector.collect(&self.shared_data);
+                {
+                    let mut data = self.shared_data.lock().unwrap_or_else(PoisonError::into_inner);
+                    let health = data.health.entry(entry.collector.name().to_string()).or_default();
+                    health.interval = entry.interval;
+                    health.record(&result, SystemTime::now());
+                    data.tick += 1;
+                }
+                self.shared_tick.fetch_add(1, Ordering::SeqCst);
+                entry.next_run = Instant::now() + entry.interval;
+                collected.push(entry.collector.name());
+            }
+        }
+
+        if !collected.is_empty() && !self.sinks.is_empty() {
+            let data = self.shared_data.lock().unwrap_or_else(PoisonError::into_inner);
+            let mut stop = false;
+            // Reported like a failing collector, so the GUI shows it.
+            let mut stopped = vec![];
+            self.sinks.retain_mut(|sink| match sink.write(&collected, &data) {
+                Ok(flow) => {
+                    stop |= flow.is_break();
+                    true
+                }
+                Err(error) => {
+                    eprintln!("error: {} stopped: {}", sink.name(), error);
+                    stopped.push((sink.name(), error.to_string()));
+                    false
+                }
+            });
+            drop(data);
+            if !stopped.is_empty() {
+                let mut data = self.shared_data.lock().unwrap_or_else(PoisonError::into_inner);
+                for (name, error) in stopped {
+                    let health = data.health.entry(name.to_string()).or_default();
+                    health.last_error = Some(error);
+                    health.consecutive_failures += 1;
+                }
+            }
+            if stop {
+                return None;
+            }
+        }
+
+        let now = Instant::now();
+        let timeout = self
+            .entries
+            .iter()
+            .filter(|e| !e.paused)
+            .map(|e| e.next_run.saturating_duration_since(now))
+            .min()
+            .unwrap_or(IDLE_WAIT);
+        Some(timeout)
+    }
+
+    fn handle(&mut self, command: SchedulerCommand) {
+        match command {
+            SchedulerCommand::SetInterval(name, interval) => {
+                if let Some(entry) = self.entries.iter_mut().find(|e| e.collector.name() == name) {
+                    // Shortening the interval should take effect right away.
+                    entry.next_run = entry.next_run.min(Instant::now() + interval);
+                    entry.interval = interval;
+                }
+            }
+            SchedulerCommand::SetActive(_) if self.never_pause => {}
+            SchedulerCommand::SetActive(names) => {
+                let mut data = self.shared_data.lock().unwrap_or_else(PoisonError::into_inner);
+                for entry in &mut self.entries {
+                    let paused = !names.contains(&entry.collector.name());
+                    if entry.paused && !paused {
+                        entry.collector.resume();
+                        entry.next_run = Instant::now();
+                    }
+                    entry.paused = paused;
+                    data.health.entry(entry.collector.name().to_string()).or_default().paused = paused;
+                }
+            }
+            SchedulerCommand::Shutdown => {}
+        }
+    }
+}
+
+/// Controls a running scheduler thread.
+pub struct SchedulerHandle {
+    sender: Sender<SchedulerCommand>,
+    thread: Option<JoinHandle<()>>,
+    intervals: HashMap<&'static str, Duration>,
+}
+
+impl SchedulerHandle {
+    pub fn interval(&self, name: &str) -> Option<Duration> {
+        self.intervals.get(name).copied()
+    }
+
+    pub fn set_interval(&mut self, name: &'static str, interval: Duration) {
+        self.intervals.insert(name, interval);
+        let _ = self.sender.send(SchedulerCommand::SetInterval(name, interval));
+    }
+
+    pub fn set_active(&self, names: Vec<&'static str>) {
+        let _ = self.sender.send(SchedulerCommand::SetActive(names));
+    }
+
+    /// Stops the scheduler, waiting for a collection in progress to finish.
+    pub fn shutdown(&mut self) {
+        let _ = self.sender.send(SchedulerCommand::Shutdown);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for SchedulerHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collectors::CollectorError;
+    use std::sync::atomic::AtomicUsize;
+
+    #[derive(Default)]
+    struct Counts {
+        runs: AtomicUsize,
+        resumes: AtomicUsize,
+    }
+
+    /// Counts its passes and resumes.
+    struct Counter {
+        name: &'static str,
+        counts: Arc<Counts>,
+    }
+
+    impl Collector for Counter {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn default_interval(&self) -> Duration {
+            Duration::ZERO
+        }
+
+        fn collect(&mut self, _: &Arc<Mutex<CollectedData>>) -> Result<(), CollectorError> {
+            self.counts.runs.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        fn resume(&mut self) {
+            self.counts.resumes.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    /// Keeps what each pass collected, failing or stopping when told to.
+    struct Log {
+        passes: Arc<Mutex<Vec<Vec<&'static str>>>>,
+        result: fn() -> io::Result<ControlFlow<()>>,
+    }
+
+    impl Sink for Log {
+        fn name(&self) -> &'static str {
+            "log"
+        }
+
+        fn write(&mut self, collected: &[&'static str], _: &CollectedData) -> io::Result<ControlFlow<()>> {
+            self.passes.lock().unwrap().push(collected.to_vec());
+            (self.result)()
+        }
+    }
+
+    fn scheduler(names: &[&'static str]) -> (Scheduler, HashMap<&'static str, Arc<Counts>>) {
+        let mut scheduler = Scheduler::new(Arc::new(Mutex::new(CollectedData::default())), Arc::new(AtomicU64::new(0)));
+        let mut counters = HashMap::new();
+        for &name in names {
+            let counts = Arc::new(Counts::default());
+            scheduler.register(Box::new(Counter { name, counts: Arc::clone(&counts) }));
+            counters.insert(name, counts);
+        }
+        (scheduler, counters)
+    }
+
+    fn runs(counters: &HashMap<&'static str, Arc<Counts>>, name: &str) -> usize {
+        counters[name].runs.load(Ordering::SeqCst)
+    }
+
+    #[test]
+    fn runs_each_collector_at_its_own_interval() {
+        let (mut scheduler, counters) = scheduler(&["fast", "slow"]);
+        assert!(scheduler.set_interval("slow", Duration::from_secs(3600)));
+        assert!(!scheduler.set_interval("missing", Duration::ZERO));
+
+        scheduler.run_due();
+        let timeout = scheduler.run_due().unwrap();
+        assert_eq!(runs(&counters, "fast"), 2);
+        assert_eq!(runs(&counters, "slow"), 1);
+        assert_eq!(timeout, Duration::ZERO);
+        assert_eq!(scheduler.shared_data.lock().unwrap().health["slow"].interval, Duration::from_secs(3600));
+    }
+
+    #[test]
+    fn shortening_an_interval_takes_effect_right_away() {
+        let (mut scheduler, counters) = scheduler(&["cpu"]);
+        scheduler.set_all_intervals(Duration::from_secs(3600));
+        scheduler.run_due();
+        assert!(scheduler.run_due().unwrap() > Duration::from_secs(3500));
+
+        scheduler.handle(SchedulerCommand::SetInterval("cpu", Duration::ZERO));
+        scheduler.run_due();
+        assert_eq!(runs(&counters, "cpu"), 2);
+    }
+
+    #[test]
+    fn paused_collectors_resume_with_a_pass_right_away() {
+        let (mut scheduler, counters) = scheduler(&["cpu", "memory"]);
+        scheduler.set_all_intervals(Duration::from_secs(3600));
+        scheduler.handle(SchedulerCommand::SetActive(vec!["cpu"]));
+        scheduler.run_due();
+        assert_eq!((runs(&counters, "cpu"), runs(&counters, "memory")), (1, 0));
+        assert!(scheduler.shared_data.lock().unwrap().health["memory"].paused);
+
+        scheduler.handle(SchedulerCommand::SetActive(vec!["cpu", "memory"]));
+        scheduler.run_due();
+        assert_eq!((runs(&counters, "cpu"), runs(&counters, "memory")), (1, 1));
+        assert_eq!(counters["memory"].resumes.load(Ordering::SeqCst), 1);
+        assert_eq!(counters["cpu"].resumes.load(Ordering::SeqCst), 0);
+        assert!(!scheduler.shared_data.lock().unwrap().health["memory"].paused);
+
+        // Nothing left to run waits for a command instead.
+        scheduler.handle(SchedulerCommand::SetActive(vec![]));
+        assert_eq!(scheduler.run_due(), Some(IDLE_WAIT));
+    }
+
+    #[test]
+    fn never_pause_ignores_set_active() {
+        let (mut scheduler, counters) = scheduler(&["cpu"]);
+        scheduler.never_pause();
+        scheduler.handle(SchedulerCommand::SetActive(vec![]));
+        scheduler.run_due();
+        assert_eq!(runs(&counters, "cpu"), 1);
+    }
+
+    #[test]
+    fn sinks_get_the_collectors_of_each_pass_and_can_stop_it() {
+        let (mut scheduler, _) = scheduler(&["cpu"]);
+        let passes = Arc::new(Mutex::new(vec![]));
+        scheduler.add_sink(Box::new(Log { passes: Arc::clone(&passes), result: || Ok(ControlFlow::Break(())) }));
+
+        assert_eq!(scheduler.run_due(), None);
+        assert_eq!(*passes.lock().unwrap(), [vec!["cpu"]]);
+    }
+
+    #[test]
+    fn reports_a_failing_sink_in_the_health() {
+        let (mut scheduler, _) = scheduler(&["cpu"]);
+        let passes = Arc::new(Mutex::new(vec![]));
+        scheduler.add_sink(Box::new(Log { passes: Arc::clone(&passes), result: || Err(io::Error::other("disk full")) }));
+
+        assert!(scheduler.run_due().is_some());
+        assert!(scheduler.run_due().is_some());
+        assert!(scheduler.sinks.is_empty());
+        assert_eq!(passes.lock().unwrap().len(), 1);
+        let data = scheduler.shared_data.lock().unwrap();
+        assert_eq!(data.health["log"].last_error.as_deref(), Some("disk full"));
+        assert!(data.health["log"].is_failing());
+    }
+}